@@ -235,6 +235,27 @@ mod client_interaction_tests {
         );
     }
 
+    /// specialisation of [`generic_full_file_request`], the file is requested by its logical
+    /// name, as returned by the list requests
+    #[test]
+    #[ignore = "computationally expensive"]
+    fn test_full_text_file_request_logical_name() {
+        let (_dcmds, devents, stctrl, _stevents, smctrl, _smevents, cctrl, cevents) =
+            instanciate_testing_topology();
+        generic_full_file_request(
+            devents,
+            stctrl,
+            smctrl,
+            cevents,
+            cctrl,
+            "file.html".to_owned(),
+            |r: TextMediaResponse| {
+                assert!(r.get_media_files().is_empty());
+                assert!(r.get_html_file().1 == read("./public/file.html").unwrap());
+            },
+        );
+    }
+
     /// specialisation of [generic_full_file_request]
     #[test]
     #[ignore = "computationally expensive"]
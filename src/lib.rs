@@ -1,6 +1,8 @@
 /*!
  * # `CppEnjoyers` implementation of [`TextServer`] and [`MediaServer`]
  *
+ * The [`TextMediaServer`] answers the requests of both, for networks with a single server.
+ *
  * Supports compression of packets (if requested by client).
  * Available compressions are:
  * - Huffman
@@ -12,7 +14,7 @@
  *
  * The [`GenericServer`] uses ETX estimation to decide the best routing paths.
  *
- * The estimator uses an exponentially weighted moving average (EWMA),
+ * The default estimator uses an exponentially weighted moving average (EWMA),
  * the formula is as follows:
  * ``` text
  *     ETX(n) = p(n) * alpha + ETX(n - 1) * beta
@@ -23,6 +25,28 @@
  * - p(n) is the estimated ETX at time n, calculated from the last k samples (k is a predefined constant)
  * - alpha and beta are parameters that decide how fast the ETX adapts to change
  *
+ * Other strategies can be selected for each server with
+ * [`GenericServerBuilder::pdr_estimator`], e.g. the Beta-Bernoulli posterior mean
+ * of [`servers::BetaEstimator`] or the sliding window ratio of [`servers::WindowEstimator`],
+ * or implemented with the [`servers::PdrEstimator`] trait.
+ *
+ * The fragments of a response are paced by a congestion window: at most a window of
+ * fragments waits to be acknowledged, the window grows additively with the acknowledgements
+ * and halves after every dropped fragment (see [`GenericServerBuilder::initial_send_window`]).
+ *
+ * The network is discovered with a flood, started again whenever a neighbor is added or
+ * removed or a drone reports an `ErrorInRouting`. The floods can be debounced and rate
+ * limited ([`GenericServerBuilder::flood_debounce`], [`GenericServerBuilder::min_flood_interval`]),
+ * repeated in background ([`GenericServerBuilder::refresh_interval`]) or avoided when the
+ * removal of a node leaves every known client and server reachable
+ * ([`GenericServerBuilder::targeted_repair`]).
+ * The responses of a flood are collected until none arrives for a quiet period or a
+ * timeout expires, only then the packets waiting for a route are sent again, in one batch.
+ *
+ * The learned topology and pdr estimations can be saved to a file with
+ * [`GenericServer::save_routing_state`] or periodically with
+ * [`GenericServerBuilder::routing_state_file`], which also warm-starts a restarted server.
+ *
  * # Simulation controller interaction
  * The [`GenericServer`] can accept different command by the scl:
  * - `AddSender(ID, Channel)`: adds a new direct neighbor to the server
@@ -33,6 +57,41 @@
  * - `PacketSent(Packet)`: logs that a packet has been sent over the network
  * - `Shortcut(Packet)`: sends a packet that generated an error but cannot be dropped
  *
+ * If the [`GenericServerBuilder`] is given a channel for them, the [`GenericServer`]
+ * also sends [`servers::extensions::ExtendedEvent`]s:
+ * - `FragmentGivenUp`: a fragment was never acknowledged and has been dropped
+ * - `Errors(ErrorCounters)`: the counters of the erroneous requests changed
+ * - `Topology(TopologySnapshot)`: the network known by the server, with the pdr and ETX
+ *   of every drone and the weight of every edge
+ * - `Metrics(ServerMetrics)`: the counters of the packets, requests and floods handled by
+ *   the server, requested with [`servers::extensions::ExtendedCommand::Metrics`]
+ *
+ * The snapshot is requested with [`servers::extensions::ExtendedCommand::TopologySnapshot`],
+ * received on the channel given with [`GenericServerBuilder::extended_commands`], and can
+ * be exported with [`servers::TopologySnapshot::to_dot`] or [`servers::TopologySnapshot::to_json`].
+ *
+ * The [`GenericServer`] stops, returning from `run`, after
+ * [`servers::extensions::ExtendedCommand::Shutdown`] or when the controller disconnects, once
+ * its in-flight responses are acknowledged or given up (see
+ * [`GenericServerBuilder::shutdown_timeout`]). It stops immediately after
+ * [`servers::extensions::ExtendedCommand::Crash`] or when its packet channel disconnects.
+ *
+ * The requests are answered by the service, and the responses are read, serialized and compressed,
+ * by a pool of worker threads (see [`GenericServerBuilder::workers`]), so that the [`GenericServer`]
+ * keeps handling packets meanwhile. The responses are still sent in the order in which the requests
+ * were completed, and the workers are joined when `run` returns.
+ * The uncompressed responses containing large files are streamed: their fragments are read
+ * from the file only when the window of the response has room for them (see
+ * [`GenericServerBuilder::streaming_threshold`]).
+ *
+ * # Request services
+ * The content of the responses is decided by a [`servers::RequestService`], the
+ * [`GenericServer`] only takes care of the transport, routing and compression.
+ * The [`TextServer`], the [`MediaServer`] and the [`TextMediaServer`] use by default a
+ * [`servers::TextService`], a [`servers::MediaService`] and a [`servers::TextMediaService`],
+ * other services can be registered with
+ * [`GenericServerBuilder::service`].
+ *
  * # High level protocol
  *
  * The protocol between Client and Server is defined as follows:
@@ -40,13 +99,37 @@
  * - The server answers with a response containing its type
  * - After that the Client can query the Server for a list of available file or for a specific file
  * - The Server answer with the requested information or with an error in case of unknown/unsupported requests
+ * - The requests of files outside of the content root of the Server are answered as if the files did not
+ *   exist, and counted apart in [`servers::extensions::ErrorCounters::forbidden`]
  *
  * Every request is associated with a request id (16 bits) that will be part of the response id used by the server.
  * In this way the Client can easily recognise the request associated with the response and handle it accordingly.
  *
+ * A Client can request only a range of bytes of a text file or a media, e.g. to resume an interrupted
+ * download or to fetch a large file in parallel, by requesting a [`servers::RangedName`] instead of the
+ * name of the file (see [`servers::ByteRange::request_name`]). The content of the response is then a serialized
+ * [`servers::RangeResponse`], carrying the offset of the received bytes and the size of the whole file.
+ * A response carries at most [`servers::MAX_RANGE_LEN`] bytes, the rest of a larger range is requested again.
+ * Malformed ranges and ranges starting after the end of the file are answered as invalid requests.
+ *
+ * The messages of the protocol have no field for the ranges, so both are in-band conventions:
+ * - the range is appended to the name of the file after a `'\0'`, which the names of the files cannot
+ *   contain. A server that doesn't support ranges answers such a request as a missing file
+ * - the [`servers::RangeResponse`] is sent as the body of an ordinary text or media response, so a Client
+ *   can't tell a partial response from a whole file by its type: only the Client that requested the range
+ *   knows that the body must be deserialized
+ *
  * Every request/response is serialized and fragmented into binary before being sent as packets in the netowork.
  * Optionally the Client can specify in the request a compression method to use on the serialized data: this
  * can help reduce the network bottleneck due to less packets being sent.
+ *
+ * A server built with [`servers::CompressionPolicy::Auto`] doesn't compress the files that don't compress
+ * (e.g. images already compressed), even if the Client requested a compression. Since the Client cannot
+ * read the compression reported by the response before decompressing it, every response of such a server
+ * starts with a byte identifying the compression of the rest of the response (see
+ * [`servers::compression_tag`]), and the Client decodes it with [`servers::decode_response`]. The Clients
+ * that decode the responses with the requested compression only must use a server with the default
+ * [`servers::CompressionPolicy::Requested`] policy.
  */
 
 #![allow(dead_code)]
@@ -69,8 +152,12 @@ pub mod servers;
 #[doc(inline)]
 pub use servers::GenericServer;
 #[doc(inline)]
+pub use servers::GenericServerBuilder;
+#[doc(inline)]
 pub use servers::MediaServer;
 #[doc(inline)]
+pub use servers::TextMediaServer;
+#[doc(inline)]
 pub use servers::TextServer;
 // pub use servers::RequestHandler;
 // pub use servers::ServerType
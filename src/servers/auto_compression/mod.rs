@@ -0,0 +1,137 @@
+use common::web_messages::{
+    Compression, MediaResponse, Response, ResponseMessage, Serializable, TextResponse,
+};
+use log::info;
+
+use super::workers::{decompress, encode_bytes};
+
+/// testing module
+#[cfg(test)]
+mod test;
+
+/// size of the smallest file compressed by the [`CompressionPolicy::Auto`] policy, in bytes
+const MIN_COMPRESSED_LEN: usize = 64;
+/// number of bytes of a file used to estimate its entropy
+const ENTROPY_SAMPLE_LEN: usize = 1 << 16;
+/// entropy, in bits per byte, above which a file is considered not compressible
+const MAX_ENTROPY: f64 = 7.5;
+/// signatures of the formats whose content is already compressed
+const COMPRESSED_SIGNATURES: [&[u8]; 8] = [
+    b"\x89PNG",
+    b"\xFF\xD8\xFF",
+    b"GIF8",
+    b"PK\x03\x04",
+    b"\x1F\x8B",
+    b"\x28\xB5\x2F\xFD",
+    b"7z\xBC\xAF",
+    b"OggS",
+];
+
+/// Policy used by the [`super::GenericServer`] to choose the compression of the responses
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionPolicy {
+    /// the responses are compressed with the compression requested by the client
+    #[default]
+    Requested,
+    /// the responses are compressed with the compression requested by the client, unless
+    /// the file they contain doesn't compress: too short, in an already compressed format
+    /// or with high entropy. Then they are not compressed.
+    ///
+    /// Since the compression may differ from the requested one, every response starts
+    /// with a byte identifying the compression of the rest of the response (see
+    /// [`compression_tag`]), the clients decode it with [`decode_response`]
+    Auto,
+}
+
+/// estimates the entropy of the data, in bits per byte.
+/// The counts are far below the precision of a [f64]
+#[allow(clippy::cast_precision_loss)]
+fn entropy(data: &[u8]) -> f64 {
+    let mut counts: [usize; 256] = [0; 256];
+    for &b in data {
+        counts[usize::from(b)] += 1;
+    }
+    let len: f64 = data.len() as f64;
+    counts
+        .iter()
+        .filter(|&&n: &&usize| n > 0)
+        .map(|&n: &usize| {
+            let p: f64 = n as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// checks if the data starts with the signature of an already compressed format
+fn is_precompressed(data: &[u8]) -> bool {
+    COMPRESSED_SIGNATURES
+        .iter()
+        .any(|signature: &&[u8]| data.starts_with(signature))
+}
+
+/// checks if the data is worth compressing: it is not too short, not in an already
+/// compressed format and its entropy is low enough
+pub(super) fn is_compressible(data: &[u8]) -> bool {
+    data.len() >= MIN_COMPRESSED_LEN
+        && !is_precompressed(data)
+        && entropy(&data[..data.len().min(ENTROPY_SAMPLE_LEN)]) <= MAX_ENTROPY
+}
+
+/// byte preceding a response compressed with the given compression by a server with the
+/// [`CompressionPolicy::Auto`] policy: 0 if not compressed, 1 for LZW and 2 for Huffman
+#[must_use]
+pub fn compression_tag(compression: &Compression) -> u8 {
+    match compression {
+        Compression::None => 0,
+        Compression::LZW => 1,
+        Compression::Huffman => 2,
+    }
+}
+
+/// compression identified by the first byte of a response, see [`compression_tag`]
+fn tagged_compression(tag: u8) -> Option<Compression> {
+    match tag {
+        0 => Some(Compression::None),
+        1 => Some(Compression::LZW),
+        2 => Some(Compression::Huffman),
+        _ => None,
+    }
+}
+
+/// serializes and compresses a response following the [`CompressionPolicy::Auto`]
+/// policy, preceded by the tag of the compression used. [None] if any step fails.
+/// Returns also the size of the response before the compression
+pub(super) fn encode_auto(
+    mut resp: ResponseMessage,
+    target_topic: &str,
+) -> Option<(Vec<u8>, usize)> {
+    // only the files are checked, the other responses are short
+    let compressible: bool = match &resp.content {
+        Response::Text(TextResponse::Text(data)) | Response::Media(MediaResponse::Media(data)) => {
+            is_compressible(data)
+        }
+        _ => true,
+    };
+    if !compressible {
+        resp.compression_type = Compression::None;
+    }
+    let (encoded, uncompressed) = encode_bytes(&resp, target_topic)?;
+    info!(target: target_topic, "Compressed response with {:?}", resp.compression_type);
+    let mut tagged: Vec<u8> = Vec::with_capacity(encoded.len() + 1);
+    tagged.push(compression_tag(&resp.compression_type));
+    tagged.extend(encoded);
+    Some((tagged, uncompressed))
+}
+
+/// decodes a response sent by a server with the [`CompressionPolicy::Auto`] policy: the
+/// rest of the data is decompressed with the compression identified by its first byte.
+/// The data can end with the padding of the last fragment. [None] if the data is not
+/// a response compressed as its tag says
+#[must_use]
+pub fn decode_response(data: &[u8]) -> Option<ResponseMessage> {
+    let (&tag, compressed) = data.split_first()?;
+    let compression: Compression = tagged_compression(tag)?;
+    let plain: Vec<u8> = decompress(compressed.to_vec(), &compression).ok()?;
+    let resp: ResponseMessage = ResponseMessage::deserialize(plain).ok()?;
+    (resp.compression_type == compression).then_some(resp)
+}
@@ -0,0 +1,153 @@
+#[cfg(test)]
+mod auto_compression_tests {
+    use std::{env, fs, path::PathBuf};
+
+    use common::web_messages::{Compression, RequestMessage, ResponseMessage};
+    use crossbeam_channel::Receiver;
+    use wg_2024::packet::Packet;
+
+    use crate::servers::{
+        auto_compression::{entropy, is_compressible},
+        compression_tag, decode_response,
+        test_utils::{get_dummy_builder, get_routed_server, request_data},
+        workers::encode_bytes,
+        CompressedBytes, CompressionPolicy, CompressionRatios, GenericServer, TextMedia,
+    };
+
+    /// bytes that look random, not compressible
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state: u32 = 12345;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                state.to_be_bytes()[0]
+            })
+            .collect()
+    }
+
+    /// text that compresses well
+    fn text(len: usize) -> Vec<u8> {
+        b"aaaaaaaabbbbbbbbccccccccdddddddd"
+            .iter()
+            .copied()
+            .cycle()
+            .take(len)
+            .collect()
+    }
+
+    /// tests the heuristics that exclude the files not worth compressing
+    #[test]
+    fn test_is_compressible() {
+        assert!(entropy(&noise(10_000)) > 7.5);
+        assert!(entropy(&text(10_000)) < 3.);
+        assert!(is_compressible(&text(10_000)));
+
+        // too short, random or already compressed files are not compressed
+        assert!(!is_compressible(&text(10)));
+        assert!(!is_compressible(&noise(10_000)));
+        let mut png: Vec<u8> = b"\x89PNG\r\n\x1a\n".to_vec();
+        png.extend(text(10_000));
+        assert!(!is_compressible(&png));
+    }
+
+    /// the data sent by a server with the [`CompressionPolicy::Auto`] policy for a response
+    /// encoded with its compression: the tag of the compression, then the response
+    /// serialized and compressed as with the [`CompressionPolicy::Requested`] policy
+    fn wire_format(resp: &ResponseMessage) -> Vec<u8> {
+        let (compressed, _) = encode_bytes(resp, "test").unwrap();
+        let mut data: Vec<u8> = vec![compression_tag(&resp.compression_type)];
+        data.extend(compressed);
+        data
+    }
+
+    /// requests a file from a server with the [`CompressionPolicy::Auto`] policy, checks
+    /// that the data sent is the given response in the wire format and decodes it
+    fn auto_response(
+        server: &mut GenericServer<TextMedia>,
+        dr: &Receiver<Packet>,
+        request: &RequestMessage,
+        expected: &ResponseMessage,
+    ) {
+        let data: Vec<u8> = request_data(server, dr, request);
+        assert!(data.starts_with(&wire_format(expected)));
+        assert!(decode_response(&data).unwrap() == *expected);
+    }
+
+    /// tests the decoding of the responses of a server with the [`CompressionPolicy::Auto`] policy
+    #[test]
+    fn test_decode_response() {
+        let resp: ResponseMessage =
+            ResponseMessage::new_text_response(0, Compression::Huffman, text(1000));
+        let mut data: Vec<u8> = wire_format(&resp);
+        assert_eq!(data[0], 2);
+        // the padding of the last fragment is ignored
+        data.extend([0; 100]);
+        assert!(decode_response(&data).unwrap() == resp);
+
+        // the tag must identify a compression
+        data[0] = 3;
+        assert!(decode_response(&data).is_none());
+        assert!(decode_response(&[]).is_none());
+    }
+
+    /// tests the responses of a server choosing the compression
+    #[test]
+    fn test_auto_responses() {
+        let dir: PathBuf = env::temp_dir().join(format!("auto_compression_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let page: Vec<u8> = text(5000);
+        fs::write(dir.join("page.html"), &page).unwrap();
+        let mut image: Vec<u8> = b"\x89PNG\r\n\x1a\n".to_vec();
+        image.extend(text(5000));
+        fs::write(dir.join("image.png"), &image).unwrap();
+        let (mut server, dr) = get_routed_server(
+            get_dummy_builder(0)
+                .content_dir(&dir)
+                .media_dir(&dir)
+                .compression_policy(CompressionPolicy::Auto)
+                .workers(0),
+            &[2],
+        );
+
+        // the compressible file is compressed with the requested compression
+        for compression in [Compression::LZW, Compression::Huffman, Compression::None] {
+            let request: RequestMessage =
+                RequestMessage::new_text_request(2, compression.clone(), "page.html".to_string());
+            let expected: ResponseMessage =
+                ResponseMessage::new_text_response(0, compression, page.clone());
+            auto_response(&mut server, &dr, &request, &expected);
+        }
+
+        // the image is already compressed, it is sent uncompressed whatever the request
+        let request: RequestMessage =
+            RequestMessage::new_media_request(2, Compression::Huffman, "image.png".to_string());
+        let expected: ResponseMessage =
+            ResponseMessage::new_media_response(0, Compression::None, image);
+        auto_response(&mut server, &dr, &request, &expected);
+
+        // the other responses are tagged too
+        let request: RequestMessage =
+            RequestMessage::new_text_request(2, Compression::LZW, "missing.html".to_string());
+        let expected: ResponseMessage =
+            ResponseMessage::new_not_found_response(0, Compression::LZW);
+        auto_response(&mut server, &dr, &request, &expected);
+
+        // the ratios are recorded by type of file, even if the files are of the same kind
+        fs::write(dir.join("photo.JPG"), b"\xFF\xD8\xFF\xE0").unwrap();
+        let request: RequestMessage =
+            RequestMessage::new_media_request(2, Compression::LZW, "photo.JPG".to_string());
+        let expected: ResponseMessage =
+            ResponseMessage::new_media_response(0, Compression::None, b"\xFF\xD8\xFF\xE0".to_vec());
+        auto_response(&mut server, &dr, &request, &expected);
+        let ratios: CompressionRatios = server.metrics().compression;
+        assert!(ratios.get("html").ratio().unwrap() < 1.);
+        let png: CompressedBytes = ratios.get("png");
+        assert!(png.uncompressed > 0);
+        // only the tag is added to the uncompressed image
+        assert_eq!(png.uncompressed + 1, png.compressed);
+        assert!(ratios.get("jpg").uncompressed > 0);
+        assert!(ratios.get("gif") == CompressedBytes::default());
+        assert!(ratios.iter().map(|(t, _)| t).eq(["html", "jpg", "png"]));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
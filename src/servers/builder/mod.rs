@@ -0,0 +1,621 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    marker::PhantomData,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::slc_commands::{ServerCommand, ServerEvent};
+use crossbeam_channel::{Receiver, Sender};
+use log::warn;
+use wg_2024::{network::NodeId, packet::Packet};
+
+use super::{
+    auto_compression::CompressionPolicy,
+    content::ContentRoot,
+    default_estimator,
+    extensions::{ErrorCounters, ExtendedCommand, ExtendedEvent},
+    flow_control::FlowConfig,
+    networking::FloodConfig,
+    reassembly::ReassemblyConfig,
+    requests_handling::{ContentCache, DEFAULT_CACHE_CAPACITY},
+    retransmission::RetransmissionConfig,
+    routing::{PdrEstimator, RoutingTable, StateFile, DEFAULT_HALF_LIFE, DEFAULT_MAX_PATHS},
+    services::RequestService,
+    shutdown::{Lifecycle, DEFAULT_SHUTDOWN_TIMEOUT},
+    streaming::DEFAULT_STREAMING_THRESHOLD,
+    workers::{WorkerPool, DEFAULT_WORKERS},
+    GenericServer, ServerMetrics, ServerType, DEFAULT_ALPHA, DEFAULT_WINDOW_SZ, INITIAL_PDR,
+    MEDIA_PATH,
+};
+
+/// testing module
+#[cfg(test)]
+mod test;
+
+/// Builder used to configure a [`GenericServer`] before creating it.
+///
+/// Every parameter that is not explicitly set keeps the same default used by
+/// [`common::Server::new`]:
+/// - the requests are answered by [`ServerType::default_service`]
+/// - the content directory is [`ServerType::DEFAULT_CONTENT_PATH`]
+/// - the media directory of the [`super::TextMediaServer`] is `./media/`
+/// - list requests only return the files at the top level of the content directory
+/// - the log topic is `Server[<id>]`
+/// - the content cache holds up to 32 compressed responses
+/// - the responses are serialized and compressed by 2 worker threads
+/// - the uncompressed responses containing files of at least 1MiB are streamed
+/// - unacknowledged fragments are sent again after 500ms, the timeout doubles at
+///   every retransmission up to 8s and a fragment is given up after 5 retransmissions
+/// - a request is dropped if none of its fragments is received in 30s, each client
+///   can have at most 16 requests being reassembled, of at most 64 fragments each
+/// - no [`ExtendedEvent`] is sent to the controller and no [`ExtendedCommand`] is received
+/// - the pdr of the drones is estimated by an [`super::EwmaEstimator`] with
+///   a window of 12 samples, alpha = 0.35 and an initial PDR of 0.5
+/// - the pdr estimations decay toward the initial PDR with a half-life of 30s
+/// - the best route is always chosen, without exploring other routes
+/// - all the fragments of a response are sent over the same route
+/// - at most 8 fragments of a response wait to be acknowledged, the window grows with
+///   every window of acknowledged fragments up to 64 and halves after a dropped fragment
+/// - the network is flooded as soon as its topology changes, without background refreshes
+/// - a flood is complete when no response is received for 100ms, or after 1s
+/// - the routing state is not saved, every server starts without knowing the network
+/// - a graceful shutdown waits at most 5s for the in-flight responses
+///
+/// ```no_run
+/// # use std::{collections::HashMap, time::Duration};
+/// # use ap2024_unitn_cppenjoyers_webservers::{GenericServerBuilder, TextServer};
+/// # let (controller_send, _) = crossbeam_channel::unbounded();
+/// # let (_, controller_recv) = crossbeam_channel::unbounded();
+/// # let (_, packet_recv) = crossbeam_channel::unbounded();
+/// let server: TextServer =
+///     GenericServerBuilder::new(1, controller_send, controller_recv, packet_recv, HashMap::new())
+///         .content_dir("./other_public/")
+///         .recursive_listing(true)
+///         .target_topic("TextServer[1]")
+///         .cache_capacity(64)
+///         .retransmission_timeout(Duration::from_millis(200))
+///         .max_request_fragments(16)
+///         .window_size(20)
+///         .alpha(0.5)
+///         .initial_pdr(0.8)
+///         .build();
+/// ```
+pub struct GenericServerBuilder<T: ServerType> {
+    /// id of the node
+    id: NodeId,
+    /// channel to communicate [`ServerEvent`]s to the controller
+    controller_send: Sender<ServerEvent>,
+    /// channel to receive [`ServerCommand`]s from the controller
+    controller_recv: Receiver<ServerCommand>,
+    /// channel to receive [Packet]s from the drones
+    packet_recv: Receiver<Packet>,
+    /// channels to send [Packet]s to the direct neighbors
+    packet_send: HashMap<NodeId, Sender<Packet>>,
+    /// channel to communicate [`ExtendedEvent`]s to the controller, if any
+    extended_send: Option<Sender<ExtendedEvent>>,
+    /// channel to receive [`ExtendedCommand`]s from the controller
+    extended_recv: Receiver<ExtendedCommand>,
+    /// service answering the requests, if [None] the default one of the [`ServerType`] is used
+    service: Option<Box<dyn RequestService>>,
+    /// directory containing the served files
+    content_dir: PathBuf,
+    /// directory containing the served media, used only by the servers
+    /// answering both text and media requests
+    media_dir: PathBuf,
+    /// whether or not list requests include the files in subdirectories
+    recursive_listing: bool,
+    /// target topic of the server, used in logs
+    target_topic: String,
+    /// maximum number of responses kept in the content cache
+    cache_capacity: usize,
+    /// number of threads encoding the responses
+    workers: usize,
+    /// size of the files whose uncompressed responses are streamed, if any
+    streaming_threshold: Option<u64>,
+    /// policy used to choose the compression of the responses
+    compression_policy: CompressionPolicy,
+    /// retransmission timer configuration
+    retransmission: RetransmissionConfig,
+    /// limits on the requests being reassembled
+    reassembly: ReassemblyConfig,
+    /// estimator of the pdr of the drones, if [None] the default one is used
+    pdr_estimator: Option<Box<dyn PdrEstimator>>,
+    /// window size of the ETX estimator
+    window_sz: u32,
+    /// alpha constant of the EWMA
+    alpha: f64,
+    /// pdr assigned to newly discovered drones
+    initial_pdr: f64,
+    /// half-life of the decay of the pdr estimations, [None] if they don't decay
+    pdr_half_life: Option<Duration>,
+    /// probability of choosing a non-optimal route
+    exploration: f64,
+    /// maximum number of disjoint routes used to send a response
+    max_paths: usize,
+    /// configuration of the floods
+    flooding: FloodConfig,
+    /// file used to warm-start the server and to save its routing state, if any
+    state_file: Option<PathBuf>,
+    /// maximum time waited for the in-flight responses during a graceful shutdown
+    shutdown_timeout: Duration,
+    /// configuration of the windows of the responses
+    flow: FlowConfig,
+    /// marker used to specify the [`GenericServer`]'s type
+    _marker: PhantomData<T>,
+}
+
+impl<T: ServerType> GenericServerBuilder<T> {
+    /// creates a new [`GenericServerBuilder`] from the given network channels,
+    /// with the default configuration
+    #[must_use]
+    pub fn new(
+        id: NodeId,
+        controller_send: Sender<ServerEvent>,
+        controller_recv: Receiver<ServerCommand>,
+        packet_recv: Receiver<Packet>,
+        packet_send: HashMap<NodeId, Sender<Packet>>,
+    ) -> Self {
+        Self {
+            id,
+            controller_send,
+            controller_recv,
+            packet_recv,
+            packet_send,
+            extended_send: None,
+            extended_recv: crossbeam_channel::never(),
+            service: None,
+            content_dir: PathBuf::from(T::DEFAULT_CONTENT_PATH),
+            media_dir: PathBuf::from(MEDIA_PATH),
+            recursive_listing: false,
+            target_topic: format!("Server[{id}]"),
+            cache_capacity: DEFAULT_CACHE_CAPACITY,
+            workers: DEFAULT_WORKERS,
+            streaming_threshold: Some(DEFAULT_STREAMING_THRESHOLD),
+            compression_policy: CompressionPolicy::default(),
+            retransmission: RetransmissionConfig::default(),
+            reassembly: ReassemblyConfig::default(),
+            pdr_estimator: None,
+            window_sz: DEFAULT_WINDOW_SZ,
+            alpha: DEFAULT_ALPHA,
+            initial_pdr: INITIAL_PDR,
+            pdr_half_life: Some(DEFAULT_HALF_LIFE),
+            exploration: 0.,
+            max_paths: DEFAULT_MAX_PATHS,
+            flooding: FloodConfig::default(),
+            state_file: None,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            flow: FlowConfig::default(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// sets the [`RequestService`] answering the requests, replacing the default
+    /// one of the [`ServerType`]. The content directories and the listing options
+    /// are used only by the default service
+    #[must_use]
+    pub fn service(mut self, service: impl RequestService + 'static) -> Self {
+        self.service = Some(Box::new(service));
+        self
+    }
+
+    /// sets the directory containing the files served by the [`GenericServer`]
+    #[must_use]
+    pub fn content_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.content_dir = dir.into();
+        self
+    }
+
+    /// sets the directory containing the media served by a [`super::TextMediaServer`],
+    /// the other types of [`GenericServer`] only serve the content directory
+    #[must_use]
+    pub fn media_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.media_dir = dir.into();
+        self
+    }
+
+    /// sets whether or not list requests include the files contained in the subdirectories
+    /// of the content directory, named as `<subdir>/<file>`
+    #[must_use]
+    pub fn recursive_listing(mut self, recursive: bool) -> Self {
+        self.recursive_listing = recursive;
+        self
+    }
+
+    /// sets the target topic used in the logs of the [`GenericServer`]
+    #[must_use]
+    pub fn target_topic(mut self, topic: impl Into<String>) -> Self {
+        self.target_topic = topic.into();
+        self
+    }
+
+    /// sets the maximum number of compressed responses kept in the content cache,
+    /// 0 disables the cache
+    #[must_use]
+    pub fn cache_capacity(mut self, capacity: usize) -> Self {
+        self.cache_capacity = capacity;
+        self
+    }
+
+    /// sets the number of threads that answer the requests with the [`RequestService`] and
+    /// read, serialize and compress the responses while the server keeps handling packets,
+    /// 0 does the work on the main loop
+    #[must_use]
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    /// sets the size, in bytes, of the files whose uncompressed responses are streamed:
+    /// their fragments are read from the file only when they are sent, instead of
+    /// loading the whole file. [None] never streams the responses
+    #[must_use]
+    pub fn streaming_threshold(mut self, threshold: Option<u64>) -> Self {
+        self.streaming_threshold = threshold;
+        self
+    }
+
+    /// sets the policy used to choose the compression of the responses, by default the
+    /// compression requested by the client is used. See [`CompressionPolicy::Auto`] for
+    /// how the responses are encoded when the server chooses the compression
+    #[must_use]
+    pub fn compression_policy(mut self, policy: CompressionPolicy) -> Self {
+        self.compression_policy = policy;
+        self
+    }
+
+    /// sets the channel used to send [`ExtendedEvent`]s to the controller
+    #[must_use]
+    pub fn extended_events(mut self, extended_send: Sender<ExtendedEvent>) -> Self {
+        self.extended_send = Some(extended_send);
+        self
+    }
+
+    /// sets the channel used to receive [`ExtendedCommand`]s from the controller, the
+    /// answers are sent on the channel set with [`GenericServerBuilder::extended_events`]
+    #[must_use]
+    pub fn extended_commands(mut self, extended_recv: Receiver<ExtendedCommand>) -> Self {
+        self.extended_recv = extended_recv;
+        self
+    }
+
+    /// sets the time after which an unacknowledged fragment is sent again,
+    /// the timeout doubles at every retransmission of the same fragment
+    ///
+    /// # Panics
+    /// Panics if `timeout` is zero
+    #[must_use]
+    pub fn retransmission_timeout(mut self, timeout: Duration) -> Self {
+        assert!(
+            !timeout.is_zero(),
+            "the retransmission timeout must be positive"
+        );
+        self.retransmission.timeout = timeout;
+        self
+    }
+
+    /// sets the upper bound of the retransmission timeout
+    ///
+    /// # Panics
+    /// Panics if `timeout` is zero
+    #[must_use]
+    pub fn max_retransmission_timeout(mut self, timeout: Duration) -> Self {
+        assert!(
+            !timeout.is_zero(),
+            "the retransmission timeout must be positive"
+        );
+        self.retransmission.max_timeout = timeout;
+        self
+    }
+
+    /// sets the number of retransmissions after which an unacknowledged fragment is given up
+    #[must_use]
+    pub fn max_retransmissions(mut self, attempts: u32) -> Self {
+        self.retransmission.max_attempts = attempts;
+        self
+    }
+
+    /// sets the time after which a request that is not receiving fragments is dropped
+    ///
+    /// # Panics
+    /// Panics if `timeout` is zero
+    #[must_use]
+    pub fn reassembly_timeout(mut self, timeout: Duration) -> Self {
+        assert!(
+            !timeout.is_zero(),
+            "the reassembly timeout must be positive"
+        );
+        self.reassembly.timeout = timeout;
+        self
+    }
+
+    /// sets the maximum number of requests being reassembled for each client,
+    /// further requests are answered with an error
+    #[must_use]
+    pub fn max_partial_requests(mut self, max_partial: usize) -> Self {
+        self.reassembly.max_partial = max_partial;
+        self
+    }
+
+    /// sets the maximum number of fragments of a request,
+    /// larger requests are answered with an error
+    #[must_use]
+    pub fn max_request_fragments(mut self, max_fragments: u64) -> Self {
+        self.reassembly.max_fragments = max_fragments;
+        self
+    }
+
+    /// sets the [`PdrEstimator`] used to choose the routes, replacing the default one.
+    /// The window size, alpha and the initial pdr are used only by the default estimator
+    #[must_use]
+    pub fn pdr_estimator(mut self, estimator: impl PdrEstimator + 'static) -> Self {
+        self.pdr_estimator = Some(Box::new(estimator));
+        self
+    }
+
+    /// sets the number of samples collected before each ETX update
+    ///
+    /// # Panics
+    /// Panics if `window_sz` is 0
+    #[must_use]
+    pub fn window_size(mut self, window_sz: u32) -> Self {
+        assert!(window_sz > 0, "the window size must be positive");
+        self.window_sz = window_sz;
+        self
+    }
+
+    /// sets the alpha constant of the EWMA, i.e. how fast the ETX adapts to change
+    ///
+    /// # Panics
+    /// Panics if `alpha` is not in (0, 1]
+    #[must_use]
+    pub fn alpha(mut self, alpha: f64) -> Self {
+        assert!(alpha > 0. && alpha <= 1., "alpha must be in (0, 1]");
+        self.alpha = alpha;
+        self
+    }
+
+    /// sets the pdr assigned to newly discovered drones
+    ///
+    /// # Panics
+    /// Panics if `initial_pdr` is not in (0, 1]
+    #[must_use]
+    pub fn initial_pdr(mut self, initial_pdr: f64) -> Self {
+        assert!(
+            initial_pdr > 0. && initial_pdr <= 1.,
+            "the initial pdr must be in (0, 1]"
+        );
+        self.initial_pdr = initial_pdr;
+        self
+    }
+
+    /// sets the half-life of the decay of the pdr estimations toward the initial pdr,
+    /// so that drones avoided because of old losses are eventually used again.
+    /// [None] disables the decay
+    ///
+    /// # Panics
+    /// Panics if `half_life` is zero
+    #[must_use]
+    pub fn pdr_half_life(mut self, half_life: Option<Duration>) -> Self {
+        assert!(
+            half_life != Some(Duration::ZERO),
+            "the half-life must be positive"
+        );
+        self.pdr_half_life = half_life;
+        self
+    }
+
+    /// sets the probability of sending a response over a non-optimal route,
+    /// which goes through a random drone, in order to sample drones that are not used
+    ///
+    /// # Panics
+    /// Panics if `exploration` is not in [0, 1]
+    #[must_use]
+    pub fn exploration(mut self, exploration: f64) -> Self {
+        assert!(
+            (0. ..=1.).contains(&exploration),
+            "the exploration probability must be in [0, 1]"
+        );
+        self.exploration = exploration;
+        self
+    }
+
+    /// sets the maximum number of routes used to send the fragments of a response.
+    /// The routes don't share any drone and the fragments are spread over them weighted
+    /// by their ETX, so that the failure of a drone doesn't stall the whole response
+    ///
+    /// # Panics
+    /// Panics if `max_paths` is 0
+    #[must_use]
+    pub fn max_paths(mut self, max_paths: usize) -> Self {
+        assert!(max_paths > 0, "the number of paths must be positive");
+        self.max_paths = max_paths;
+        self
+    }
+
+    /// sets the number of fragments of a response sent before receiving any acknowledgement,
+    /// the window then grows by one fragment for every window of acknowledged fragments
+    /// and halves after every dropped fragment
+    ///
+    /// # Panics
+    /// Panics if `window` is 0
+    #[must_use]
+    pub fn initial_send_window(mut self, window: u32) -> Self {
+        assert!(window > 0, "the send window must be positive");
+        self.flow.initial_window = window;
+        self
+    }
+
+    /// sets the upper bound of the window of the responses
+    ///
+    /// # Panics
+    /// Panics if `window` is 0
+    #[must_use]
+    pub fn max_send_window(mut self, window: u32) -> Self {
+        assert!(window > 0, "the send window must be positive");
+        self.flow.max_window = window;
+        self
+    }
+
+    /// sets the minimum time between the start of two floods, the floods requested
+    /// earlier are delayed
+    #[must_use]
+    pub fn min_flood_interval(mut self, interval: Duration) -> Self {
+        self.flooding.min_interval = interval;
+        self
+    }
+
+    /// sets the time waited before starting a requested flood, so that the changes
+    /// of the topology happening within this time are handled by a single flood
+    #[must_use]
+    pub fn flood_debounce(mut self, debounce: Duration) -> Self {
+        self.flooding.debounce = debounce;
+        self
+    }
+
+    /// sets the time after which the network is flooded again even if no change
+    /// was detected. [None] disables the background refresh
+    ///
+    /// # Panics
+    /// Panics if `interval` is zero
+    #[must_use]
+    pub fn refresh_interval(mut self, interval: Option<Duration>) -> Self {
+        assert!(
+            interval != Some(Duration::ZERO),
+            "the refresh interval must be positive"
+        );
+        self.flooding.refresh_interval = interval;
+        self
+    }
+
+    /// sets the time without flood responses after which a flood is considered
+    /// complete and the pending packets are sent again
+    #[must_use]
+    pub fn flood_quiet_period(mut self, quiet_period: Duration) -> Self {
+        self.flooding.quiet_period = quiet_period;
+        self
+    }
+
+    /// sets the maximum time waited for the responses of a flood before sending
+    /// the pending packets again
+    ///
+    /// # Panics
+    /// Panics if `timeout` is zero
+    #[must_use]
+    pub fn flood_settle_timeout(mut self, timeout: Duration) -> Self {
+        assert!(!timeout.is_zero(), "the settle timeout must be positive");
+        self.flooding.settle_timeout = timeout;
+        self
+    }
+
+    /// sets the file where the topology, the pdr estimations and the state of the
+    /// [`crate::servers::PdrEstimator`] learned by the server are saved every 5s.
+    /// If the file exists when the server is built, the server is warm-started from it,
+    /// so that it can route responses before its first flood.
+    /// A file that cannot be loaded is ignored
+    #[must_use]
+    pub fn routing_state_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.state_file = Some(path.into());
+        self
+    }
+
+    /// sets the maximum time waited for the in-flight responses to be acknowledged
+    /// during a graceful shutdown, zero abandons them immediately
+    #[must_use]
+    pub fn shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    /// sets whether the removal of a node, either by the controller or after a
+    /// [`wg_2024::packet::NackType::ErrorInRouting`], floods the network only if some
+    /// known client or server can no longer be reached with the remaining graph
+    #[must_use]
+    pub fn targeted_repair(mut self, targeted_repair: bool) -> Self {
+        self.flooding.targeted_repair = targeted_repair;
+        self
+    }
+
+    /// creates the configured [`GenericServer`]
+    #[must_use]
+    pub fn build(self) -> GenericServer<T> {
+        let estimator: Box<dyn PdrEstimator> = self.pdr_estimator.unwrap_or_else(|| {
+            Box::new(
+                default_estimator()
+                    .with_window_sz(self.window_sz)
+                    .with_alpha(self.alpha)
+                    .with_initial_pdr(self.initial_pdr),
+            )
+        });
+        let mut network_graph: RoutingTable = RoutingTable::new(estimator)
+            .with_half_life(self.pdr_half_life)
+            .with_exploration(self.exploration)
+            .with_seed(u64::from(self.id));
+        network_graph.add_endpoint(self.id);
+        for did in self.packet_send.keys() {
+            network_graph.check_and_add_edge(self.id, *did);
+        }
+
+        let service: Box<dyn RequestService> = self.service.unwrap_or_else(|| {
+            T::default_service(
+                ContentRoot::new(self.content_dir).with_recursive_listing(self.recursive_listing),
+                ContentRoot::new(self.media_dir).with_recursive_listing(self.recursive_listing),
+            )
+        });
+        let workers: WorkerPool = WorkerPool::new(
+            self.workers,
+            &self.target_topic,
+            self.compression_policy,
+            Arc::new(Mutex::new(service)),
+        );
+        let mut server: GenericServer<T> = GenericServer {
+            id: self.id,
+            target_topic: self.target_topic,
+            session_id: 0,
+            need_flood: true,
+            flood_requested: None,
+            last_flood: None,
+            flooding: self.flooding,
+            outstanding_floods: HashMap::new(),
+            graph_updated: false,
+            controller_send: self.controller_send,
+            controller_recv: self.controller_recv,
+            packet_recv: self.packet_recv,
+            packet_send: self.packet_send,
+            flood_history: HashMap::new(),
+            fragment_history: HashMap::new(),
+            rejected_requests: HashMap::new(),
+            reassembly: self.reassembly,
+            sent_history: HashMap::new(),
+            network_graph,
+            pending_packets: VecDeque::new(),
+            send_windows: HashMap::new(),
+            flow: self.flow,
+            max_paths: self.max_paths,
+            content_cache: ContentCache::new(self.cache_capacity),
+            workers,
+            streaming_threshold: self.streaming_threshold,
+            stream_layouts: HashMap::new(),
+            compression_policy: self.compression_policy,
+            extended_send: self.extended_send,
+            extended_recv: self.extended_recv,
+            metrics: ServerMetrics::default(),
+            error_counters: ErrorCounters::default(),
+            retransmission: self.retransmission,
+            state_file: None,
+            lifecycle: Lifecycle::Running,
+            shutdown_timeout: self.shutdown_timeout,
+            _marker: PhantomData,
+        };
+        if let Some(path) = self.state_file {
+            if path.exists() {
+                if let Err(e) = server.load_routing_state(&path) {
+                    warn!(target: &server.target_topic, "Unable to restore the routing state: {e:?}");
+                }
+            }
+            server.state_file = Some(StateFile::new(path));
+        }
+        server
+    }
+}
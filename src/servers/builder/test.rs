@@ -0,0 +1,262 @@
+#[cfg(test)]
+mod builder_tests {
+    use std::{collections::HashMap, env, fs, path::PathBuf, time::Duration};
+
+    use common::{
+        slc_commands::ServerType as ProtocolServerType,
+        web_messages::{Compression, RequestMessage, ResponseMessage},
+    };
+
+    use crate::servers::{
+        test_utils::get_dummy_builder, BetaEstimator, FileKind, GenericServer,
+        GenericServerBuilder, Media, Reply, ServerType, Text, TextMedia, INITIAL_ETX, MEDIA_PATH,
+        TEXT_PATH,
+    };
+
+    /// sends a text request for `name` to the service of the server
+    fn text_request<T: ServerType>(server: &GenericServer<T>, name: &str) -> Reply {
+        let request: RequestMessage =
+            RequestMessage::new_text_request(1, Compression::None, name.to_string());
+        server.workers.service().handle(server.id, &request)
+    }
+
+    /// canonical path of a file
+    fn canonical(path: &str) -> PathBuf {
+        PathBuf::from(path).canonicalize().unwrap()
+    }
+
+    /// tests that the default configuration matches the one of [`common::Server::new`]
+    #[test]
+    fn test_default_config() {
+        let text: GenericServer<Text> = get_dummy_builder(3).build();
+        assert_eq!(text.target_topic, "Server[3]");
+        assert_eq!(
+            text.workers.service().server_type(),
+            ProtocolServerType::FileServer
+        );
+        assert_eq!(
+            text_request(&text, "file.html"),
+            Reply::File {
+                path: canonical(&(TEXT_PATH.to_owned() + "file.html")),
+                kind: FileKind::Text
+            }
+        );
+        let mut media: GenericServer<Media> = get_dummy_builder(4).build();
+        assert_eq!(
+            media.workers.service().server_type(),
+            ProtocolServerType::MediaServer
+        );
+        let request: RequestMessage =
+            RequestMessage::new_media_request(1, Compression::None, "rust.png".to_string());
+        assert_eq!(
+            media.workers.service().handle(4, &request),
+            Reply::File {
+                path: canonical(&(MEDIA_PATH.to_owned() + "rust.png")),
+                kind: FileKind::Media
+            }
+        );
+        media.check_and_add_edge(4, 1);
+        assert!(*media.network_graph.get_graph().edge_weight(4, 1).unwrap() == INITIAL_ETX);
+    }
+
+    /// tests that several [`GenericServer`]s of the same type can serve different content
+    #[test]
+    fn test_content_dir() {
+        let text: GenericServer<Text> = get_dummy_builder(1).build();
+        let other: GenericServer<Text> = get_dummy_builder(2).content_dir(MEDIA_PATH).build();
+        assert_eq!(text_request(&text, "rust.png"), Reply::NotFound);
+        assert_eq!(
+            text_request(&other, "rust.png"),
+            Reply::File {
+                path: canonical(&(MEDIA_PATH.to_owned() + "rust.png")),
+                kind: FileKind::Text
+            }
+        );
+        assert_eq!(text_request(&other, "file.html"), Reply::NotFound);
+    }
+
+    /// tests the media directory of the [`crate::servers::TextMediaServer`]
+    #[test]
+    fn test_media_dir() {
+        let server: GenericServer<TextMedia> = get_dummy_builder(1).build();
+        assert_eq!(
+            server.workers.service().server_type(),
+            ProtocolServerType::FileServer
+        );
+        let media =
+            |name: &str| RequestMessage::new_media_request(1, Compression::None, name.to_string());
+        assert_eq!(
+            server.workers.service().handle(1, &media("rust.png")),
+            Reply::File {
+                path: canonical(&(MEDIA_PATH.to_owned() + "rust.png")),
+                kind: FileKind::Media
+            }
+        );
+
+        let other: GenericServer<TextMedia> = get_dummy_builder(2).media_dir(TEXT_PATH).build();
+        assert_eq!(
+            other.workers.service().handle(2, &media("rust.png")),
+            Reply::NotFound
+        );
+        assert_eq!(
+            other.workers.service().handle(2, &media("file.html")),
+            Reply::File {
+                path: canonical(&(TEXT_PATH.to_owned() + "file.html")),
+                kind: FileKind::Media
+            }
+        );
+        assert!(matches!(
+            text_request(&other, "file.html"),
+            Reply::File {
+                kind: FileKind::Text,
+                ..
+            }
+        ));
+    }
+
+    /// tests that list requests are not recursive unless configured
+    #[test]
+    fn test_recursive_listing() {
+        let dir: PathBuf =
+            env::temp_dir().join(format!("builder_list_test_{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("a.html"), b"a").unwrap();
+        fs::write(dir.join("sub/b.html"), b"b").unwrap();
+
+        let list: RequestMessage = RequestMessage::new_text_list_request(1, Compression::None);
+        let flat: GenericServer<Text> = get_dummy_builder(1).content_dir(&dir).build();
+        let recursive: GenericServer<Text> = get_dummy_builder(2)
+            .content_dir(&dir)
+            .recursive_listing(true)
+            .build();
+        assert_eq!(
+            flat.workers.service().handle(1, &list),
+            Reply::Response(ResponseMessage::new_text_list_response(
+                1,
+                Compression::None,
+                vec!["a.html".to_owned()]
+            ))
+        );
+        assert_eq!(
+            recursive.workers.service().handle(2, &list),
+            Reply::Response(ResponseMessage::new_text_list_response(
+                2,
+                Compression::None,
+                vec!["a.html".to_owned(), "sub/b.html".to_owned()]
+            ))
+        );
+        assert!(matches!(
+            text_request(&recursive, "sub/b.html"),
+            Reply::File { .. }
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// tests the log topic configuration
+    #[test]
+    fn test_target_topic() {
+        let server: GenericServer<Text> = get_dummy_builder(1).target_topic("Text[1]").build();
+        assert_eq!(server.target_topic, "Text[1]");
+    }
+
+    /// tests that the neighbors are added to the graph
+    #[test]
+    fn test_neighbors() {
+        let (ctrl_send, _) = crossbeam_channel::unbounded();
+        let (_, ctrl_recv) = crossbeam_channel::unbounded();
+        let (_, server_recv) = crossbeam_channel::unbounded();
+        let (ds, _) = crossbeam_channel::unbounded();
+        let server: GenericServer<Text> = GenericServerBuilder::new(
+            0,
+            ctrl_send,
+            ctrl_recv,
+            server_recv,
+            HashMap::from([(1, ds.clone()), (2, ds)]),
+        )
+        .initial_pdr(0.25)
+        .build();
+        assert!(server.network_graph.get_graph().contains_edge(0, 1));
+        assert!(server.network_graph.get_graph().contains_edge(0, 2));
+        assert!(*server.network_graph.get_graph().edge_weight(0, 1).unwrap() == 4.);
+    }
+
+    /// tests the configuration of the ETX estimator
+    #[test]
+    fn test_etx_config() {
+        let mut server: GenericServer<Text> = get_dummy_builder(0)
+            .window_size(2)
+            .alpha(1.)
+            .initial_pdr(0.25)
+            .build();
+        server.check_and_add_edge(2, 1);
+        assert!(*server.network_graph.get_graph().edge_weight(2, 1).unwrap() == 4.);
+        server.update_pdr_from_ack(&[0, 1, 3]);
+        assert!(*server.network_graph.get_graph().edge_weight(2, 1).unwrap() == 4.);
+        server.update_pdr_from_ack(&[0, 1, 3]);
+        assert!(*server.network_graph.get_graph().edge_weight(2, 1).unwrap() == 1.);
+    }
+
+    /// tests that a custom [`crate::servers::PdrEstimator`] replaces the default one
+    #[test]
+    fn test_pdr_estimator() {
+        let mut server: GenericServer<Text> = get_dummy_builder(0)
+            .initial_pdr(0.5)
+            .pdr_estimator(BetaEstimator::new(1., 3.))
+            .build();
+        server.check_and_add_edge(2, 1);
+        assert!(*server.network_graph.get_graph().edge_weight(2, 1).unwrap() == 4.);
+        // Beta(1 + 1, 3), updated after every sample
+        server.update_pdr_from_ack(&[0, 1, 3]);
+        assert!((*server.network_graph.get_graph().edge_weight(2, 1).unwrap() - 2.5).abs() < 1e-9);
+    }
+
+    /// tests the validation of the ETX parameters
+    #[test]
+    #[should_panic(expected = "alpha must be in (0, 1]")]
+    fn test_invalid_alpha() {
+        let _ = get_dummy_builder::<Text>(0).alpha(0.);
+    }
+
+    /// tests the validation of the ETX parameters
+    #[test]
+    #[should_panic(expected = "the window size must be positive")]
+    fn test_invalid_window() {
+        let _ = get_dummy_builder::<Text>(0).window_size(0);
+    }
+
+    /// tests the validation of the decay of the pdr estimations
+    #[test]
+    #[should_panic(expected = "the half-life must be positive")]
+    fn test_invalid_half_life() {
+        let _ = get_dummy_builder::<Text>(0).pdr_half_life(Some(Duration::ZERO));
+    }
+
+    /// tests the validation of the exploration probability
+    #[test]
+    #[should_panic(expected = "the exploration probability must be in [0, 1]")]
+    fn test_invalid_exploration() {
+        let _ = get_dummy_builder::<Text>(0).exploration(1.5);
+    }
+
+    /// tests the validation of the background refresh of the topology
+    #[test]
+    #[should_panic(expected = "the refresh interval must be positive")]
+    fn test_invalid_refresh_interval() {
+        let _ = get_dummy_builder::<Text>(0).refresh_interval(Some(Duration::ZERO));
+    }
+
+    /// tests the validation of the flood settle timeout
+    #[test]
+    #[should_panic(expected = "the settle timeout must be positive")]
+    fn test_invalid_settle_timeout() {
+        let _ = get_dummy_builder::<Text>(0).flood_settle_timeout(Duration::ZERO);
+    }
+
+    /// tests the validation of the send window
+    #[test]
+    #[should_panic(expected = "the send window must be positive")]
+    fn test_invalid_send_window() {
+        let _ = get_dummy_builder::<Text>(0).initial_send_window(0);
+    }
+}
@@ -0,0 +1,151 @@
+use std::{
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+/// testing module
+#[cfg(test)]
+mod test;
+
+/// reasons why a name received from a client cannot be served from a [`ContentRoot`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentError {
+    /// the name points outside of the content root (absolute path, `..`
+    /// components or a symlink leading outside of the root)
+    Forbidden,
+    /// the name is valid but it doesn't match any file in the content root
+    NotFound,
+}
+
+/// directory containing the files that a server is allowed to serve.
+/// Every name received from a client is resolved relative to this directory
+/// and can never refer to a file outside of it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentRoot {
+    /// path of the directory, as configured
+    root: PathBuf,
+    /// whether or not the files contained in subdirectories are listed
+    recursive: bool,
+}
+
+impl ContentRoot {
+    /// creates a new [`ContentRoot`] from the given directory, subdirectories are not listed
+    #[inline]
+    #[must_use]
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            recursive: false,
+        }
+    }
+
+    /// sets whether or not the files contained in subdirectories are listed
+    #[inline]
+    #[must_use]
+    pub fn with_recursive_listing(mut self, recursive: bool) -> Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// returns the configured path of the content root
+    #[inline]
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    /// resolves the logical name of a file (i.e. the name returned by [`ContentRoot::list`])
+    /// to its canonical path inside the content root.
+    ///
+    /// If the logical name cannot be resolved, the name is tried again without the
+    /// configured root path, since the html documents embed their resources as
+    /// `<root>/<name>` (e.g. `./media/rust.png`).
+    /// Absolute paths and `..` components are rejected, symlinks are canonicalized
+    /// and must still point inside of the root
+    ///
+    /// # Errors
+    /// Returns [`ContentError::Forbidden`] if the name points outside of the root and
+    /// [`ContentError::NotFound`] if the name does not match any file
+    pub fn resolve(&self, name: &str) -> Result<PathBuf, ContentError> {
+        let path: &Path = Path::new(name);
+        self.resolve_relative(path).or_else(|e: ContentError| {
+            path.strip_prefix(&self.root)
+                .map_or(Err(e), |p: &Path| self.resolve_relative(p))
+        })
+    }
+
+    /// resolves a path relative to the content root
+    fn resolve_relative(&self, path: &Path) -> Result<PathBuf, ContentError> {
+        if path
+            .components()
+            .any(|c: Component<'_>| !matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(ContentError::Forbidden);
+        }
+
+        let root: PathBuf = self
+            .root
+            .canonicalize()
+            .map_err(|_| ContentError::NotFound)?;
+        let full: PathBuf = root
+            .join(path)
+            .canonicalize()
+            .map_err(|_| ContentError::NotFound)?;
+        if !full.starts_with(&root) {
+            Err(ContentError::Forbidden)
+        } else if full.is_file() {
+            Ok(full)
+        } else {
+            Err(ContentError::NotFound)
+        }
+    }
+
+    /// reads the content of the file with the given name, see [`ContentRoot::resolve`]
+    ///
+    /// # Errors
+    /// See [`ContentRoot::resolve`]
+    pub fn read(&self, name: &str) -> Result<Vec<u8>, ContentError> {
+        fs::read(self.resolve(name)?).map_err(|_| ContentError::NotFound)
+    }
+
+    /// lists the logical names of the files in the content root, i.e. their paths relative
+    /// to the root with `/` as separator. The names are sorted and can be resolved
+    /// with [`ContentRoot::resolve`]
+    ///
+    /// # Errors
+    /// Returns an error if the root directory cannot be read
+    pub fn list(&self) -> Result<Vec<String>, io::Error> {
+        let mut names: Vec<String> = Vec::new();
+        self.list_into(&self.root, "", &mut names)?;
+        names.sort();
+        Ok(names)
+    }
+
+    /// appends to `names` the files contained in `dir`, prefixed by `prefix`.
+    /// symlinks to directories are never followed, so that the listing cannot loop
+    fn list_into(
+        &self,
+        dir: &Path,
+        prefix: &str,
+        names: &mut Vec<String>,
+    ) -> Result<(), io::Error> {
+        for entry in fs::read_dir(dir)?.filter_map(Result::ok) {
+            let Some(name) = entry
+                .file_name()
+                .to_str()
+                .map(|n: &str| format!("{prefix}{n}"))
+            else {
+                continue;
+            };
+            if entry.file_type().is_ok_and(|t: fs::FileType| t.is_dir()) {
+                if self.recursive {
+                    // unreadable subdirectories are simply skipped
+                    let _ = self.list_into(&entry.path(), &format!("{name}/"), names);
+                }
+            } else if self.resolve_relative(Path::new(&name)).is_ok() {
+                names.push(name);
+            }
+        }
+        Ok(())
+    }
+}
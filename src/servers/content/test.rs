@@ -0,0 +1,145 @@
+#[cfg(test)]
+mod content_tests {
+    use std::{env, fs, path::PathBuf};
+
+    use crate::servers::{
+        content::{ContentError, ContentRoot},
+        MEDIA_PATH, TEXT_PATH,
+    };
+
+    /// tests the resolution of valid names
+    #[test]
+    fn test_resolve_valid_names() {
+        let root: ContentRoot = ContentRoot::new(TEXT_PATH);
+        let expected: PathBuf = PathBuf::from(TEXT_PATH)
+            .join("file.html")
+            .canonicalize()
+            .unwrap();
+        assert_eq!(root.resolve("file.html").unwrap(), expected);
+        assert_eq!(root.resolve("./file.html").unwrap(), expected);
+        assert_eq!(root.resolve("./public/file.html").unwrap(), expected);
+        let media: ContentRoot = ContentRoot::new(MEDIA_PATH);
+        assert!(media.resolve("./media/rust.png").is_ok());
+    }
+
+    /// tests that unknown names and directories are not found
+    #[test]
+    fn test_resolve_not_found() {
+        let root: ContentRoot = ContentRoot::new(TEXT_PATH);
+        assert_eq!(root.resolve("non_esisto"), Err(ContentError::NotFound));
+        assert_eq!(root.resolve(""), Err(ContentError::NotFound));
+        assert_eq!(root.resolve("."), Err(ContentError::NotFound));
+        assert_eq!(
+            ContentRoot::new("./non_esisto/").resolve("file.html"),
+            Err(ContentError::NotFound)
+        );
+    }
+
+    /// tests that absolute paths are refused
+    #[test]
+    fn test_resolve_absolute() {
+        let root: ContentRoot = ContentRoot::new(TEXT_PATH);
+        assert_eq!(root.resolve("/etc/passwd"), Err(ContentError::Forbidden));
+        let absolute: PathBuf = PathBuf::from(TEXT_PATH)
+            .join("file.html")
+            .canonicalize()
+            .unwrap();
+        assert_eq!(
+            root.resolve(absolute.to_str().unwrap()),
+            Err(ContentError::Forbidden)
+        );
+    }
+
+    /// tests that `..` components are refused, even if they would land inside the root
+    #[test]
+    fn test_resolve_parent_dir() {
+        let root: ContentRoot = ContentRoot::new(TEXT_PATH);
+        assert_eq!(root.resolve("../src/lib.rs"), Err(ContentError::Forbidden));
+        assert_eq!(
+            root.resolve("./public/../Cargo.toml"),
+            Err(ContentError::Forbidden)
+        );
+        assert_eq!(
+            root.resolve("a/../../Cargo.toml"),
+            Err(ContentError::Forbidden)
+        );
+        assert_eq!(
+            root.resolve("../public/file.html"),
+            Err(ContentError::Forbidden)
+        );
+        assert_eq!(root.resolve(".."), Err(ContentError::Forbidden));
+    }
+
+    /// tests that symlinks are followed only if they point inside the root
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_symlinks() {
+        let base: PathBuf =
+            env::temp_dir().join(format!("content_root_test_{}", std::process::id()));
+        let root_dir: PathBuf = base.join("root");
+        fs::create_dir_all(&root_dir).unwrap();
+        fs::write(base.join("secret.txt"), b"secret").unwrap();
+        fs::write(root_dir.join("public.txt"), b"public").unwrap();
+        std::os::unix::fs::symlink(base.join("secret.txt"), root_dir.join("evil")).unwrap();
+        std::os::unix::fs::symlink(root_dir.join("public.txt"), root_dir.join("good")).unwrap();
+
+        let root: ContentRoot = ContentRoot::new(&root_dir);
+        assert_eq!(root.resolve("evil"), Err(ContentError::Forbidden));
+        assert_eq!(root.read("good").unwrap(), b"public".to_vec());
+        assert_eq!(root.read("public.txt").unwrap(), b"public".to_vec());
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    /// tests the read of a file
+    #[test]
+    fn test_read() {
+        let root: ContentRoot = ContentRoot::new(TEXT_PATH);
+        assert_eq!(
+            root.read("file.html").unwrap(),
+            fs::read(TEXT_PATH.to_owned() + "file.html").unwrap()
+        );
+        assert_eq!(root.read("../Cargo.toml"), Err(ContentError::Forbidden));
+    }
+
+    /// tests that the listing returns logical names that can be resolved
+    #[test]
+    fn test_list() {
+        let root: ContentRoot = ContentRoot::new(TEXT_PATH);
+        let names: Vec<String> = root.list().unwrap();
+        assert_eq!(
+            names,
+            vec!["file.html", "file2.html", "index.html", "three.html"]
+        );
+        for name in &names {
+            assert!(root.resolve(name).is_ok());
+        }
+        assert!(ContentRoot::new("./non_esisto/").list().is_err());
+    }
+
+    /// tests the recursive listing of subdirectories and the exclusion of escaping symlinks
+    #[cfg(unix)]
+    #[test]
+    fn test_list_recursive() {
+        let base: PathBuf =
+            env::temp_dir().join(format!("content_list_test_{}", std::process::id()));
+        let root_dir: PathBuf = base.join("root");
+        fs::create_dir_all(root_dir.join("sub/inner")).unwrap();
+        fs::write(base.join("secret.txt"), b"secret").unwrap();
+        fs::write(root_dir.join("a.txt"), b"a").unwrap();
+        fs::write(root_dir.join("sub/b.txt"), b"b").unwrap();
+        fs::write(root_dir.join("sub/inner/c.txt"), b"c").unwrap();
+        std::os::unix::fs::symlink(base.join("secret.txt"), root_dir.join("evil")).unwrap();
+        std::os::unix::fs::symlink(&root_dir, root_dir.join("sub/loop")).unwrap();
+
+        let flat: ContentRoot = ContentRoot::new(&root_dir);
+        assert_eq!(flat.list().unwrap(), vec!["a.txt"]);
+
+        let recursive: ContentRoot = ContentRoot::new(&root_dir).with_recursive_listing(true);
+        let names: Vec<String> = recursive.list().unwrap();
+        assert_eq!(names, vec!["a.txt", "sub/b.txt", "sub/inner/c.txt"]);
+        assert_eq!(recursive.read("sub/inner/c.txt").unwrap(), b"c".to_vec());
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+}
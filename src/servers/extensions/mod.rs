@@ -0,0 +1,123 @@
+use std::time::Instant;
+
+use log::info;
+use wg_2024::network::NodeId;
+
+use super::{metrics::ServerMetrics, topology::TopologySnapshot, GenericServer, ServerType};
+
+/// Commands sent by the controller to a [`GenericServer`] in addition to the
+/// [`common::slc_commands::ServerCommand`]s, they are received only if the
+/// [`crate::GenericServerBuilder`] was given a channel for them
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExtendedCommand {
+    /// asks for the current network topology, answered with [`ExtendedEvent::Topology`]
+    TopologySnapshot,
+    /// asks the server to stop once the in-flight responses are acknowledged or given
+    /// up, [`common::Server::run`] returns after at most the shutdown timeout
+    Shutdown,
+    /// stops the server immediately, abandoning the in-flight responses
+    Crash,
+    /// asks for the current metrics of the server, answered with [`ExtendedEvent::Metrics`]
+    Metrics,
+}
+
+/// Events sent by a [`GenericServer`] to the controller in addition to the
+/// [`common::slc_commands::ServerEvent`]s, they are sent only if the
+/// [`crate::GenericServerBuilder`] was given a channel for them
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum ExtendedEvent {
+    /// a fragment of a response was not acknowledged even after the maximum number
+    /// of retransmissions, so it has been dropped
+    FragmentGivenUp {
+        /// session id of the fragment
+        session_id: u64,
+        /// node id of the receiver of the response
+        receiver_id: NodeId,
+        /// index of the fragment in the response
+        frag_idx: u64,
+        /// total number of fragments in the response
+        n_frags: u64,
+    },
+    /// the error counters changed, contains their updated values
+    Errors(ErrorCounters),
+    /// network topology requested with [`ExtendedCommand::TopologySnapshot`]
+    Topology(TopologySnapshot),
+    /// metrics requested with [`ExtendedCommand::Metrics`]
+    Metrics(ServerMetrics),
+}
+
+/// Counters of the erroneous requests received by a [`GenericServer`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorCounters {
+    /// requests that could not be deserialized
+    pub undeserializable: u64,
+    /// requests not supported by the server
+    pub invalid: u64,
+    /// requests of files outside of the content root of the server
+    pub forbidden: u64,
+    /// requests refused due to the reassembly limits
+    pub rejected: u64,
+}
+
+/// Kind of error of a received request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum RequestError {
+    /// see [`ErrorCounters::undeserializable`]
+    Undeserializable,
+    /// see [`ErrorCounters::invalid`]
+    Invalid,
+    /// see [`ErrorCounters::forbidden`]
+    Forbidden,
+    /// see [`ErrorCounters::rejected`]
+    Rejected,
+}
+
+impl<T: ServerType> GenericServer<T> {
+    /// sends an [`ExtendedEvent`] to the controller, if it is listening for them
+    pub(super) fn send_extended_event(&self, event: ExtendedEvent) {
+        if let Some(c) = &self.extended_send {
+            let _ = c.send(event);
+        }
+    }
+
+    /// handles an [`ExtendedCommand`] received from the controller
+    pub(super) fn handle_extended_command(&mut self, command: &ExtendedCommand) {
+        match command {
+            ExtendedCommand::TopologySnapshot => {
+                info!(target: &self.target_topic, "Received topology snapshot command");
+                self.send_extended_event(ExtendedEvent::Topology(self.topology_snapshot()));
+            }
+            ExtendedCommand::Shutdown => {
+                info!(target: &self.target_topic, "Received shutdown command");
+                self.begin_shutdown(Instant::now());
+            }
+            ExtendedCommand::Crash => {
+                info!(target: &self.target_topic, "Received crash command");
+                self.crash();
+            }
+            ExtendedCommand::Metrics => {
+                info!(target: &self.target_topic, "Received metrics command");
+                self.send_extended_event(ExtendedEvent::Metrics(self.metrics()));
+            }
+        }
+    }
+
+    /// counts an erroneous request and sends the updated counters to the controller
+    pub(super) fn record_error(&mut self, error: RequestError) {
+        match error {
+            RequestError::Undeserializable => self.error_counters.undeserializable += 1,
+            RequestError::Invalid => self.error_counters.invalid += 1,
+            RequestError::Forbidden => self.error_counters.forbidden += 1,
+            RequestError::Rejected => self.error_counters.rejected += 1,
+        }
+        self.send_extended_event(ExtendedEvent::Errors(self.error_counters));
+    }
+
+    /// counters of the erroneous requests received so far
+    #[must_use]
+    pub fn error_counters(&self) -> ErrorCounters {
+        self.error_counters
+    }
+}
@@ -0,0 +1,290 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    mem,
+    time::Instant,
+};
+
+use common::slc_commands::ServerEvent;
+use log::{error, info};
+use wg_2024::{
+    network::{NodeId, SourceRoutingHeader},
+    packet::{Fragment, Packet},
+};
+
+use super::{streaming::FileStream, GenericServer, HistoryEntry, MessageHistory, ServerType};
+use crate::protocol_utils as network_protocol;
+
+/// testing module
+#[cfg(test)]
+mod test;
+
+/// default number of fragments of a response sent before receiving any acknowledgement
+const DEFAULT_INITIAL_WINDOW: u32 = 8;
+/// default upper bound of the congestion window
+const DEFAULT_MAX_WINDOW: u32 = 64;
+/// lower bound of the congestion window, at least one fragment is always in flight
+const MIN_WINDOW: f64 = 1.;
+
+/// Configuration of the congestion window of the responses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FlowConfig {
+    /// size of the congestion window of a new response
+    pub(crate) initial_window: u32,
+    /// upper bound of the congestion window
+    pub(crate) max_window: u32,
+}
+
+impl Default for FlowConfig {
+    fn default() -> Self {
+        Self {
+            initial_window: DEFAULT_INITIAL_WINDOW,
+            max_window: DEFAULT_MAX_WINDOW,
+        }
+    }
+}
+
+/// Fragment of a response waiting for a free slot of the window
+#[derive(Debug, Clone)]
+struct QueuedFragment {
+    /// session id of the fragment
+    sid: u64,
+    /// routing header used to send the fragment
+    hdr: SourceRoutingHeader,
+    /// entry inserted in the sent history once the fragment is sent
+    entry: HistoryEntry,
+}
+
+/// Sliding window of a response: at most `cwnd` fragments are waiting to be acknowledged.
+/// The window grows by one fragment for every window of acknowledged fragments and
+/// halves after a [`wg_2024::packet::NackType::Dropped`] (AIMD)
+#[derive(Debug)]
+pub(super) struct SendWindow {
+    /// congestion window, in fragments
+    cwnd: f64,
+    /// fragments sent and not yet acknowledged or given up
+    in_flight: HashSet<u64>,
+    /// fragments not yet sent
+    queue: VecDeque<QueuedFragment>,
+    /// streamed response whose fragments are read once the queue is empty, if any
+    pub(super) stream: Option<FileStream>,
+    /// true if the queued fragments are waiting for a route to the receiver
+    stalled: bool,
+}
+
+impl SendWindow {
+    /// creates an empty [`SendWindow`]
+    fn new(config: FlowConfig) -> Self {
+        Self {
+            cwnd: f64::from(config.initial_window.min(config.max_window)),
+            in_flight: HashSet::new(),
+            queue: VecDeque::new(),
+            stream: None,
+            stalled: false,
+        }
+    }
+
+    /// checks if a new fragment can be sent
+    #[allow(clippy::cast_precision_loss)]
+    fn has_room(&self) -> bool {
+        (self.in_flight.len() as f64) < self.cwnd.floor()
+    }
+}
+
+/// Data structure used to remember the windows of the responses being sent
+/// maps (`ReceiverId`, rid) -> window of the response
+pub(super) type SendWindows = HashMap<(NodeId, u16), SendWindow>;
+
+impl<T: ServerType> GenericServer<T> {
+    /// queues the fragments of a response, each with its routing header, and sends
+    /// the ones allowed by the window. The session ids are assigned in order
+    pub(super) fn send_windowed(
+        &mut self,
+        receiver_id: NodeId,
+        rid: u16,
+        fragments: Vec<(SourceRoutingHeader, HistoryEntry)>,
+    ) {
+        let config: FlowConfig = self.flow;
+        let mut sids: Vec<u64> = Vec::with_capacity(fragments.len());
+        for _ in 0..fragments.len() {
+            sids.push(network_protocol::generate_response_id(self.session_id, rid));
+            self.session_id = network_protocol::next_sid(self.session_id);
+        }
+        let window: &mut SendWindow = self
+            .send_windows
+            .entry((receiver_id, rid))
+            .or_insert_with(|| SendWindow::new(config));
+        window.queue.extend(
+            sids.into_iter()
+                .zip(fragments)
+                .map(|(sid, (hdr, entry))| QueuedFragment { sid, hdr, entry }),
+        );
+        self.pump_window((receiver_id, rid));
+    }
+
+    /// sends the fragments of a streamed response as the window allows,
+    /// reading them only when they are sent
+    pub(super) fn stream_windowed(&mut self, receiver_id: NodeId, rid: u16, stream: FileStream) {
+        let config: FlowConfig = self.flow;
+        self.send_windows
+            .entry((receiver_id, rid))
+            .or_insert_with(|| SendWindow::new(config))
+            .stream = Some(stream);
+        self.pump_window((receiver_id, rid));
+    }
+
+    /// sends the queued fragments of a response while the window has room, then the
+    /// ones of its stream, a stream that cannot be read is answered again. The window is
+    /// removed once the response is completely sent.
+    /// The queued fragments whose next hop is not a neighbor are routed again, if no
+    /// route is known the window stalls until the next update of the network graph
+    fn pump_window(&mut self, key: (NodeId, u16)) {
+        let Some(window) = self.send_windows.get_mut(&key) else {
+            return;
+        };
+        let mut ready: Vec<QueuedFragment> = Vec::new();
+        let mut route: Option<SourceRoutingHeader> = None;
+        let mut failed: Option<FileStream> = None;
+        // the version of the streamed file is checked once per pump
+        let mut checked: bool = false;
+        window.stalled = false;
+        while window.has_room() {
+            if let Some(mut queued) = window.queue.pop_front() {
+                if !self.packet_send.contains_key(&queued.hdr.hops[1]) {
+                    if route.is_none() {
+                        route = self
+                            .network_graph
+                            .get_route(self.id, key.0)
+                            .filter(|p: &Vec<NodeId>| self.packet_send.contains_key(&p[1]))
+                            .map(|p: Vec<NodeId>| SourceRoutingHeader::new(p, 1));
+                    }
+                    let Some(hdr) = &route else {
+                        info!(target: &self.target_topic, "No path found for response {}, waiting for a route", key.1);
+                        window.queue.push_front(queued);
+                        window.stalled = true;
+                        self.graph_updated = false;
+                        break;
+                    };
+                    queued.hdr.clone_from(hdr);
+                    queued.entry.hops.clone_from(&hdr.hops);
+                }
+                window.in_flight.insert(queued.sid);
+                ready.push(queued);
+                continue;
+            }
+            let Some(stream) = window.stream.as_mut() else {
+                break;
+            };
+            match stream.next_fragment(key.0, !mem::replace(&mut checked, true)) {
+                Some(Ok((hdr, entry))) => {
+                    let sid: u64 = network_protocol::generate_response_id(self.session_id, key.1);
+                    self.session_id = network_protocol::next_sid(self.session_id);
+                    window.in_flight.insert(sid);
+                    ready.push(QueuedFragment { sid, hdr, entry });
+                }
+                Some(Err(e)) => {
+                    error!(target: &self.target_topic, "Cannot read the streamed response {}: {e}, answering again", key.1);
+                    failed = window.stream.take();
+                }
+                None => window.stream = None,
+            }
+        }
+        if window.in_flight.is_empty() && window.queue.is_empty() && window.stream.is_none() {
+            self.send_windows.remove(&key);
+        }
+        for QueuedFragment {
+            sid,
+            hdr,
+            mut entry,
+        } in ready
+        {
+            let packet: Packet = Packet::new_fragment(
+                hdr,
+                sid,
+                Fragment::new(entry.frag_idx, entry.n_frags, entry.frag),
+            );
+            entry.start_timer(&self.retransmission, Instant::now());
+            self.sent_history.insert(sid, entry);
+            self.metrics.fragments_sent += 1;
+            info!(target: &self.target_topic, "Sending response: {packet}");
+            if let Some(next_hop) = self.packet_send.get(&packet.routing_header.hops[1]) {
+                let _ = next_hop.send(packet.clone());
+            }
+            let _ = self.controller_send.send(ServerEvent::PacketSent(packet));
+        }
+        if let Some(stream) = failed {
+            self.stream_failed(key.0, key.1, stream);
+        }
+    }
+
+    /// sends the fragments of the responses that were waiting for a route,
+    /// after an update of the network graph
+    pub(super) fn pump_stalled_windows(&mut self) {
+        let stalled: Vec<(NodeId, u16)> = self
+            .send_windows
+            .iter()
+            .filter(|(_, w): &(&(NodeId, u16), &SendWindow)| w.stalled)
+            .map(|(k, _)| *k)
+            .collect();
+        for key in stalled {
+            self.pump_window(key);
+        }
+    }
+
+    /// checks if some response is waiting for a route
+    pub(super) fn has_stalled_windows(&self) -> bool {
+        self.send_windows.values().any(|w: &SendWindow| w.stalled)
+    }
+
+    /// number of fragments of new responses waiting for a route
+    pub(super) fn stalled_fragments(&self) -> usize {
+        self.send_windows
+            .values()
+            .filter(|w: &&SendWindow| w.stalled)
+            .map(|w: &SendWindow| w.queue.len())
+            .sum()
+    }
+
+    /// grows the window of the response after an acknowledged fragment
+    /// and sends the fragments that now fit in it
+    pub(super) fn window_on_ack(&mut self, receiver_id: NodeId, sid: u64) {
+        let key: (NodeId, u16) = (receiver_id, network_protocol::get_rid(sid));
+        let max: f64 = f64::from(self.flow.max_window);
+        if let Some(window) = self.send_windows.get_mut(&key) {
+            if window.in_flight.remove(&sid) {
+                window.cwnd = (window.cwnd + 1. / window.cwnd).min(max);
+            }
+            self.pump_window(key);
+        }
+    }
+
+    /// halves the window of the response after a dropped fragment
+    pub(super) fn window_on_drop(&mut self, receiver_id: NodeId, sid: u64) {
+        let key: (NodeId, u16) = (receiver_id, network_protocol::get_rid(sid));
+        if let Some(window) = self.send_windows.get_mut(&key) {
+            window.cwnd = (window.cwnd / 2.).max(MIN_WINDOW);
+            info!(target: &self.target_topic, "Dropped sid: {sid}, window shrunk to {:.2}", window.cwnd);
+        }
+    }
+
+    /// frees the slot of a fragment that was given up
+    pub(super) fn window_on_give_up(&mut self, receiver_id: NodeId, sid: u64) {
+        let key: (NodeId, u16) = (receiver_id, network_protocol::get_rid(sid));
+        if let Some(window) = self.send_windows.get_mut(&key) {
+            window.in_flight.remove(&sid);
+            self.pump_window(key);
+        }
+    }
+
+    /// forgets the response `rid` to the receiver: its window, the fragments waiting to be
+    /// acknowledged and the pending ones, so that none of them is sent again
+    pub(super) fn abandon_response(&mut self, receiver_id: NodeId, rid: u16) {
+        self.send_windows.remove(&(receiver_id, rid));
+        self.sent_history
+            .retain(|&sid: &u64, entry: &mut HistoryEntry| {
+                entry.receiver_id != receiver_id || network_protocol::get_rid(sid) != rid
+            });
+        let history: &MessageHistory = &self.sent_history;
+        self.pending_packets
+            .retain(|sid: &u64| history.contains_key(sid));
+    }
+}
@@ -0,0 +1,184 @@
+#[cfg(test)]
+mod flow_control_tests {
+    use crossbeam_channel::Receiver;
+    use wg_2024::{
+        network::SourceRoutingHeader,
+        packet::{Ack, Packet, PacketType},
+    };
+
+    use crate::servers::{
+        flow_control::SendWindow,
+        test_utils::{get_dummy_builder, get_routed_server},
+        GenericServer, HistoryEntry, Text,
+    };
+
+    /// get a [`GenericServer<Text>`] with a route to the client 2 and the given window,
+    /// the packets sent to the drone 1 can be read from the returned channel
+    fn get_server(initial: u32, max: u32) -> (GenericServer<Text>, Receiver<Packet>) {
+        get_routed_server(
+            get_dummy_builder(0)
+                .initial_send_window(initial)
+                .max_send_window(max),
+            &[2],
+        )
+    }
+
+    /// sends a response of `n` fragments to the client 2
+    fn send_response(server: &mut GenericServer<Text>, n: u8) {
+        let data: Vec<[u8; 128]> = (0..n).map(|i: u8| [i; 128]).collect();
+        server.send_fragments(&SourceRoutingHeader::new(vec![2, 1, 0], 2), 2, 7, data);
+    }
+
+    /// session ids and indexes of the fragments sent to the drone 1
+    fn sent(dr: &Receiver<Packet>) -> Vec<(u64, u64)> {
+        dr.try_iter()
+            .filter_map(|p: Packet| match p.pack_type {
+                PacketType::MsgFragment(f) => Some((p.session_id, f.fragment_index)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// the window of the response sent by `send_response`
+    fn window(server: &GenericServer<Text>) -> &SendWindow {
+        server.send_windows.get(&(2, 7)).unwrap()
+    }
+
+    /// tests that at most a window of fragments is waiting to be acknowledged
+    #[test]
+    fn test_window_limit() {
+        let (mut server, dr) = get_server(4, 64);
+        send_response(&mut server, 10);
+        let first: Vec<(u64, u64)> = sent(&dr);
+        assert_eq!(
+            first.iter().map(|f| f.1).collect::<Vec<u64>>(),
+            vec![0, 1, 2, 3]
+        );
+        assert!(server.sent_history.len() == 4);
+        assert!(window(&server).queue.len() == 6);
+
+        // cwnd = 4.25, one slot is free
+        server.handle_ack(first[0].0, &Ack { fragment_index: 0 });
+        assert_eq!(sent(&dr).iter().map(|f| f.1).collect::<Vec<u64>>(), vec![4]);
+        assert!((window(&server).cwnd - 4.25).abs() < 1e-9);
+
+        // acknowledging everything sends the whole response
+        let mut acked: usize = 1;
+        let mut in_flight: Vec<(u64, u64)> = first[1..].to_vec();
+        while let Some((sid, idx)) = in_flight.pop() {
+            server.handle_ack(
+                sid,
+                &Ack {
+                    fragment_index: idx,
+                },
+            );
+            acked += 1;
+            in_flight.extend(sent(&dr));
+            if in_flight.is_empty() {
+                in_flight.extend(server.sent_history.iter().map(|(&s, e)| (s, e.frag_idx)));
+            }
+        }
+        assert!(acked == 10);
+        assert!(server.send_windows.is_empty());
+        assert!(server.sent_history.is_empty());
+    }
+
+    /// tests the additive increase and the multiplicative decrease of the window
+    #[test]
+    fn test_aimd() {
+        let (mut server, dr) = get_server(8, 9);
+        send_response(&mut server, 20);
+        let first: Vec<(u64, u64)> = sent(&dr);
+        server.window_on_drop(2, first[0].0);
+        assert!((window(&server).cwnd - 4.).abs() < 1e-9);
+        for _ in 0..5 {
+            server.window_on_drop(2, first[0].0);
+        }
+        assert!((window(&server).cwnd - 1.).abs() < 1e-9);
+        // nothing is sent while more fragments than the window are in flight
+        server.handle_ack(first[0].0, &Ack { fragment_index: 0 });
+        assert!((window(&server).cwnd - 2.).abs() < 1e-9);
+        assert!(sent(&dr).is_empty());
+        for &(sid, idx) in &first[1..6] {
+            server.handle_ack(
+                sid,
+                &Ack {
+                    fragment_index: idx,
+                },
+            );
+        }
+        // cwnd ~ 3.83 with 2 fragments in flight
+        assert!(sent(&dr).len() == 1);
+
+        let (mut server, dr) = get_server(8, 9);
+        send_response(&mut server, 20);
+        // the window stops growing at the maximum
+        let mut in_flight: Vec<(u64, u64)> = sent(&dr);
+        for _ in 0..10 {
+            let (sid, idx) = in_flight.remove(0);
+            server.handle_ack(
+                sid,
+                &Ack {
+                    fragment_index: idx,
+                },
+            );
+            in_flight.extend(sent(&dr));
+        }
+        assert!((window(&server).cwnd - 9.).abs() < 1e-9);
+        assert!(in_flight.len() == 9);
+    }
+
+    /// tests that a given up fragment frees its slot of the window
+    #[test]
+    fn test_give_up() {
+        let (mut server, dr) = get_server(1, 64);
+        send_response(&mut server, 2);
+        let first: Vec<(u64, u64)> = sent(&dr);
+        assert!(first.len() == 1);
+        server.sent_history.remove(&first[0].0);
+        server.window_on_give_up(2, first[0].0);
+        assert_eq!(sent(&dr).iter().map(|f| f.1).collect::<Vec<u64>>(), vec![1]);
+    }
+
+    /// tests that the windows of different responses are independent
+    #[test]
+    fn test_independent_windows() {
+        let (mut server, dr) = get_server(2, 64);
+        send_response(&mut server, 4);
+        let data: Vec<[u8; 128]> = vec![[0; 128]; 4];
+        server.send_fragments(&SourceRoutingHeader::new(vec![2, 1, 0], 2), 2, 8, data);
+        assert!(sent(&dr).len() == 4);
+        assert!(server.send_windows.len() == 2);
+    }
+
+    /// tests that the fragments of a response without a route wait in its window
+    /// and are sent once a route is known
+    #[test]
+    fn test_no_route() {
+        let (mut server, dr) = get_routed_server(get_dummy_builder(0).initial_send_window(4), &[]);
+        let data: Vec<[u8; 128]> = vec![[0; 128]; 6];
+        // the route of the request goes through 5, which is not a neighbor
+        server.send_fragments(&SourceRoutingHeader::new(vec![2, 5, 0], 2), 2, 7, data);
+        assert!(sent(&dr).is_empty());
+        assert!(server.sent_history.is_empty());
+        assert!(window(&server).stalled);
+        assert!(server.metrics().pending_packets == 6);
+        assert!(server.pending_packets.is_empty());
+
+        server.network_graph.remove_node(5);
+        server.check_and_add_edge(1, 2);
+        server.graph_updated = true;
+        server.resend_pending();
+        assert_eq!(
+            sent(&dr).iter().map(|f| f.1).collect::<Vec<u64>>(),
+            vec![0, 1, 2, 3]
+        );
+        assert!(!window(&server).stalled);
+        assert!(window(&server).queue.len() == 2);
+        assert!(server.metrics().pending_packets == 0);
+        assert!(server
+            .sent_history
+            .values()
+            .all(|e: &HistoryEntry| e.hops == vec![0, 1, 2]));
+    }
+}
@@ -0,0 +1,204 @@
+use std::{collections::BTreeMap, ffi::OsStr, path::Path};
+
+use common::web_messages::{MediaRequest, Request, TextRequest};
+use wg_2024::{network::NodeId, packet::NackType};
+
+use super::{extensions::ErrorCounters, requests_handling::CacheStats, GenericServer, ServerType};
+
+/// testing module
+#[cfg(test)]
+mod test;
+
+/// Counters of the [`wg_2024::packet::Nack`]s received by a [`GenericServer`], by [`NackType`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NackCounters {
+    /// [`NackType::ErrorInRouting`] nacks
+    pub error_in_routing: u64,
+    /// [`NackType::DestinationIsDrone`] nacks
+    pub destination_is_drone: u64,
+    /// [`NackType::Dropped`] nacks
+    pub dropped: u64,
+    /// [`NackType::UnexpectedRecipient`] nacks
+    pub unexpected_recipient: u64,
+}
+
+impl NackCounters {
+    /// counts a nack of the given type
+    fn count(&mut self, nack_type: &NackType) {
+        let counter: &mut u64 = match nack_type {
+            NackType::ErrorInRouting(_) => &mut self.error_in_routing,
+            NackType::DestinationIsDrone => &mut self.destination_is_drone,
+            NackType::Dropped => &mut self.dropped,
+            NackType::UnexpectedRecipient(_) => &mut self.unexpected_recipient,
+        };
+        *counter += 1;
+    }
+}
+
+/// Counters of the requests received by a [`GenericServer`], by kind
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RequestCounters {
+    /// requests of the type of the server
+    pub server_type: u64,
+    /// requests of the list of the text files
+    pub text_list: u64,
+    /// requests of a text file
+    pub text: u64,
+    /// requests of the list of the media
+    pub media_list: u64,
+    /// requests of a media
+    pub media: u64,
+}
+
+impl RequestCounters {
+    /// counts a request
+    fn count(&mut self, request: &Request) {
+        let counter: &mut u64 = match request {
+            Request::Type => &mut self.server_type,
+            Request::Text(TextRequest::TextList) => &mut self.text_list,
+            Request::Text(TextRequest::Text(_)) => &mut self.text,
+            Request::Media(MediaRequest::MediaList) => &mut self.media_list,
+            Request::Media(MediaRequest::Media(_)) => &mut self.media,
+        };
+        *counter += 1;
+    }
+}
+
+/// Bytes of the serialized file responses before and after the compression
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CompressedBytes {
+    /// bytes before the compression
+    pub uncompressed: u64,
+    /// bytes after the compression
+    pub compressed: u64,
+}
+
+impl CompressedBytes {
+    /// ratio between the compressed and the uncompressed size, [None] if no byte was sent.
+    /// The precision lost converting the counters is negligible for a ratio
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn ratio(&self) -> Option<f64> {
+        (self.uncompressed > 0).then(|| self.compressed as f64 / self.uncompressed as f64)
+    }
+}
+
+/// Compression achieved on the file responses sent by a [`GenericServer`], by type of
+/// file (see [`file_type`]). The responses taken from the content cache are not counted
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CompressionRatios {
+    /// bytes of the responses, by type of file
+    by_type: BTreeMap<String, CompressedBytes>,
+}
+
+impl CompressionRatios {
+    /// bytes of the responses containing files of the given type, e.g. `"png"`
+    #[must_use]
+    pub fn get(&self, file_type: &str) -> CompressedBytes {
+        self.by_type.get(file_type).copied().unwrap_or_default()
+    }
+
+    /// types of the files sent, in order, with the bytes of their responses
+    pub fn iter(&self) -> impl Iterator<Item = (&str, CompressedBytes)> {
+        self.by_type
+            .iter()
+            .map(|(file_type, bytes): (&String, &CompressedBytes)| (file_type.as_str(), *bytes))
+    }
+
+    /// counts the bytes of a response containing a file of the given type
+    fn count(&mut self, file_type: &str, uncompressed: u64, compressed: u64) {
+        let counter: &mut CompressedBytes = self.by_type.entry(file_type.to_string()).or_default();
+        counter.uncompressed += uncompressed;
+        counter.compressed += compressed;
+    }
+}
+
+/// type of the file at `path` in the [`CompressionRatios`]: its extension in lowercase,
+/// e.g. `"png"` or `"html"`, empty if the file has none
+#[must_use]
+pub fn file_type(path: &Path) -> String {
+    path.extension()
+        .map(|ext: &OsStr| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Snapshot of the metrics of a [`GenericServer`], counted since its creation
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ServerMetrics {
+    /// id of the server
+    pub server_id: NodeId,
+    /// fragments of requests received
+    pub fragments_received: u64,
+    /// fragments of responses sent, including the retransmissions
+    pub fragments_sent: u64,
+    /// acks received for the fragments of the responses
+    pub acks_received: u64,
+    /// acks sent for the fragments of the requests
+    pub acks_sent: u64,
+    /// nacks received, by type
+    pub nacks: NackCounters,
+    /// fragments sent again after a nack or a timeout
+    pub retransmissions: u64,
+    /// floods started by the server
+    pub floods_started: u64,
+    /// bytes of the serialized responses, before the compression. The responses taken
+    /// from the content cache are not counted
+    pub bytes_uncompressed: u64,
+    /// bytes of the serialized responses, after the compression
+    pub bytes_compressed: u64,
+    /// compression achieved on the file responses, by type of file
+    pub compression: CompressionRatios,
+    /// requests received, by kind
+    pub requests: RequestCounters,
+    /// erroneous requests received
+    pub errors: ErrorCounters,
+    /// hits and misses of the content cache
+    pub cache: CacheStats,
+    /// fragments waiting for a route
+    pub pending_packets: usize,
+    /// fragments sent and not yet acknowledged or given up
+    pub sent_history: usize,
+}
+
+impl ServerMetrics {
+    /// counts a received nack
+    pub(super) fn count_nack(&mut self, nack_type: &NackType) {
+        self.nacks.count(nack_type);
+    }
+
+    /// counts a received request
+    pub(super) fn count_request(&mut self, request: &Request) {
+        self.requests.count(request);
+    }
+
+    /// counts the bytes of a response before and after the compression, `file_type`
+    /// is the type of the file contained in the response, if any
+    pub(super) fn count_bytes(
+        &mut self,
+        file_type: Option<&str>,
+        uncompressed: usize,
+        compressed: usize,
+    ) {
+        self.bytes_uncompressed += uncompressed as u64;
+        self.bytes_compressed += compressed as u64;
+        if let Some(file_type) = file_type {
+            self.compression
+                .count(file_type, uncompressed as u64, compressed as u64);
+        }
+    }
+}
+
+impl<T: ServerType> GenericServer<T> {
+    /// current metrics of the [`GenericServer`]
+    #[must_use]
+    pub fn metrics(&self) -> ServerMetrics {
+        ServerMetrics {
+            server_id: self.id,
+            errors: self.error_counters,
+            cache: self.cache_stats(),
+            pending_packets: self.pending_packets.len() + self.stalled_fragments(),
+            sent_history: self.sent_history.len(),
+            ..self.metrics.clone()
+        }
+    }
+}
@@ -0,0 +1,146 @@
+#[cfg(test)]
+mod metrics_tests {
+    use common::web_messages::{Compression, RequestMessage, Serializable};
+    use crossbeam_channel::Receiver;
+    use wg_2024::{
+        network::SourceRoutingHeader,
+        packet::{Fragment, Nack, NackType, Packet, PacketType},
+    };
+
+    use crate::servers::{
+        extensions::{ExtendedCommand, ExtendedEvent},
+        serialization::fragment_response,
+        test_utils::{get_dummy_builder, get_routed_server},
+        GenericServer, NackCounters, RequestCounters, ServerMetrics, Text,
+    };
+
+    /// get a [`GenericServer<Text>`] with a route to the client 2, the packets sent
+    /// to the drone 1 can be read from the returned channel
+    fn get_server() -> (GenericServer<Text>, Receiver<Packet>) {
+        get_routed_server(get_dummy_builder(0), &[2])
+    }
+
+    /// delivers a request to the server, fragment by fragment
+    fn deliver(server: &mut GenericServer<Text>, request: &RequestMessage) -> u64 {
+        let data: Vec<[u8; 128]> = fragment_response(request.serialize().unwrap());
+        let total: u64 = data.len() as u64;
+        for (i, frag) in data.into_iter().enumerate() {
+            server.handle_packet(Packet::new_fragment(
+                SourceRoutingHeader::new(vec![2, 1, 0], 2),
+                0,
+                Fragment {
+                    fragment_index: i as u64,
+                    total_n_fragments: total,
+                    length: 128,
+                    data: frag,
+                },
+            ));
+        }
+        server.wait_workers();
+        total
+    }
+
+    /// tests the counters of the requests and of the fragments exchanged to answer them
+    #[test]
+    fn test_request_metrics() {
+        let (mut server, dr) = get_server();
+        let received: u64 = deliver(
+            &mut server,
+            &RequestMessage::new_type_request(2, Compression::LZW),
+        );
+        let received: u64 = received
+            + deliver(
+                &mut server,
+                &RequestMessage::new_text_list_request(2, Compression::None),
+            );
+        let sent: Vec<Packet> = dr
+            .try_iter()
+            .filter(|p: &Packet| matches!(p.pack_type, PacketType::MsgFragment(_)))
+            .collect();
+
+        let metrics: ServerMetrics = server.metrics();
+        assert_eq!(
+            metrics.requests,
+            RequestCounters {
+                server_type: 1,
+                text_list: 1,
+                ..RequestCounters::default()
+            }
+        );
+        assert!(metrics.fragments_received == received);
+        assert!(metrics.acks_sent == received);
+        assert!(metrics.fragments_sent == sent.len() as u64);
+        assert!(metrics.sent_history == sent.len());
+        assert!(metrics.bytes_uncompressed > 0);
+        assert!(metrics.bytes_compressed > 0);
+
+        // the acknowledged fragments leave the sent history
+        for p in &sent {
+            if let PacketType::MsgFragment(f) = &p.pack_type {
+                server.handle_packet(Packet::new_ack(
+                    SourceRoutingHeader::new(vec![2, 1, 0], 2),
+                    p.session_id,
+                    f.fragment_index,
+                ));
+            }
+        }
+        let metrics: ServerMetrics = server.metrics();
+        assert!(metrics.acks_received == sent.len() as u64);
+        assert!(metrics.sent_history == 0);
+    }
+
+    /// tests the counters of the nacks and of the retransmissions
+    #[test]
+    fn test_nack_metrics() {
+        let (mut server, dr) = get_server();
+        server.send_fragments(
+            &SourceRoutingHeader::new(vec![2, 1, 0], 2),
+            2,
+            7,
+            vec![[0; 128]],
+        );
+        let sid: u64 = dr.try_recv().unwrap().session_id;
+        let nack = |nack_type: NackType| {
+            Packet::new_nack(
+                SourceRoutingHeader::new(vec![1, 0], 1),
+                sid,
+                Nack {
+                    fragment_index: 0,
+                    nack_type,
+                },
+            )
+        };
+        server.handle_packet(nack(NackType::Dropped));
+        server.handle_packet(nack(NackType::Dropped));
+        server.handle_packet(nack(NackType::UnexpectedRecipient(1)));
+
+        let metrics: ServerMetrics = server.metrics();
+        assert_eq!(
+            metrics.nacks,
+            NackCounters {
+                dropped: 2,
+                unexpected_recipient: 1,
+                ..NackCounters::default()
+            }
+        );
+        assert!(metrics.retransmissions == 3);
+        assert!(metrics.fragments_sent == 4);
+        assert!(dr.try_iter().count() == 3);
+    }
+
+    /// tests that the metrics command is answered with an [`ExtendedEvent::Metrics`]
+    #[test]
+    fn test_metrics_command() {
+        let (ext_send, ext_recv) = crossbeam_channel::unbounded();
+        let mut server: GenericServer<Text> =
+            get_dummy_builder(3).extended_events(ext_send).build();
+        server.flood();
+        server.handle_extended_command(&ExtendedCommand::Metrics);
+        let Ok(ExtendedEvent::Metrics(metrics)) = ext_recv.try_recv() else {
+            panic!("expected the metrics of the server");
+        };
+        assert_eq!(metrics, server.metrics());
+        assert!(metrics.server_id == 3);
+        assert!(metrics.floods_started == 1);
+    }
+}
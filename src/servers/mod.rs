@@ -1,48 +1,104 @@
 /*
  * TODOS: ETX with packet count + exponentially moving average
- *        Multithreading (prob there's no time >.<)
  *        Test SimController behaviour
  */
 
 use std::{
     collections::{HashMap, VecDeque},
     marker::PhantomData,
+    time::{Duration, Instant},
 };
 
+pub use auto_compression::{compression_tag, decode_response, CompressionPolicy};
+pub use builder::GenericServerBuilder;
+pub use content::{ContentError, ContentRoot};
+pub use metrics::{
+    file_type, CompressedBytes, CompressionRatios, NackCounters, RequestCounters, ServerMetrics,
+};
+pub use ranges::{ByteRange, RangeResponse, RangedName, MAX_RANGE_LEN};
+pub use requests_handling::CacheStats;
+pub use routing::{
+    BetaEstimator, EstimatorState, EwmaEstimator, FnEstimator, PdrEstimator, RoutingStateError,
+    WindowEstimator,
+};
+pub use services::{FileKind, MediaService, Reply, RequestService, TextMediaService, TextService};
+pub use topology::{EdgeSnapshot, NodeSnapshot, TopologySnapshot};
+
 use common::{
     networking::flooder::Flooder,
     ring_buffer::RingBuffer,
     slc_commands::{ServerCommand, ServerEvent},
     Server,
 };
-use crossbeam_channel::{select_biased, Receiver, Sender};
+use crossbeam_channel::{never, select_biased, tick, Receiver, Sender};
+use extensions::{ErrorCounters, ExtendedCommand, ExtendedEvent};
+use flow_control::{FlowConfig, SendWindows};
 use log::{info, warn};
+use networking::{FloodConfig, OutstandingFlood};
 use petgraph::prelude::DiGraphMap;
-use routing::{PdrEstimator, RoutingTable};
+use reassembly::{ReassemblyConfig, RejectedRequests};
+use requests_handling::ContentCache;
+use retransmission::RetransmissionConfig;
+use routing::{RoutingTable, StateFile};
+use shutdown::Lifecycle;
+use streaming::StreamLayouts;
 use wg_2024::{
     network::{NodeId, SourceRoutingHeader},
     packet::{Packet, PacketType, FRAGMENT_DSIZE},
 };
+use workers::WorkerPool;
 
+/// Module containing the automatic choice of the compression of the responses
+mod auto_compression;
+/// Module containing the [`GenericServerBuilder`], used to configure a [`GenericServer`]
+/// instance before creating it
+mod builder;
+/// Module containing the content root used to safely resolve the files
+/// requested by the clients
+mod content;
+/// Module containing the events sent to the controller that are not part of
+/// the [`ServerEvent`]s defined by the common protocol
+pub mod extensions;
+/// Module containing the congestion windows of the responses
+mod flow_control;
+/// Module containing the metrics reported to the controller
+mod metrics;
 /// Module containing the necessary netowrking functions to discover the network
 mod networking;
 /// Module containing the necessary functions to handle received packets
 mod packet_handling;
+/// Module containing the requests of a range of bytes of a file
+mod ranges;
+/// Module containing the limits on the requests being reassembled
+mod reassembly;
 /// Module containing the necessary functions to handle received requests and
 /// handle/create associated responses
 mod requests_handling;
+/// Module containing the retransmission timer of the unacknowledged fragments
+mod retransmission;
 /// Module containing the necessary routing functions to find route paths and
 /// estimate drone ETXs
 mod routing;
 /// Module containing auxiliary functions for the serialization and deserialization
 /// of received/sended packets
 mod serialization;
+/// Module containing the [`RequestService`]s, which decide how a [`GenericServer`]
+/// answers the requests
+mod services;
+/// Module containing the shutdown of the [`GenericServer`]
+mod shutdown;
+/// Module containing the file responses read while they are sent
+mod streaming;
 /// Test module
 #[cfg(test)]
 mod test;
 /// Common utilities for testing
 #[cfg(test)]
 mod test_utils;
+/// Module containing the snapshots of the network topology known by a [`GenericServer`]
+mod topology;
+/// Module containing the pool of threads that encode the responses
+mod workers;
 
 /// Struct containing the necessary information to update and resend a packet in case of a Nack
 #[derive(Debug, Clone)]
@@ -58,6 +114,11 @@ struct HistoryEntry {
     n_frags: u64,
     /// The actual fragment
     frag: [u8; 128],
+    /// Number of retransmissions caused by a timeout
+    attempts: u32,
+    /// Instant after which the fragment is retransmitted,
+    /// [None] if the fragment is waiting in the pending queue
+    deadline: Option<Instant>,
 }
 
 impl HistoryEntry {
@@ -77,14 +138,43 @@ impl HistoryEntry {
             frag_idx,
             n_frags,
             frag,
+            attempts: 0,
+            deadline: None,
+        }
+    }
+}
+
+/// Struct containing the fragments received so far of a request
+#[derive(Debug, Clone)]
+struct PartialRequest {
+    /// Number of fragments received
+    received: u64,
+    /// Fragments of the request, indexed by their fragment index
+    fragments: Vec<[u8; FRAGMENT_DSIZE]>,
+    /// Bitmap of the indexes of the received fragments
+    received_idx: Vec<u64>,
+    /// Instant of the last received fragment, used to drop abandoned requests
+    last_update: Instant,
+}
+
+impl PartialRequest {
+    /// Creates a new empty [`PartialRequest`] of `n_frags` fragments
+    #[inline]
+    #[must_use]
+    fn new(n_frags: usize, now: Instant) -> Self {
+        Self {
+            received: 0,
+            fragments: vec![[0; FRAGMENT_DSIZE]; n_frags],
+            received_idx: vec![0; n_frags.div_ceil(64)],
+            last_update: now,
         }
     }
 }
 
 /// Data structure used to handle received fragments and map them to the related
 /// request id
-/// maps (`SenderId`, rid) -> (#`recv_fragments`, fragments)
-type FragmentHistory = HashMap<(NodeId, u16), (u64, Vec<[u8; FRAGMENT_DSIZE]>)>;
+/// maps (`SenderId`, rid) -> [`PartialRequest`]
+type FragmentHistory = HashMap<(NodeId, u16), PartialRequest>;
 /// Data structure used to cache sended packets that are yet to be acknowledged
 type MessageHistory = HashMap<u64, HistoryEntry>;
 /// Data structure used to remember already seen flood ids
@@ -96,9 +186,9 @@ type NetworkGraph = DiGraphMap<NodeId, f64>;
 /// to the server not being able to find a route when they were handled
 type PendingQueue = VecDeque<u64>;
 
-/// path of the [`TextServer`] files
+/// default path of the [`TextServer`] files
 const TEXT_PATH: &str = "./public/";
-/// path of the [`MediaServer`] files
+/// default path of the [`MediaServer`] files
 const MEDIA_PATH: &str = "./media/";
 /// Initial pdr assigned to the drone (note PDR = 1 / ETX), we use a uniform approach so
 /// the initial value is 0.5. another approach could be to set the initial value to
@@ -108,13 +198,22 @@ const INITIAL_PDR: f64 = 0.5; // Beta(1, 1), is a baesyan approach better?
 const INITIAL_ETX: f64 = 1. / INITIAL_PDR;
 /// default window size used by the ETX estimator for the EWMA
 const DEFAULT_WINDOW_SZ: u32 = 12;
-/// alpha constant of the EWMA
+/// default alpha constant of the EWMA, beta is `1 - alpha`
 const DEFAULT_ALPHA: f64 = 0.35;
-/// beta constant of the EWMA
-const DEFAULT_BETA: f64 = 1. - DEFAULT_ALPHA;
+/// period of the periodic tasks of the [`GenericServer`], e.g. the retransmission timer
+const HOUSEKEEPING_PERIOD: Duration = Duration::from_millis(50);
 
 /// Marker trait used to represent the `ServerType` of a [`GenericServer`]
-pub trait ServerType {}
+pub trait ServerType {
+    /// directory containing the served files, used when the
+    /// [`GenericServerBuilder`] doesn't specify one
+    const DEFAULT_CONTENT_PATH: &'static str;
+
+    /// creates the [`RequestService`] used when the [`GenericServerBuilder`]
+    /// doesn't specify one, `media` is the root of the media of the servers
+    /// answering both text and media requests
+    fn default_service(content: ContentRoot, media: ContentRoot) -> Box<dyn RequestService>;
+}
 
 /// One of the two default types of a [`GenericServer`], the [`MediaServer`]
 /// handles requests related to the images contained in the files sent
@@ -124,13 +223,35 @@ pub struct Media {}
 /// handles file requests. The default format used is html so that also
 /// images can be embedded in the document, if needed
 pub struct Text {}
+/// Type of a [`GenericServer`] that answers both the requests of the [`TextServer`]
+/// and the ones of the [`MediaServer`], useful for networks with a single server.
+/// The text files and the media are served from two different directories
+pub struct TextMedia {}
+
+impl ServerType for Media {
+    const DEFAULT_CONTENT_PATH: &'static str = MEDIA_PATH;
+
+    fn default_service(content: ContentRoot, _media: ContentRoot) -> Box<dyn RequestService> {
+        Box::new(MediaService::new(content))
+    }
+}
+impl ServerType for Text {
+    const DEFAULT_CONTENT_PATH: &'static str = TEXT_PATH;
 
-impl ServerType for Media {}
-impl ServerType for Text {}
+    fn default_service(content: ContentRoot, _media: ContentRoot) -> Box<dyn RequestService> {
+        Box::new(TextService::new(content))
+    }
+}
+impl ServerType for TextMedia {
+    const DEFAULT_CONTENT_PATH: &'static str = TEXT_PATH;
+
+    fn default_service(content: ContentRoot, media: ContentRoot) -> Box<dyn RequestService> {
+        Box::new(TextMediaService::new(content, media))
+    }
+}
 
-/// Trait utilized to speicalise [`GenericServer`<T: `ServerType`>]. This trait
-/// allows to specify how the server should handle the received protocol
-/// requests based on its [`ServerType`]
+/// Trait used to handle the fully received protocol requests. Every [`GenericServer`]
+/// implements it by answering the requests with its [`RequestService`]
 pub trait RequestHandler {
     /// Function to implement the desired behaviour of a specialised [`GenericServer`]
     fn handle_request(
@@ -146,11 +267,14 @@ pub trait RequestHandler {
 pub type TextServer = GenericServer<Text>;
 /// Handy type alias for a [`GenericServer<Media>`]
 pub type MediaServer = GenericServer<Media>;
+/// Handy type alias for a [`GenericServer<TextMedia>`]
+pub type TextMediaServer = GenericServer<TextMedia>;
 
 /// Struct containing all the necessary information for a server to correctly
 /// handle received packets according to the network protocol. <br>
-/// Requires a generic type that implements [`ServerType`] and the trait [`RequestHandler`]
-/// to implement the desired behaviour in the high level protocol
+/// Requires a generic type that implements [`ServerType`]; the requests of the high level
+/// protocol are answered by a [`RequestService`], either the one given to the
+/// [`GenericServerBuilder`] or the default one of the [`ServerType`]
 pub struct GenericServer<T: ServerType> {
     /// id of the node
     id: NodeId,
@@ -161,6 +285,20 @@ pub struct GenericServer<T: ServerType> {
     /// flag to indicate wheter or not the server needs
     /// to start a new flood
     need_flood: bool,
+    /// instant of the first flood request not yet handled, if any
+    flood_requested: Option<Instant>,
+    /// instant of the start of the last flood, if any
+    last_flood: Option<Instant>,
+    /// configuration of the floods
+    flooding: FloodConfig,
+    /// floods whose responses are still being collected, mapped to their flood id
+    outstanding_floods: HashMap<u64, OutstandingFlood>,
+    /// file where the routing state is periodically saved, if any
+    state_file: Option<StateFile>,
+    /// phase of the life of the server
+    lifecycle: Lifecycle,
+    /// maximum time waited for the in-flight responses during a graceful shutdown
+    shutdown_timeout: Duration,
     /// flag to signal an update in the network graph.
     /// this is useful as it allows to know when to try
     /// sending again the pending packets
@@ -179,6 +317,11 @@ pub struct GenericServer<T: ServerType> {
     flood_history: FloodHistory,
     /// history of received fragments, mapped to their rid
     fragment_history: FragmentHistory,
+    /// requests refused due to the reassembly limits, mapped to the instant
+    /// of their last received fragment
+    rejected_requests: RejectedRequests,
+    /// limits on the requests being reassembled
+    reassembly: ReassemblyConfig,
     /// history of sended messages still waiting to be acknowledged
     sent_history: MessageHistory,
     /// the network graph with the necessary estimators for [Packet]
@@ -186,6 +329,34 @@ pub struct GenericServer<T: ServerType> {
     network_graph: RoutingTable,
     /// queue of [Packet]s waiting to be re sent
     pending_packets: PendingQueue,
+    /// windows of the responses being sent
+    send_windows: SendWindows,
+    /// configuration of the windows of the responses
+    flow: FlowConfig,
+    /// maximum number of disjoint routes used to send the fragments of a response
+    max_paths: usize,
+    /// channel to communicate [`ExtendedEvent`]s to the controller, if any
+    extended_send: Option<Sender<ExtendedEvent>>,
+    /// channel to receive [`ExtendedCommand`]s from the controller
+    extended_recv: Receiver<ExtendedCommand>,
+    /// counters of the packets and requests handled by the server
+    metrics: ServerMetrics,
+    /// counters of the erroneous requests
+    error_counters: ErrorCounters,
+    /// retransmission timer configuration
+    retransmission: RetransmissionConfig,
+    /// cache of the compressed responses to file requests
+    content_cache: ContentCache,
+    /// threads answering the requests with the service, serializing and compressing
+    /// the responses
+    workers: WorkerPool,
+    /// size of the files whose uncompressed responses are streamed, [None] if
+    /// the responses are never streamed
+    streaming_threshold: Option<u64>,
+    /// layouts of the streamed responses, computed on the first stream of each kind
+    stream_layouts: StreamLayouts,
+    /// policy used to choose the compression of the responses
+    compression_policy: CompressionPolicy,
     /// marker used to specify the [`GenericServer`]'s type
     _marker: PhantomData<T>,
 }
@@ -198,10 +369,8 @@ pub struct GenericServer<T: ServerType> {
 /// where ETX(n) is the ETX at time n
 /// p(n) is the estimated ETX at time n, estimated from the last [`DEFAULT_WINDOW_SZ`] samples
 /// alpha and beta are parameters that decide how fast the ETX adapts to change
-fn default_estimator() -> PdrEstimator {
-    PdrEstimator::new(DEFAULT_WINDOW_SZ, |old: f64, acks: u32, nacks: u32| {
-        DEFAULT_ALPHA * (f64::from(acks) / f64::from(acks + nacks)) + DEFAULT_BETA * old
-    })
+fn default_estimator() -> EwmaEstimator {
+    EwmaEstimator::default()
 }
 
 impl<T: ServerType> GenericServer<T>
@@ -215,14 +384,17 @@ where
         match packet.pack_type {
             PacketType::MsgFragment(frag) => {
                 info!(target: &self.target_topic, "Received message fragment {frag}");
+                self.metrics.fragments_received += 1;
                 self.handle_fragment(&srch, sid, &frag);
             }
             PacketType::Ack(ack) => {
                 info!(target: &self.target_topic, "Received ack {ack}");
+                self.metrics.acks_received += 1;
                 self.handle_ack(sid, &ack);
             }
             PacketType::Nack(nack) => {
                 info!(target: &self.target_topic, "Received nack {nack}");
+                self.metrics.count_nack(&nack.nack_type);
                 self.handle_nack(sid, &srch, &nack);
             }
             PacketType::FloodRequest(mut fr) => {
@@ -240,6 +412,17 @@ where
         }
     }
 
+    /// periodic tasks of the [`GenericServer`], executed every [`HOUSEKEEPING_PERIOD`]
+    fn housekeeping(&mut self) {
+        let now: Instant = Instant::now();
+        self.retransmit_expired(now);
+        self.drop_stale_requests(now);
+        self.network_graph.decay(now);
+        self.settle_floods(now);
+        self.refresh_topology(now);
+        self.autosave_routing_state(now);
+    }
+
     /// function to handle command based on it's internal type
     fn handle_command(&mut self, command: ServerCommand) {
         match command {
@@ -247,13 +430,12 @@ where
                 self.packet_send.insert(node_id, channel);
                 self.check_and_add_edge(self.id, node_id);
                 // self.network_graph.check_and_add_edge(node_id, self.id);
-                self.need_flood = true;
+                self.request_flood(Instant::now());
                 info!(target: &self.target_topic, "Received add sender command, sender id: {node_id}");
             }
             ServerCommand::RemoveSender(node_id) => {
                 self.packet_send.remove(&node_id);
-                self.network_graph.remove_node(node_id);
-                self.need_flood = true;
+                self.handle_node_removal(node_id, Instant::now());
                 info!(target: &self.target_topic, "Received remove sender command, sender id: {node_id}");
             }
             ServerCommand::Shortcut(p) => {
@@ -268,7 +450,8 @@ impl<T: ServerType + Send> Server for GenericServer<T>
 where
     GenericServer<T>: RequestHandler,
 {
-    /// creates a new [`GenericServer`] from the given network channels
+    /// creates a new [`GenericServer`] from the given network channels,
+    /// with the default configuration (see [`GenericServerBuilder`])
     fn new(
         id: NodeId,
         controller_send: Sender<ServerEvent>,
@@ -279,67 +462,70 @@ where
     where
         Self: Sized,
     {
-        let mut network_graph: RoutingTable = RoutingTable::new(default_estimator());
-        for did in packet_send.keys() {
-            network_graph.check_and_add_edge(id, *did);
-        }
-
-        GenericServer {
+        GenericServerBuilder::new(
             id,
-            target_topic: format!("Server[{id}]"),
-            session_id: 0,
-            need_flood: true,
-            graph_updated: false,
             controller_send,
             controller_recv,
             packet_recv,
             packet_send,
-            flood_history: HashMap::new(),
-            fragment_history: HashMap::new(),
-            sent_history: HashMap::new(),
-            network_graph,
-            pending_packets: VecDeque::new(),
-            _marker: PhantomData,
-        }
+        )
+        .build()
     }
 
-    /// main loop of the [`GenericServer`]
+    /// main loop of the [`GenericServer`], returns after a shutdown. The server shuts down
+    /// gracefully when the controller disconnects and immediately when no packet can
+    /// be received anymore
     fn run(&mut self) {
+        let housekeeping: Receiver<Instant> = tick(HOUSEKEEPING_PERIOD);
         loop {
-            if self.need_flood {
+            if self.should_stop(Instant::now()) {
+                break;
+            } else if self.flood_due(Instant::now()) {
                 info!(target: &self.target_topic, "Starting new flood request to construct network");
                 self.flood();
-            } else if self.graph_updated && !self.pending_packets.is_empty() {
-                let sid = self.pending_packets.pop_back().unwrap();
-                info!(target: &self.target_topic, "Trying to resend packet with sid: {sid}");
-                let fragment: Option<&HistoryEntry> = self.sent_history.get(&sid);
-
-                if let Some(entry) = fragment {
-                    let HistoryEntry {
-                        hops: _,
-                        receiver_id,
-                        frag_idx,
-                        n_frags,
-                        frag,
-                    } = *entry;
-                    self.resend_packet(sid, receiver_id, frag_idx, n_frags, frag);
-                } else {
-                    warn!(target: &self.target_topic, "CRITICAL: cannot find pending packet in sent history!");
-                }
+            } else if self.graph_updated
+                && (!self.pending_packets.is_empty() || self.has_stalled_windows())
+            {
+                self.resend_pending();
             } else {
                 select_biased! {
                     recv(self.controller_recv) -> command => {
                         if let Ok(command) = command {
                             self.handle_command(command);
+                        } else {
+                            warn!(target: &self.target_topic, "Controller disconnected");
+                            self.controller_recv = never();
+                            self.begin_shutdown(Instant::now());
+                        }
+                    },
+                    recv(self.extended_recv) -> command => {
+                        if let Ok(command) = command {
+                            self.handle_extended_command(&command);
+                        } else {
+                            self.extended_recv = never();
+                        }
+                    },
+                    recv(housekeeping) -> _ => {
+                        self.housekeeping();
+                    },
+                    recv(self.workers.results) -> encoded => {
+                        if let Ok(encoded) = encoded {
+                            self.finish(encoded);
+                        } else {
+                            self.workers_stopped();
                         }
                     },
                     recv(self.packet_recv) -> packet => {
                         if let Ok(packet) = packet {
                             self.handle_packet(packet);
+                        } else {
+                            warn!(target: &self.target_topic, "Packet channel disconnected");
+                            self.crash();
                         }
                     }
                 }
             }
         }
+        self.workers.join();
     }
 }
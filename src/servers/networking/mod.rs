@@ -1,3 +1,5 @@
+use std::time::{Duration, Instant};
+
 use common::{networking::flooder::Flooder, ring_buffer::RingBuffer, slc_commands::ServerEvent};
 use crossbeam_channel::Sender;
 use log::{error, info, warn};
@@ -13,6 +15,72 @@ use crate::protocol_utils as network_protocol;
 #[cfg(test)]
 mod test;
 
+/// default time without flood responses after which a flood is considered complete
+const DEFAULT_QUIET_PERIOD: Duration = Duration::from_millis(100);
+/// default maximum time waited for the responses of a flood
+const DEFAULT_SETTLE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Configuration of the floods started by a [`GenericServer`] after a change of the
+/// topology. The default one floods the network as soon as a change is detected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FloodConfig {
+    /// minimum time between the start of two floods
+    pub(crate) min_interval: Duration,
+    /// time waited after a flood is requested, so that the changes happening
+    /// within this time are handled by a single flood
+    pub(crate) debounce: Duration,
+    /// time after which the network is flooded again even if no change was
+    /// detected, [None] if there is no background refresh
+    pub(crate) refresh_interval: Option<Duration>,
+    /// whether the removal of a node triggers a flood only if some known
+    /// client or server can no longer be reached
+    pub(crate) targeted_repair: bool,
+    /// time without responses after which a flood is considered complete
+    pub(crate) quiet_period: Duration,
+    /// maximum time waited for the responses of a flood
+    pub(crate) settle_timeout: Duration,
+}
+
+impl Default for FloodConfig {
+    fn default() -> Self {
+        Self {
+            min_interval: Duration::ZERO,
+            debounce: Duration::ZERO,
+            refresh_interval: None,
+            targeted_repair: false,
+            quiet_period: DEFAULT_QUIET_PERIOD,
+            settle_timeout: DEFAULT_SETTLE_TIMEOUT,
+        }
+    }
+}
+
+/// Flood started by the [`GenericServer`] whose responses are still being collected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct OutstandingFlood {
+    /// instant of the start of the flood
+    started: Instant,
+    /// instant of the last received response, if any
+    last_response: Option<Instant>,
+}
+
+impl OutstandingFlood {
+    /// checks if the flood can be considered complete at `now`: either no response
+    /// was received in the quiet period or the timeout expired
+    fn settled(&self, config: &FloodConfig, now: Instant) -> bool {
+        self.last_response
+            .is_some_and(|t: Instant| now >= t + config.quiet_period)
+            || now >= self.started + config.settle_timeout
+    }
+}
+
+/// checks if at least `duration` has passed between `since` and `now`, true if `since` is [None]
+fn elapsed(since: Option<Instant>, now: Instant, duration: Duration) -> bool {
+    match since {
+        Some(t) => now >= t + duration,
+        None => true,
+    }
+}
+
 /// trait to handle flood requests
 impl<T: ServerType> Flooder for GenericServer<T> {
     /// [`NodeType`] of the [`GenericServer`]
@@ -49,7 +117,9 @@ impl<T: ServerType> Flooder for GenericServer<T> {
 
 impl<T: ServerType> GenericServer<T> {
     /// handles a flood response, if the initiator is the Server itself, it udaptes the
-    /// netowrk graph. else if forward the packet as required by the protocol
+    /// netowrk graph. else if forward the packet as required by the protocol.
+    /// The responses of a flood that is still being collected don't signal an update of
+    /// the graph, which is signaled once the flood settles (see [`GenericServer::settle_floods`])
     pub(super) fn handle_flood_response(
         &mut self,
         mut srch: SourceRoutingHeader,
@@ -60,7 +130,11 @@ impl<T: ServerType> GenericServer<T> {
             // match guard
             Some((id, _)) if *id == self.id => {
                 self.update_network_from_flood(&fr);
-                self.graph_updated = true;
+                if let Some(flood) = self.outstanding_floods.get_mut(&fr.flood_id) {
+                    flood.last_response = Some(Instant::now());
+                } else {
+                    self.graph_updated = true;
+                }
             }
             Some(_) => match srch.next_hop() {
                 Some(next_id) => {
@@ -85,6 +159,66 @@ impl<T: ServerType> GenericServer<T> {
         }
     }
 
+    /// asks for a new flood, which is started by the main loop once allowed by the [`FloodConfig`]
+    pub(super) fn request_flood(&mut self, now: Instant) {
+        self.need_flood = true;
+        self.flood_requested.get_or_insert(now);
+    }
+
+    /// checks if a flood was requested and can be started at `now`
+    pub(super) fn flood_due(&self, now: Instant) -> bool {
+        let FloodConfig {
+            min_interval,
+            debounce,
+            ..
+        } = self.flooding;
+        self.need_flood
+            && elapsed(self.flood_requested, now, debounce)
+            && elapsed(self.last_flood, now, min_interval)
+    }
+
+    /// requests a background flood if the last one is older than the refresh interval
+    pub(super) fn refresh_topology(&mut self, now: Instant) {
+        if let Some(interval) = self.flooding.refresh_interval {
+            if !self.need_flood
+                && self
+                    .last_flood
+                    .is_some_and(|t: Instant| now >= t + interval)
+            {
+                info!(target: &self.target_topic, "Refreshing the network topology");
+                self.request_flood(now);
+            }
+        }
+    }
+
+    /// removes a node that crashed or is no longer reachable. With the targeted repair
+    /// the routes are recomputed on the remaining graph and the network is flooded
+    /// only if some known client or server can no longer be reached
+    pub(super) fn handle_node_removal(&mut self, id: NodeId, now: Instant) {
+        self.network_graph.remove_node(id);
+        if self.flooding.targeted_repair && self.network_graph.reaches_endpoints(self.id) {
+            info!(target: &self.target_topic, "Removed node {id}, the known endpoints are still reachable");
+            self.graph_updated = true;
+        } else {
+            self.request_flood(now);
+        }
+    }
+
+    /// removes the outstanding floods that settled before `now`, once all of them
+    /// settled the graph is complete and the pending packets can be sent again
+    pub(super) fn settle_floods(&mut self, now: Instant) {
+        if self.outstanding_floods.is_empty() {
+            return;
+        }
+        let config: FloodConfig = self.flooding;
+        self.outstanding_floods
+            .retain(|_, f: &mut OutstandingFlood| !f.settled(&config, now));
+        if self.outstanding_floods.is_empty() {
+            info!(target: &self.target_topic, "Flood settled, network graph updated");
+            self.graph_updated = true;
+        }
+    }
+
     /// starts a new [`FloodRequest`]
     pub(super) fn flood(&mut self) {
         let flood: Packet = Packet::new_flood_request(
@@ -96,6 +230,14 @@ impl<T: ServerType> GenericServer<T> {
             .entry(self.id)
             .or_insert(RingBuffer::with_capacity(64))
             .insert(self.session_id);
+        let now: Instant = Instant::now();
+        self.outstanding_floods.insert(
+            self.session_id,
+            OutstandingFlood {
+                started: now,
+                last_response: None,
+            },
+        );
         self.session_id = network_protocol::next_sid(self.session_id);
         for (id, c) in &self.packet_send {
             info!(target: &self.target_topic, "Sending flood request to {id}");
@@ -103,5 +245,8 @@ impl<T: ServerType> GenericServer<T> {
         }
         let _ = self.controller_send.send(ServerEvent::PacketSent(flood));
         self.need_flood = false;
+        self.metrics.floods_started += 1;
+        self.flood_requested = None;
+        self.last_flood = Some(now);
     }
 }
@@ -1,6 +1,10 @@
 #[cfg(test)]
 mod networking_tests {
-    use std::{collections::HashMap, thread, time::Duration};
+    use std::{
+        collections::HashMap,
+        thread,
+        time::{Duration, Instant},
+    };
 
     use ap2024_unitn_cppenjoyers_drone::CppEnjoyersDrone;
     use common::{
@@ -16,10 +20,11 @@ mod networking_tests {
     };
 
     use crate::servers::{
-        test_utils::graphmap_eq, GenericServer, HistoryEntry, NetworkGraph, Text, INITIAL_ETX,
+        self, routing::RoutingTable, test_utils::graphmap_eq, GenericServer, HistoryEntry,
+        NetworkGraph, Text, INITIAL_ETX,
     };
 
-    use crate::servers::test_utils::get_dummy_server_text;
+    use crate::servers::test_utils::{get_dummy_builder, get_dummy_server_text};
 
     /// tests correct behaviour of the flood buffer
     #[test]
@@ -64,7 +69,7 @@ mod networking_tests {
         server.handle_flood_response(SourceRoutingHeader::new(vec![2, 1, 0], 2), 0, response);
         assert!(graphmap_eq(
             &server.network_graph.get_graph(),
-            &NetworkGraph::from_edges([(0, 1, INITIAL_ETX), (1, 2, INITIAL_ETX),])
+            &NetworkGraph::from_edges([(0, 1, INITIAL_ETX), (1, 2, RoutingTable::ENDPOINT_ETX),])
         ));
     }
 
@@ -392,8 +397,8 @@ mod networking_tests {
                 (14, 11, INITIAL_ETX),
                 (14, 13, INITIAL_ETX),
                 (13, 14, INITIAL_ETX),
-                (13, 2, INITIAL_ETX),
-                (14, 2, INITIAL_ETX),
+                (13, 2, RoutingTable::ENDPOINT_ETX),
+                (14, 2, RoutingTable::ENDPOINT_ETX),
             ])
         ));
     }
@@ -447,4 +452,170 @@ mod networking_tests {
             panic!();
         }
     }
+
+    /// tests that the flood requests close together are handled by a single flood
+    #[test]
+    fn test_flood_debounce() {
+        let mut server: GenericServer<Text> = get_dummy_builder(0)
+            .flood_debounce(Duration::from_millis(100))
+            .build();
+        let now: Instant = Instant::now();
+        assert!(server.flood_due(now));
+        server.flood();
+        assert!(!server.flood_due(now));
+        server.request_flood(now);
+        assert!(!server.flood_due(now + Duration::from_millis(50)));
+        server.request_flood(now + Duration::from_millis(50));
+        assert!(server.flood_due(now + Duration::from_millis(100)));
+        server.flood();
+        assert!(!server.need_flood);
+        assert!(server.flood_requested.is_none());
+    }
+
+    /// tests the minimum time between two floods
+    #[test]
+    fn test_min_flood_interval() {
+        let mut server: GenericServer<Text> = get_dummy_builder(0)
+            .min_flood_interval(Duration::from_secs(1))
+            .build();
+        server.flood();
+        let now: Instant = Instant::now();
+        server.request_flood(now);
+        assert!(!server.flood_due(now));
+        assert!(server.flood_due(now + Duration::from_secs(1)));
+    }
+
+    /// tests the background refresh of the topology
+    #[test]
+    fn test_refresh_topology() {
+        let mut server: GenericServer<Text> = get_dummy_server_text();
+        server.flood();
+        server.refresh_topology(Instant::now() + Duration::from_secs(100));
+        assert!(!server.need_flood);
+
+        let mut server: GenericServer<Text> = get_dummy_builder(0)
+            .refresh_interval(Some(Duration::from_secs(1)))
+            .build();
+        server.flood();
+        let now: Instant = Instant::now();
+        server.refresh_topology(now);
+        assert!(!server.need_flood);
+        server.refresh_topology(now + Duration::from_secs(1));
+        assert!(server.need_flood);
+        assert!(server.flood_due(now + Duration::from_secs(1)));
+    }
+
+    /// tests that with the targeted repair a removed node triggers a flood
+    /// only if a client is no longer reachable
+    #[test]
+    fn test_targeted_repair() {
+        let mut server: GenericServer<Text> = get_dummy_builder(0).targeted_repair(true).build();
+        server.network_graph = RoutingTable::new_with_graph(
+            NetworkGraph::from_edges([
+                (0, 1, INITIAL_ETX),
+                (0, 2, INITIAL_ETX),
+                (1, 3, INITIAL_ETX),
+                (2, 3, INITIAL_ETX),
+            ]),
+            servers::default_estimator(),
+        );
+        server.network_graph.add_endpoint(0);
+        server.network_graph.add_endpoint(3);
+        server.flood();
+
+        server.handle_node_removal(1, Instant::now());
+        assert!(!server.network_graph.get_graph().contains_node(1));
+        assert!(!server.need_flood);
+        assert!(server.graph_updated);
+        assert_eq!(server.get_route(3), Some(vec![0, 2, 3]));
+
+        server.handle_node_removal(2, Instant::now());
+        assert!(server.need_flood);
+    }
+
+    /// tests that without the targeted repair every removed node triggers a flood
+    #[test]
+    fn test_no_targeted_repair() {
+        let mut server: GenericServer<Text> = get_dummy_server_text();
+        server.network_graph = RoutingTable::new_with_graph(
+            NetworkGraph::from_edges([
+                (0, 1, INITIAL_ETX),
+                (0, 2, INITIAL_ETX),
+                (1, 3, INITIAL_ETX),
+                (2, 3, INITIAL_ETX),
+            ]),
+            servers::default_estimator(),
+        );
+        server.network_graph.add_endpoint(3);
+        server.flood();
+        server.handle_node_removal(1, Instant::now());
+        assert!(server.need_flood);
+    }
+
+    /// tests that the graph update is signaled only once the flood settles
+    #[test]
+    fn test_flood_settle() {
+        let mut server: GenericServer<Text> = get_dummy_builder(0)
+            .flood_quiet_period(Duration::from_millis(100))
+            .build();
+        server.flood();
+        assert!(server.outstanding_floods.contains_key(&0));
+        let response: FloodResponse = FloodResponse {
+            flood_id: 0,
+            path_trace: vec![
+                (0, NodeType::Server),
+                (1, NodeType::Drone),
+                (2, NodeType::Client),
+            ],
+        };
+        server.handle_flood_response(SourceRoutingHeader::new(vec![2, 1, 0], 2), 0, response);
+        assert!(server.network_graph.get_graph().contains_edge(1, 2));
+        assert!(!server.graph_updated);
+        let now: Instant = Instant::now();
+        server.settle_floods(now);
+        assert!(!server.graph_updated);
+        server.settle_floods(now + Duration::from_millis(100));
+        assert!(server.graph_updated);
+        assert!(server.outstanding_floods.is_empty());
+    }
+
+    /// tests that a flood without responses settles after the timeout
+    #[test]
+    fn test_flood_settle_timeout() {
+        let mut server: GenericServer<Text> = get_dummy_builder(0)
+            .flood_settle_timeout(Duration::from_secs(1))
+            .build();
+        let now: Instant = Instant::now();
+        server.flood();
+        server.settle_floods(now + Duration::from_millis(500));
+        assert!(!server.graph_updated);
+        server.settle_floods(now + Duration::from_secs(2));
+        assert!(server.graph_updated);
+    }
+
+    /// tests that the pending packets are sent again in one batch
+    #[test]
+    fn test_resend_pending() {
+        let mut server: GenericServer<Text> = get_dummy_server_text();
+        let (ds, dr) = crossbeam_channel::unbounded();
+        server.packet_send.insert(1, ds);
+        for sid in 0..3 {
+            server
+                .sent_history
+                .insert(sid, HistoryEntry::new(vec![], 3, sid, 3, [0; 128]));
+            server.pending_packets.push_back(sid);
+        }
+        server.network_graph.check_and_add_edge(0, 1);
+        server.resend_pending();
+        assert!(server.pending_packets.len() == 3);
+        assert!(dr.try_recv().is_err());
+
+        server.network_graph.check_and_add_edge(1, 3);
+        server.graph_updated = true;
+        server.resend_pending();
+        assert!(server.pending_packets.is_empty());
+        let sids: Vec<u64> = dr.try_iter().map(|p: Packet| p.session_id).collect();
+        assert_eq!(sids, vec![0, 1, 2]);
+        assert!(server.sent_history.get(&1).unwrap().hops == vec![0, 1, 3]);
+    }
 }
@@ -1,12 +1,14 @@
 use common::slc_commands::ServerEvent;
 use log::{error, info, warn};
-use std::vec;
+use std::time::Instant;
 use wg_2024::{
     network::{NodeId, SourceRoutingHeader},
     packet::{Ack, Fragment, Nack, NackType, Packet, FRAGMENT_DSIZE},
 };
 
-use super::{GenericServer, RequestHandler, ServerType};
+use super::{
+    reassembly::FragmentStatus, GenericServer, PartialRequest, RequestHandler, ServerType,
+};
 use crate::{protocol_utils as network_protocol, servers::HistoryEntry};
 
 /// testing module
@@ -17,12 +19,13 @@ impl<T: ServerType> GenericServer<T>
 where
     GenericServer<T>: RequestHandler,
 {
-    /// removes the acknowledged [Packet] from the sent history and updates
-    /// the pdr of the drones
+    /// removes the acknowledged [Packet] from the sent history, updates
+    /// the pdr of the drones and advances the window of the response
     pub(super) fn handle_ack(&mut self, sid: u64, _ack: &Ack) {
         if let Some(entry) = self.sent_history.remove(&sid) {
             self.update_pdr_from_ack(&entry.hops);
             info!(target: &self.target_topic, "Sid: {sid} acknoledged");
+            self.window_on_ack(entry.receiver_id, sid);
         } else {
             warn!(target: &self.target_topic, "Received unknow sid in Ack msg: {sid}");
         }
@@ -35,11 +38,13 @@ where
         match nack.nack_type {
             NackType::Dropped => {
                 self.update_pdr_from_nack(&srch.hops);
+                if let Some(entry) = self.sent_history.get(&sid) {
+                    self.window_on_drop(entry.receiver_id, sid);
+                }
                 info!(target: &self.target_topic, "Received dropped nack, updating pdr");
             }
             NackType::ErrorInRouting(id) => {
-                self.network_graph.remove_node(id);
-                self.need_flood = true;
+                self.handle_node_removal(id, Instant::now());
             }
             NackType::DestinationIsDrone => {
                 error!(target: &self.target_topic, "CRITICAL: sent a message with drone as destination?");
@@ -53,12 +58,13 @@ where
 
         if let Some(entry) = fragment {
             let HistoryEntry {
-                hops: _,
                 receiver_id,
                 frag_idx,
                 n_frags,
                 frag,
+                ..
             } = *entry;
+            self.metrics.retransmissions += 1;
             self.resend_packet(sid, receiver_id, frag_idx, n_frags, frag);
         } else {
             warn!(target: &self.target_topic, "Received Nack with unknown sid: {sid}");
@@ -66,7 +72,10 @@ where
     }
 
     /// handles a received fragment, if the fragment was the last one needed to reconstruct a request
-    /// the request is also handled
+    /// the request is also handled.
+    /// New requests that exceed the reassembly limits are answered with an error response
+    /// and their later fragments are refused, duplicated fragments are acknowledged but not counted and fragments whose total number
+    /// of fragments differs from the one of the first fragment are dropped
     #[allow(clippy::cast_possible_truncation)]
     pub(super) fn handle_fragment(
         &mut self,
@@ -76,23 +85,46 @@ where
     ) {
         let rid: u16 = network_protocol::get_rid(sid);
         if let Some(&id) = srch.hops.first() {
-            let entry: &mut (u64, Vec<[u8; FRAGMENT_DSIZE]>) =
-                self.fragment_history.entry((id, rid)).or_insert((
-                    0,
-                    // fine on 64 bit machines
-                    vec![[0; FRAGMENT_DSIZE]; frag.total_n_fragments as usize],
-                ));
-            entry.1.get_mut(frag.fragment_index as usize).map_or_else(
-                || warn!(target: &self.target_topic, "Received fragment with invalid index"),
-                |v: &mut [u8; FRAGMENT_DSIZE]| {
-                    entry.0 += 1;
-                    *v = frag.data;
-                },
-            );
-            if entry.0 == frag.total_n_fragments {
+            let now: Instant = Instant::now();
+            if self.rejected_requests.contains_key(&(id, rid)) {
+                // the rest of a refused request is refused too, even if a slot freed up
+                self.reject_request(srch, id, rid, now);
+                self.send_ack(srch, srch.hops[0], sid, frag.fragment_index);
+                return;
+            }
+            if !self.fragment_history.contains_key(&(id, rid)) {
+                if let Err(reason) = self.check_new_request(id, frag.total_n_fragments) {
+                    warn!(target: &self.target_topic, "Refusing request {rid} of {id}: {reason}");
+                    self.reject_request(srch, id, rid, now);
+                    self.send_ack(srch, srch.hops[0], sid, frag.fragment_index);
+                    return;
+                }
+            }
+            let entry: &mut PartialRequest =
+                self.fragment_history.entry((id, rid)).or_insert_with(|| {
+                    // bounded by the reassembly limits
+                    PartialRequest::new(frag.total_n_fragments as usize, now)
+                });
+            match entry.insert(frag, now) {
+                FragmentStatus::New => {}
+                FragmentStatus::Duplicate => {
+                    info!(target: &self.target_topic, "Received duplicate fragment {} of request {rid}", frag.fragment_index);
+                }
+                FragmentStatus::InvalidIndex => {
+                    warn!(target: &self.target_topic, "Received fragment with invalid index");
+                }
+                FragmentStatus::SizeMismatch(n_frags) => {
+                    warn!(target: &self.target_topic,
+                        "Refusing fragment of request {rid}: {} fragments instead of {n_frags}",
+                        frag.total_n_fragments
+                    );
+                    return;
+                }
+            }
+            if entry.is_complete() {
                 info!(target: &self.target_topic, "All fragments received, reconstructing request {rid}");
                 let data: Vec<[u8; FRAGMENT_DSIZE]> =
-                    self.fragment_history.remove(&(id, rid)).unwrap().1;
+                    self.fragment_history.remove(&(id, rid)).unwrap().fragments;
                 self.handle_request(srch, id, rid, data);
             }
             self.send_ack(srch, srch.hops[0], sid, frag.fragment_index);
@@ -126,6 +158,7 @@ where
             return;
         }
 
+        self.metrics.acks_sent += 1;
         if let Some(c) = self.packet_send.get(&ack.routing_header.hops[1]) {
             let _ = c.send(ack.clone());
             let _ = self.controller_send.send(ServerEvent::PacketSent(ack));
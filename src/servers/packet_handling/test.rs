@@ -9,7 +9,7 @@ mod packet_tests {
     use crate::{
         servers::{
             self, routing::RoutingTable, test_utils::get_dummy_server_text, HistoryEntry,
-            NetworkGraph, Text, INITIAL_PDR,
+            NetworkGraph, PartialRequest, Text, INITIAL_PDR,
         },
         GenericServer,
     };
@@ -157,10 +157,10 @@ mod packet_tests {
                 data: [0; 128],
             },
         );
-        let (sz, frag) = server.fragment_history.remove(&(2, 0)).unwrap();
-        assert!(frag.len() == 2);
-        assert!(sz == 1);
-        assert!(frag[0] == [0u8; 128]);
+        let entry: PartialRequest = server.fragment_history.remove(&(2, 0)).unwrap();
+        assert!(entry.fragments.len() == 2);
+        assert!(entry.received == 1);
+        assert!(entry.fragments[0] == [0u8; 128]);
         assert!(server.fragment_history.is_empty());
         if let Ok(p) = sclr.recv() {
             match p {
@@ -192,10 +192,10 @@ mod packet_tests {
                 data: [0; 128],
             },
         );
-        let (sz, frag) = server.fragment_history.remove(&(2, 0)).unwrap();
-        assert!(frag.len() == 2);
-        assert!(sz == 1);
-        assert!(frag[0] == [0u8; 128]);
+        let entry: PartialRequest = server.fragment_history.remove(&(2, 0)).unwrap();
+        assert!(entry.fragments.len() == 2);
+        assert!(entry.received == 1);
+        assert!(entry.fragments[0] == [0u8; 128]);
         assert!(server.fragment_history.is_empty());
         if let Ok(p) = dr.recv() {
             matches!(p.pack_type, PacketType::Ack(_));
@@ -220,4 +220,63 @@ mod packet_tests {
         );
         assert!(server.fragment_history.is_empty());
     }
+
+    /// tests that duplicated fragments are acknowledged but do not complete the request
+    #[test]
+    fn test_duplicate_fragment_recv() {
+        let mut server: GenericServer<Text> = get_dummy_server_text();
+        let (ds, dr) = crossbeam_channel::unbounded();
+        server.packet_send.insert(1, ds.clone());
+        for _ in 0..2 {
+            server.handle_fragment(
+                &SourceRoutingHeader::new(vec![2, 1, 0], 2),
+                0,
+                &Fragment {
+                    fragment_index: 0,
+                    total_n_fragments: 2,
+                    length: 11,
+                    data: [1; 128],
+                },
+            );
+        }
+        let entry: &PartialRequest = server.fragment_history.get(&(2, 0)).unwrap();
+        assert!(entry.received == 1);
+        assert!(!entry.is_complete());
+        assert!(server.sent_history.is_empty());
+        for _ in 0..2 {
+            assert!(matches!(
+                dr.try_recv().unwrap().pack_type,
+                PacketType::Ack(_)
+            ));
+        }
+        assert!(dr.try_recv().is_err());
+    }
+
+    /// tests that fragments with an inconsistent number of fragments are dropped
+    #[test]
+    fn test_mismatched_fragment_recv() {
+        let mut server: GenericServer<Text> = get_dummy_server_text();
+        let (ds, dr) = crossbeam_channel::unbounded();
+        server.packet_send.insert(1, ds.clone());
+        for (idx, total) in [(0, 2), (1, 3)] {
+            server.handle_fragment(
+                &SourceRoutingHeader::new(vec![2, 1, 0], 2),
+                0,
+                &Fragment {
+                    fragment_index: idx,
+                    total_n_fragments: total,
+                    length: 11,
+                    data: [1; 128],
+                },
+            );
+        }
+        let entry: &PartialRequest = server.fragment_history.get(&(2, 0)).unwrap();
+        assert!(entry.received == 1);
+        assert!(entry.fragments.len() == 2);
+        assert!(matches!(
+            dr.try_recv().unwrap().pack_type,
+            PacketType::Ack(_)
+        ));
+        assert!(dr.try_recv().is_err());
+    }
 }
@@ -0,0 +1,214 @@
+use std::{
+    fmt::{self, Display},
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use common::web_messages::{
+    Compression, MediaRequest, Request, ResponseMessage, SerializableSerde, TextRequest,
+};
+use serde::{Deserialize, Serialize};
+use wg_2024::{network::NodeId, packet::FRAGMENT_DSIZE};
+
+use super::{extensions::RequestError, services::FileKind};
+
+/// testing module
+#[cfg(test)]
+mod test;
+
+/// separator between the name of a file and the requested range. The names of the
+/// files cannot contain it, so every file stays reachable by its name
+const RANGE_SEPARATOR: char = '\0';
+/// maximum number of bytes sent in a [`RangeResponse`], the rest of a larger range
+/// is requested again with [`RangeResponse::next_range`]
+pub const MAX_RANGE_LEN: u64 = 1 << 20;
+
+/// Range of bytes of a file, requested with a [`RangedName`] as the name of the file
+/// of a [`TextRequest::Text`] or [`MediaRequest::Media`] (see [`ByteRange::request_name`]).
+/// The file is answered with a [`RangeResponse`] containing the requested part.
+///
+/// The ranges of fragments are ranges of blocks of [`FRAGMENT_DSIZE`] bytes of the file
+/// (see [`ByteRange::fragments`]): the fragments of a response depend on its encoding
+/// and compression, so their indexes are not stable across requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    /// offset of the first requested byte
+    pub start: u64,
+    /// offset of the last requested byte, included. [None] requests the file up to its end
+    pub end: Option<u64>,
+}
+
+impl ByteRange {
+    /// creates a new [`ByteRange`], [None] if `end` is before `start`
+    #[must_use]
+    pub fn new(start: u64, end: Option<u64>) -> Option<Self> {
+        match end {
+            Some(end) if end < start => None,
+            _ => Some(Self { start, end }),
+        }
+    }
+
+    /// range of the bytes of the fragments from `first` to `last` included, fragment
+    /// `i` being the bytes of the file from `i * FRAGMENT_DSIZE`. [None] requests the
+    /// file up to its end. Returns [None] if `last` is before `first`
+    #[must_use]
+    pub fn fragments(first: u64, last: Option<u64>) -> Option<Self> {
+        let size: u64 = FRAGMENT_DSIZE as u64;
+        Self::new(
+            first.saturating_mul(size),
+            last.map(|last: u64| last.saturating_add(1).saturating_mul(size) - 1),
+        )
+    }
+
+    /// name to request to receive this range of the file `name`
+    #[must_use]
+    pub fn request_name(self, name: &str) -> String {
+        RangedName::new(name, self).to_string()
+    }
+
+    /// parses a range formatted as `start-end` or `start-`
+    fn parse(s: &str) -> Option<Self> {
+        let (start, end) = s.split_once('-')?;
+        let start: u64 = start.parse().ok()?;
+        let end: Option<u64> = if end.is_empty() {
+            None
+        } else {
+            Some(end.parse().ok()?)
+        };
+        Self::new(start, end)
+    }
+}
+
+/// Name of a file with the requested range, carried as the name of the file of a
+/// [`TextRequest::Text`] or [`MediaRequest::Media`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangedName {
+    /// name of the file
+    pub name: String,
+    /// requested range
+    pub range: ByteRange,
+}
+
+impl RangedName {
+    /// creates a new [`RangedName`]
+    #[must_use]
+    pub fn new(name: impl Into<String>, range: ByteRange) -> Self {
+        Self {
+            name: name.into(),
+            range,
+        }
+    }
+
+    /// parses the name of a requested file, [None] if it doesn't carry a range.
+    /// Err if the range is malformed
+    fn parse(name: &str) -> Result<Option<Self>, ()> {
+        let Some((file, range)) = name.split_once(RANGE_SEPARATOR) else {
+            return Ok(None);
+        };
+        let range: ByteRange = ByteRange::parse(range).ok_or(())?;
+        Ok(Some(Self::new(file, range)))
+    }
+}
+
+/// formats the name to put in the request
+impl Display for RangedName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{RANGE_SEPARATOR}{}-", self.name, self.range.start)?;
+        match self.range.end {
+            Some(end) => write!(f, "{end}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Part of a file sent in answer to a request with a [`ByteRange`], serialized as
+/// the content of the text or media response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RangeResponse {
+    /// offset in the file of the first byte of `data`
+    pub offset: u64,
+    /// size of the whole file
+    pub total: u64,
+    /// requested bytes, fewer than requested if the range exceeds the file
+    /// or [`MAX_RANGE_LEN`]
+    pub data: Vec<u8>,
+}
+
+impl RangeResponse {
+    /// range of the rest of the file, used to resume the download. [None] if this
+    /// part reaches the end of the file
+    #[must_use]
+    pub fn next_range(&self) -> Option<ByteRange> {
+        let next: u64 = self.offset + self.data.len() as u64;
+        (next < self.total).then_some(ByteRange {
+            start: next,
+            end: None,
+        })
+    }
+}
+
+/// removes the range from the name of the file requested by `content`, if any.
+/// Err if the range is malformed
+pub(super) fn take_range(content: &mut Request) -> Result<Option<ByteRange>, ()> {
+    let (Request::Text(TextRequest::Text(name)) | Request::Media(MediaRequest::Media(name))) =
+        content
+    else {
+        return Ok(None);
+    };
+    let Some(ranged) = RangedName::parse(name)? else {
+        return Ok(None);
+    };
+    *name = ranged.name;
+    Ok(Some(ranged.range))
+}
+
+/// reads the requested range of the file at `path`, at most [`MAX_RANGE_LEN`] bytes.
+/// [None] if the range starts after the end of the file
+pub(super) fn read_range(path: &Path, range: ByteRange) -> io::Result<Option<RangeResponse>> {
+    let mut file: File = File::open(path)?;
+    let total: u64 = file.metadata()?.len();
+    if range.start > total {
+        return Ok(None);
+    }
+    let end: u64 = range
+        .end
+        .map_or(total, |end: u64| end.saturating_add(1).min(total))
+        .min(range.start.saturating_add(MAX_RANGE_LEN));
+    let mut data: Vec<u8> = Vec::new();
+    file.seek(SeekFrom::Start(range.start))?;
+    file.take(end - range.start).read_to_end(&mut data)?;
+    Ok(Some(RangeResponse {
+        offset: range.start,
+        total,
+        data,
+    }))
+}
+
+/// creates the response containing the requested range of the file at `path`, with
+/// the error to count if the range starts after the end of the file
+pub(super) fn range_response(
+    server_id: NodeId,
+    path: &Path,
+    kind: FileKind,
+    compression: Compression,
+    range: ByteRange,
+) -> (ResponseMessage, Option<RequestError>) {
+    match read_range(path, range) {
+        Ok(Some(part)) => match SerializableSerde::serialize(&part) {
+            Ok(data) => (kind.response(server_id, compression, data), None),
+            Err(_) => (
+                ResponseMessage::new_invalid_request_response(server_id, compression),
+                None,
+            ),
+        },
+        Ok(None) => (
+            ResponseMessage::new_invalid_request_response(server_id, compression),
+            Some(RequestError::Invalid),
+        ),
+        Err(_) => (
+            ResponseMessage::new_not_found_response(server_id, compression),
+            None,
+        ),
+    }
+}
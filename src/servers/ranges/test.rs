@@ -0,0 +1,133 @@
+#[cfg(test)]
+mod ranges_tests {
+    use std::{env, fs, path::PathBuf};
+
+    use common::web_messages::{
+        Compression, Request, RequestMessage, Response, ResponseMessage, SerializableSerde,
+        TextRequest, TextResponse,
+    };
+
+    use crate::servers::{
+        ranges::{read_range, take_range},
+        test_utils::{get_dummy_builder, get_routed_server, request_response},
+        ByteRange, GenericServer, RangeResponse, RangedName, Text, MAX_RANGE_LEN,
+    };
+
+    /// writes the file `page.html` in a new temporary directory, named after the test
+    fn write_file(test: &str) -> (PathBuf, Vec<u8>) {
+        let dir: PathBuf = env::temp_dir().join(format!("ranges_{test}_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let data: Vec<u8> = (0..100).collect();
+        fs::write(dir.join("page.html"), &data).unwrap();
+        (dir, data)
+    }
+
+    /// valid range from `start` to `end`
+    fn range(start: u64, end: Option<u64>) -> ByteRange {
+        ByteRange::new(start, end).unwrap()
+    }
+
+    /// tests the names of the requests with a range
+    #[test]
+    fn test_request_name() {
+        let mut content: Request = Request::Text(TextRequest::Text(
+            range(10, Some(19)).request_name("page.html"),
+        ));
+        assert_eq!(take_range(&mut content), Ok(ByteRange::new(10, Some(19))));
+        assert!(content == Request::Text(TextRequest::Text("page.html".to_string())));
+
+        let mut content: Request = Request::Text(TextRequest::Text(
+            RangedName::new("a-b", range(10, None)).to_string(),
+        ));
+        assert_eq!(take_range(&mut content), Ok(ByteRange::new(10, None)));
+        assert!(content == Request::Text(TextRequest::Text("a-b".to_string())));
+
+        // every name without a range is left as it is, whatever it contains
+        for name in ["page.html", "page.html#range=", "page.html#range=5-3"] {
+            let mut content: Request = Request::Text(TextRequest::Text(name.to_string()));
+            assert_eq!(take_range(&mut content), Ok(None));
+            assert!(content == Request::Text(TextRequest::Text(name.to_string())));
+        }
+        for malformed in ["page.html\x00", "page.html\x005", "page.html\x009-3"] {
+            let mut content: Request = Request::Text(TextRequest::Text(malformed.to_string()));
+            assert_eq!(take_range(&mut content), Err(()));
+        }
+
+        // the ranges ending before their start are refused
+        assert_eq!(ByteRange::new(9, Some(3)), None);
+        assert_eq!(ByteRange::new(3, Some(3)).unwrap().end, Some(3));
+
+        // fragment i is the block of 128 bytes of the file starting at i * 128
+        assert_eq!(
+            ByteRange::fragments(2, Some(3)),
+            ByteRange::new(256, Some(511))
+        );
+        assert_eq!(ByteRange::fragments(1, None), ByteRange::new(128, None));
+        assert_eq!(ByteRange::fragments(3, Some(2)), None);
+    }
+
+    /// tests the reading of the ranges of a file
+    #[test]
+    fn test_read_range() {
+        let (dir, data) = write_file("read");
+        let path: PathBuf = dir.join("page.html");
+        let part: RangeResponse = read_range(&path, range(10, Some(19))).unwrap().unwrap();
+        assert_eq!(part.offset, 10);
+        assert_eq!(part.total, 100);
+        assert_eq!(part.data, data[10..20]);
+        assert_eq!(part.next_range(), ByteRange::new(20, None));
+
+        // the range is clamped to the end of the file
+        let part: RangeResponse = read_range(&path, range(90, Some(200))).unwrap().unwrap();
+        assert_eq!(part.data, data[90..]);
+        assert_eq!(part.next_range(), None);
+        let part: RangeResponse = read_range(&path, range(100, None)).unwrap().unwrap();
+        assert!(part.data.is_empty());
+        assert!(read_range(&path, range(101, None)).unwrap().is_none());
+
+        // at most MAX_RANGE_LEN bytes are read, the rest is requested again
+        let max: usize = usize::try_from(MAX_RANGE_LEN).unwrap();
+        let large: Vec<u8> = (0..=u8::MAX).cycle().take(max + 100).collect();
+        fs::write(&path, &large).unwrap();
+        let part: RangeResponse = read_range(&path, range(0, None)).unwrap().unwrap();
+        assert_eq!(part.data, large[..max]);
+        assert_eq!(part.next_range(), ByteRange::new(MAX_RANGE_LEN, None));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// tests that a file can be downloaded in parts
+    #[test]
+    fn test_range_request() {
+        let (dir, data) = write_file("request");
+        let (mut server, dr): (GenericServer<Text>, _) =
+            get_routed_server(get_dummy_builder(0).content_dir(&dir).workers(0), &[2]);
+
+        let mut received: Vec<u8> = Vec::new();
+        let mut range: Option<ByteRange> = ByteRange::new(0, Some(59));
+        while let Some(r) = range {
+            let request: RequestMessage =
+                RequestMessage::new_text_request(2, Compression::None, r.request_name("page.html"));
+            let resp: ResponseMessage = request_response(&mut server, &dr, &request);
+            let Response::Text(TextResponse::Text(content)) = resp.content else {
+                panic!("expected a text response");
+            };
+            let part: RangeResponse = SerializableSerde::deserialize(content).unwrap();
+            assert_eq!(part.offset, received.len() as u64);
+            received.extend(part.data.iter());
+            range = part.next_range();
+        }
+        assert_eq!(received, data);
+
+        // malformed and unsatisfiable ranges are invalid requests
+        for name in ["page.html\x00a-", "page.html\x00200-"] {
+            let request: RequestMessage =
+                RequestMessage::new_text_request(2, Compression::None, name.to_string());
+            assert!(
+                request_response(&mut server, &dr, &request)
+                    == ResponseMessage::new_invalid_request_response(0, Compression::None)
+            );
+        }
+        assert_eq!(server.error_counters().invalid, 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,168 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use common::web_messages::{Compression, ResponseMessage};
+use log::warn;
+use wg_2024::{
+    network::{NodeId, SourceRoutingHeader},
+    packet::Fragment,
+};
+
+use super::{extensions::RequestError, GenericServer, PartialRequest, ServerType};
+
+/// testing module
+#[cfg(test)]
+mod test;
+
+/// default time after which a request that is not receiving fragments is dropped
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+/// default maximum number of requests being reassembled for each client
+const DEFAULT_MAX_PARTIAL: usize = 16;
+/// default maximum number of fragments of a request
+const DEFAULT_MAX_FRAGMENTS: u64 = 64;
+
+/// Data structure used to remember the requests refused due to the reassembly limits,
+/// so that the error response is sent only once
+/// maps (`SenderId`, rid) -> instant of the last received fragment
+pub(super) type RejectedRequests = HashMap<(NodeId, u16), Instant>;
+
+/// Limits on the requests being reassembled by a [`GenericServer`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ReassemblyConfig {
+    /// time after which a request that is not receiving fragments is dropped
+    pub(crate) timeout: Duration,
+    /// maximum number of requests being reassembled for each client
+    pub(crate) max_partial: usize,
+    /// maximum number of fragments of a request
+    pub(crate) max_fragments: u64,
+}
+
+impl Default for ReassemblyConfig {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_TIMEOUT,
+            max_partial: DEFAULT_MAX_PARTIAL,
+            max_fragments: DEFAULT_MAX_FRAGMENTS,
+        }
+    }
+}
+
+/// Reason why a new request cannot be reassembled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Rejection {
+    /// the request has 0 fragments or more than [`ReassemblyConfig::max_fragments`]
+    InvalidSize(u64),
+    /// the client has already [`ReassemblyConfig::max_partial`] requests being reassembled
+    TooManyPartialRequests,
+}
+
+impl Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidSize(n) => write!(f, "invalid number of fragments {n}"),
+            Self::TooManyPartialRequests => write!(f, "too many partial requests"),
+        }
+    }
+}
+
+/// Outcome of the insertion of a fragment in a [`PartialRequest`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum FragmentStatus {
+    /// the fragment has been stored
+    New,
+    /// a fragment with the same index had already been stored
+    Duplicate,
+    /// the index of the fragment is out of bounds
+    InvalidIndex,
+    /// the total number of fragments differs from the one of the first fragment,
+    /// which is returned
+    SizeMismatch(u64),
+}
+
+impl PartialRequest {
+    /// stores a received fragment, duplicated fragments are not counted
+    pub(super) fn insert(&mut self, frag: &Fragment, now: Instant) -> FragmentStatus {
+        let n_frags: u64 = self.fragments.len() as u64;
+        if frag.total_n_fragments != n_frags {
+            return FragmentStatus::SizeMismatch(n_frags);
+        }
+        let Some(idx) = usize::try_from(frag.fragment_index)
+            .ok()
+            .filter(|i: &usize| *i < self.fragments.len())
+        else {
+            return FragmentStatus::InvalidIndex;
+        };
+        self.last_update = now;
+        let (word, bit): (usize, u64) = (idx / 64, 1 << (idx % 64));
+        if self.received_idx[word] & bit != 0 {
+            return FragmentStatus::Duplicate;
+        }
+        self.received_idx[word] |= bit;
+        self.fragments[idx] = frag.data;
+        self.received += 1;
+        FragmentStatus::New
+    }
+
+    /// whether or not all the fragments of the request have been received
+    #[inline]
+    #[must_use]
+    pub(super) fn is_complete(&self) -> bool {
+        self.received == self.fragments.len() as u64
+    }
+}
+
+impl<T: ServerType> GenericServer<T> {
+    /// checks whether or not a new request of `n_frags` fragments from `client` can be reassembled
+    pub(super) fn check_new_request(&self, client: NodeId, n_frags: u64) -> Result<(), Rejection> {
+        if n_frags == 0 || n_frags > self.reassembly.max_fragments {
+            return Err(Rejection::InvalidSize(n_frags));
+        }
+        let partial: usize = self
+            .fragment_history
+            .keys()
+            .filter(|(id, _): &&(NodeId, u16)| *id == client)
+            .count();
+        if partial >= self.reassembly.max_partial {
+            return Err(Rejection::TooManyPartialRequests);
+        }
+        Ok(())
+    }
+
+    /// answers a refused request with an invalid request response, the response is sent
+    /// only for the first received fragment of the request.
+    /// Since the request cannot be deserialized, the response is not compressed
+    pub(super) fn reject_request(
+        &mut self,
+        srch: &SourceRoutingHeader,
+        client: NodeId,
+        rid: u16,
+        now: Instant,
+    ) {
+        if self.rejected_requests.insert((client, rid), now).is_none() {
+            self.record_error(RequestError::Rejected);
+            let resp: ResponseMessage =
+                ResponseMessage::new_invalid_request_response(self.id, Compression::None);
+            self.send_response(srch, client, rid, resp);
+        }
+    }
+
+    /// drops the requests that did not receive any fragment in the last
+    /// [`ReassemblyConfig::timeout`]
+    pub(super) fn drop_stale_requests(&mut self, now: Instant) {
+        let timeout: Duration = self.reassembly.timeout;
+        let target_topic: &str = &self.target_topic;
+        self.fragment_history
+            .retain(|(id, rid), r: &mut PartialRequest| {
+                let alive: bool = now.saturating_duration_since(r.last_update) < timeout;
+                if !alive {
+                    warn!(target: target_topic, "Dropping request {rid} of {id}: reassembly timed out");
+                }
+                alive
+            });
+        self.rejected_requests
+            .retain(|_, last: &mut Instant| now.saturating_duration_since(*last) < timeout);
+    }
+}
@@ -0,0 +1,187 @@
+#[cfg(test)]
+mod reassembly_tests {
+    use std::time::{Duration, Instant};
+
+    use crossbeam_channel::Receiver;
+    use wg_2024::{
+        network::SourceRoutingHeader,
+        packet::{Fragment, Packet, PacketType},
+    };
+
+    use crate::{
+        servers::{
+            reassembly::{FragmentStatus, Rejection},
+            test_utils::{get_dummy_builder, get_routed_server},
+            PartialRequest, Text,
+        },
+        GenericServer,
+    };
+
+    /// get a [`GenericServer<Text>`] with a route to the clients 2 and 3, the packets
+    /// sent to the drone 1 can be read from the returned channel
+    fn get_server(
+        max_partial: usize,
+        max_fragments: u64,
+    ) -> (GenericServer<Text>, Receiver<Packet>) {
+        get_routed_server(
+            get_dummy_builder(0)
+                .max_partial_requests(max_partial)
+                .max_request_fragments(max_fragments)
+                .reassembly_timeout(Duration::from_secs(1)),
+            &[2, 3],
+        )
+    }
+
+    /// delivers to the server the first fragment of the request `rid` of `client`
+    fn send_first_fragment(server: &mut GenericServer<Text>, client: u8, rid: u16, total: u64) {
+        server.handle_fragment(
+            &SourceRoutingHeader::new(vec![client, 1, 0], 2),
+            u64::from(rid),
+            &Fragment {
+                fragment_index: 0,
+                total_n_fragments: total,
+                length: 128,
+                data: [0; 128],
+            },
+        );
+        server.wait_workers();
+    }
+
+    /// counts the acks and the response fragments sent by the server
+    fn count_sent(dr: &Receiver<Packet>) -> (usize, usize) {
+        let (mut acks, mut frags) = (0, 0);
+        while let Ok(p) = dr.try_recv() {
+            match p.pack_type {
+                PacketType::Ack(_) => acks += 1,
+                PacketType::MsgFragment(_) => frags += 1,
+                _ => panic!(),
+            }
+        }
+        (acks, frags)
+    }
+
+    /// tests that requests with an invalid size are refused without allocating them
+    #[test]
+    fn test_invalid_size() {
+        let (mut server, dr) = get_server(16, 64);
+        assert_eq!(
+            server.check_new_request(2, u64::MAX),
+            Err(Rejection::InvalidSize(u64::MAX))
+        );
+        assert_eq!(
+            server.check_new_request(2, 0),
+            Err(Rejection::InvalidSize(0))
+        );
+        assert_eq!(server.check_new_request(2, 64), Ok(()));
+
+        send_first_fragment(&mut server, 2, 0, u64::MAX);
+        assert!(server.fragment_history.is_empty());
+        let (acks, frags) = count_sent(&dr);
+        assert_eq!(acks, 1);
+        assert!(frags > 0);
+
+        // the error response is sent only once
+        send_first_fragment(&mut server, 2, 0, u64::MAX);
+        assert_eq!(count_sent(&dr), (1, 0));
+        assert!(server.fragment_history.is_empty());
+    }
+
+    /// tests the limit on the number of partial requests of each client
+    #[test]
+    fn test_max_partial_requests() {
+        let (mut server, dr) = get_server(2, 64);
+        send_first_fragment(&mut server, 2, 0, 2);
+        send_first_fragment(&mut server, 2, 1, 2);
+        assert_eq!(count_sent(&dr), (2, 0));
+        assert_eq!(
+            server.check_new_request(2, 2),
+            Err(Rejection::TooManyPartialRequests)
+        );
+
+        send_first_fragment(&mut server, 2, 2, 2);
+        assert_eq!(server.fragment_history.len(), 2);
+        let (acks, frags) = count_sent(&dr);
+        assert_eq!(acks, 1);
+        assert!(frags > 0);
+
+        // other clients are not affected
+        send_first_fragment(&mut server, 3, 2, 2);
+        assert_eq!(server.fragment_history.len(), 3);
+        assert_eq!(count_sent(&dr), (1, 0));
+
+        // the rest of the refused request is refused even after a slot frees up
+        server.fragment_history.remove(&(2, 0));
+        server.handle_fragment(
+            &SourceRoutingHeader::new(vec![2, 1, 0], 2),
+            2,
+            &fragment(1, 2, 0),
+        );
+        assert!(!server.fragment_history.contains_key(&(2, 2)));
+        assert_eq!(count_sent(&dr), (1, 0));
+    }
+
+    /// tests that abandoned requests are dropped after the timeout
+    #[test]
+    fn test_drop_stale_requests() {
+        let (mut server, _dr) = get_server(16, 64);
+        send_first_fragment(&mut server, 2, 0, 2);
+        send_first_fragment(&mut server, 2, 1, u64::MAX);
+        assert_eq!(server.fragment_history.len(), 1);
+        assert_eq!(server.rejected_requests.len(), 1);
+
+        server.drop_stale_requests(Instant::now());
+        assert_eq!(server.fragment_history.len(), 1);
+
+        server.drop_stale_requests(Instant::now() + Duration::from_secs(1));
+        assert!(server.fragment_history.is_empty());
+        assert!(server.rejected_requests.is_empty());
+    }
+
+    /// get a fragment of a request of `total` fragments
+    fn fragment(idx: u64, total: u64, data: u8) -> Fragment {
+        Fragment {
+            fragment_index: idx,
+            total_n_fragments: total,
+            length: 128,
+            data: [data; 128],
+        }
+    }
+
+    /// tests the detection of duplicated, out of bounds and inconsistent fragments
+    #[test]
+    fn test_partial_request_insert() {
+        let now: Instant = Instant::now();
+        let mut request: PartialRequest = PartialRequest::new(70, now);
+        assert_eq!(
+            request.insert(&fragment(0, 70, 1), now),
+            FragmentStatus::New
+        );
+        assert_eq!(
+            request.insert(&fragment(69, 70, 2), now),
+            FragmentStatus::New
+        );
+        assert_eq!(
+            request.insert(&fragment(0, 70, 3), now),
+            FragmentStatus::Duplicate
+        );
+        assert_eq!(
+            request.insert(&fragment(70, 70, 3), now),
+            FragmentStatus::InvalidIndex
+        );
+        assert_eq!(
+            request.insert(&fragment(1, 71, 3), now),
+            FragmentStatus::SizeMismatch(70)
+        );
+        assert_eq!(request.received, 2);
+        assert_eq!(request.fragments[0], [1; 128]);
+        assert_eq!(request.fragments[69], [2; 128]);
+        assert!(!request.is_complete());
+        for i in 1..69 {
+            assert_eq!(
+                request.insert(&fragment(i, 70, 0), now),
+                FragmentStatus::New
+            );
+        }
+        assert!(request.is_complete());
+    }
+}
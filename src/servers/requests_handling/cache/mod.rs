@@ -0,0 +1,213 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use common::web_messages::Compression;
+use wg_2024::packet::FRAGMENT_DSIZE;
+
+use crate::servers::services::FileKind;
+
+/// testing module
+#[cfg(test)]
+mod test;
+
+/// default number of responses kept in the [`ContentCache`]
+pub(crate) const DEFAULT_CACHE_CAPACITY: usize = 32;
+
+/// Hit and miss counters of the content cache of a [`crate::GenericServer`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    /// number of file requests answered with an already compressed response
+    pub hits: u64,
+    /// number of file requests that required to read and compress the file
+    pub misses: u64,
+}
+
+/// key of a cached response: canonical path of the file, kind of response and requested compression
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct CacheKey {
+    /// canonical path of the file
+    path: PathBuf,
+    /// kind of the response containing the file
+    kind: FileKind,
+    /// requested compression, see [`compression_id`]
+    compression: u8,
+}
+
+impl CacheKey {
+    /// creates a new [`CacheKey`]
+    #[inline]
+    #[must_use]
+    pub(crate) fn new(path: PathBuf, kind: FileKind, compression: &Compression) -> Self {
+        Self {
+            path,
+            kind,
+            compression: compression_id(compression),
+        }
+    }
+
+    /// path of the file
+    #[inline]
+    #[must_use]
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// maps a [`Compression`] to a numeric id, since it cannot be hashed
+fn compression_id(compression: &Compression) -> u8 {
+    match compression {
+        Compression::None => 0,
+        Compression::LZW => 1,
+        Compression::Huffman => 2,
+    }
+}
+
+/// version of a file, used to detect when a cached response is stale
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FileVersion {
+    /// last modification time
+    modified: SystemTime,
+    /// size of the file, as the modification time may have a coarse granularity
+    len: u64,
+}
+
+impl FileVersion {
+    /// reads the current version of the file, [None] if its metadata are unavailable
+    pub(crate) fn of(path: &Path) -> Option<Self> {
+        Self::from_metadata(&fs::metadata(path).ok()?)
+    }
+
+    /// reads the current version of an open file, [None] if its metadata are unavailable
+    pub(crate) fn of_file(file: &File) -> Option<Self> {
+        Self::from_metadata(&file.metadata().ok()?)
+    }
+
+    /// version described by the metadata of a file
+    fn from_metadata(metadata: &fs::Metadata) -> Option<Self> {
+        Some(Self {
+            modified: metadata.modified().ok()?,
+            len: metadata.len(),
+        })
+    }
+
+    /// size of the file, in bytes
+    #[inline]
+    pub(crate) fn size(&self) -> u64 {
+        self.len
+    }
+}
+
+/// a cached response
+#[derive(Debug)]
+struct CacheEntry {
+    /// version of the file used to create the response
+    version: FileVersion,
+    /// serialized, compressed and fragmented response
+    fragments: Vec<[u8; FRAGMENT_DSIZE]>,
+    /// logical time of the last access, used to find the least recently used entry
+    last_used: u64,
+}
+
+/// Bounded LRU cache of the already compressed and fragmented file responses.
+/// An entry is used only if the file did not change since the response was created
+#[derive(Debug)]
+pub(crate) struct ContentCache {
+    /// maximum number of entries, 0 disables the cache
+    capacity: usize,
+    /// cached responses
+    entries: HashMap<CacheKey, CacheEntry>,
+    /// logical clock, incremented at every access
+    clock: u64,
+    /// hit and miss counters
+    stats: CacheStats,
+}
+
+impl ContentCache {
+    /// creates an empty [`ContentCache`] that holds at most `capacity` responses
+    #[must_use]
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            clock: 0,
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// returns the cached fragments of the response if they were created from
+    /// the given version of the file, stale entries are removed
+    pub(crate) fn get(
+        &mut self,
+        key: &CacheKey,
+        version: FileVersion,
+    ) -> Option<Vec<[u8; FRAGMENT_DSIZE]>> {
+        self.clock += 1;
+        match self.entries.get_mut(key) {
+            Some(entry) if entry.version == version => {
+                entry.last_used = self.clock;
+                self.stats.hits += 1;
+                Some(entry.fragments.clone())
+            }
+            Some(_) => {
+                self.entries.remove(key);
+                self.stats.misses += 1;
+                None
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// caches the fragments of a response, evicting the least recently used
+    /// entry if the cache is full
+    pub(crate) fn insert(
+        &mut self,
+        key: CacheKey,
+        version: FileVersion,
+        fragments: Vec<[u8; FRAGMENT_DSIZE]>,
+    ) {
+        if self.capacity == 0 {
+            return;
+        }
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.capacity {
+            // linear scan, the cache is expected to be small
+            if let Some(lru) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, e): &(&CacheKey, &CacheEntry)| e.last_used)
+                .map(|(k, _): (&CacheKey, &CacheEntry)| k.clone())
+            {
+                self.entries.remove(&lru);
+            }
+        }
+        self.clock += 1;
+        self.entries.insert(
+            key,
+            CacheEntry {
+                version,
+                fragments,
+                last_used: self.clock,
+            },
+        );
+    }
+
+    /// number of cached responses
+    #[inline]
+    #[must_use]
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// hit and miss counters
+    #[inline]
+    #[must_use]
+    pub(crate) fn stats(&self) -> CacheStats {
+        self.stats
+    }
+}
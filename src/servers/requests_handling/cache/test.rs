@@ -0,0 +1,94 @@
+#[cfg(test)]
+mod cache_tests {
+    use std::{
+        path::PathBuf,
+        time::{Duration, SystemTime},
+    };
+
+    use common::web_messages::Compression;
+
+    use crate::servers::{
+        requests_handling::cache::{CacheKey, CacheStats, ContentCache, FileVersion},
+        services::FileKind,
+    };
+
+    /// get a [`FileVersion`] for the given modification time and size
+    fn version(secs: u64, len: u64) -> FileVersion {
+        FileVersion {
+            modified: SystemTime::UNIX_EPOCH + Duration::from_secs(secs),
+            len,
+        }
+    }
+
+    /// get a [`CacheKey`] for the given file name
+    fn key(name: &str, compression: &Compression) -> CacheKey {
+        CacheKey::new(PathBuf::from(name), FileKind::Text, compression)
+    }
+
+    /// tests hits, misses and the separation of the compressions
+    #[test]
+    fn test_hit_miss() {
+        let mut cache: ContentCache = ContentCache::new(4);
+        assert!(cache
+            .get(&key("a", &Compression::LZW), version(1, 1))
+            .is_none());
+        cache.insert(key("a", &Compression::LZW), version(1, 1), vec![[1; 128]]);
+        assert_eq!(
+            cache.get(&key("a", &Compression::LZW), version(1, 1)),
+            Some(vec![[1; 128]])
+        );
+        assert!(cache
+            .get(&key("a", &Compression::Huffman), version(1, 1))
+            .is_none());
+        assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 2 });
+    }
+
+    /// tests that entries created from an older version of the file are discarded
+    #[test]
+    fn test_stale_entry() {
+        let mut cache: ContentCache = ContentCache::new(4);
+        cache.insert(key("a", &Compression::None), version(1, 1), vec![[1; 128]]);
+        assert!(cache
+            .get(&key("a", &Compression::None), version(2, 1))
+            .is_none());
+        assert_eq!(cache.len(), 0);
+        cache.insert(key("a", &Compression::None), version(2, 1), vec![[2; 128]]);
+        assert!(cache
+            .get(&key("a", &Compression::None), version(2, 3))
+            .is_none());
+        assert_eq!(cache.stats(), CacheStats { hits: 0, misses: 2 });
+    }
+
+    /// tests that the least recently used entry is evicted
+    #[test]
+    fn test_lru_eviction() {
+        let mut cache: ContentCache = ContentCache::new(2);
+        cache.insert(key("a", &Compression::None), version(1, 1), vec![[1; 128]]);
+        cache.insert(key("b", &Compression::None), version(1, 1), vec![[2; 128]]);
+        assert!(cache
+            .get(&key("a", &Compression::None), version(1, 1))
+            .is_some());
+        cache.insert(key("c", &Compression::None), version(1, 1), vec![[3; 128]]);
+        assert_eq!(cache.len(), 2);
+        assert!(cache
+            .get(&key("b", &Compression::None), version(1, 1))
+            .is_none());
+        assert!(cache
+            .get(&key("a", &Compression::None), version(1, 1))
+            .is_some());
+        assert!(cache
+            .get(&key("c", &Compression::None), version(1, 1))
+            .is_some());
+    }
+
+    /// tests that a cache with no capacity never stores anything
+    #[test]
+    fn test_disabled() {
+        let mut cache: ContentCache = ContentCache::new(0);
+        cache.insert(key("a", &Compression::None), version(1, 1), vec![[1; 128]]);
+        assert_eq!(cache.len(), 0);
+        assert!(cache
+            .get(&key("a", &Compression::None), version(1, 1))
+            .is_none());
+    }
+}
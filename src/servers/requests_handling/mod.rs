@@ -1,18 +1,8 @@
-use std::{
-    fs::{self, read},
-    io,
-    path::PathBuf,
-};
+use std::{mem, path::PathBuf, time::Instant};
 
 use common::{
-    slc_commands::{ServerEvent, ServerType},
-    web_messages::{
-        Compression, MediaRequest, Request, ResponseMessage, Serializable, SerializableSerde,
-        TextRequest,
-    },
-};
-use compression::{
-    bypass::BypassCompressor, huffman::HuffmanCompressor, lzw::LZWCompressor, Compressor,
+    slc_commands::ServerEvent,
+    web_messages::{Compression, ResponseMessage},
 };
 use log::{error, info, warn};
 use wg_2024::{
@@ -21,51 +11,144 @@ use wg_2024::{
 };
 
 use super::{
-    serialization::{defragment_deserialize_request, fragment_response},
-    GenericServer, HistoryEntry, Media, RequestHandler, Text,
+    extensions::RequestError,
+    ranges::take_range,
+    serialization::defragment_deserialize_request,
+    services::FileKind,
+    workers::{FileReply, ResponseTarget, Work},
+    GenericServer, HistoryEntry, PendingQueue, RequestHandler,
 };
 
-use crate::protocol_utils as network_protocol;
-use crate::servers::{ServerType as ST, MEDIA_PATH, TEXT_PATH};
+use crate::servers::ServerType as ST;
 
+pub use cache::CacheStats;
+pub(crate) use cache::{CacheKey, ContentCache, FileVersion, DEFAULT_CACHE_CAPACITY};
+
+/// LRU cache of the compressed file responses
+mod cache;
 /// testing module
 #[cfg(test)]
 mod test;
 
-/// lists the contents of a directory
-fn list_dir(path: &str) -> Result<Vec<String>, io::Error> {
-    Ok(fs::read_dir(path)?
-        .filter(Result::is_ok)
-        .map(|p: Result<fs::DirEntry, io::Error>| p.unwrap().path())
-        .filter(|p: &PathBuf| p.is_file())
-        .map(|p: PathBuf| p.into_os_string().into_string().unwrap())
-        .collect())
-}
-
 impl<T: ST> GenericServer<T> {
-    /// compresses the data based on the requested type
-    fn compress(data: Vec<u8>, comp: &Compression) -> Result<Vec<u8>, String> {
-        match comp {
-            Compression::Huffman => HuffmanCompressor::new()
-                .compress(data)?
-                .serialize()
-                .map_err(|_| "Error during compression".to_string()),
-            Compression::LZW => Serializable::serialize(&LZWCompressor::new().compress(data)?)
-                .map_err(|_| "Error during compression".to_string()),
-            Compression::None => BypassCompressor::new().compress(data),
-        }
+    /// hit and miss counters of the content cache
+    #[must_use]
+    pub fn cache_stats(&self) -> CacheStats {
+        self.content_cache.stats()
+    }
+
+    /// answers a request that cannot be deserialized with an invalid request response.
+    /// Since the requested compression is unknown, the response is not compressed
+    fn reply_undeserializable(&mut self, srch: &SourceRoutingHeader, src_id: NodeId, rid: u16) {
+        error!(target: &self.target_topic, "Received undeserializable request {rid}, sending error response");
+        self.record_error(RequestError::Undeserializable);
+        let resp: ResponseMessage =
+            ResponseMessage::new_invalid_request_response(self.id, Compression::None);
+        self.send_response(srch, src_id, rid, resp);
     }
 
     /// send response realted to a fully received request.
-    /// the response will have the same rid of the response as required by the protocol
+    /// the response will have the same rid of the response as required by the protocol,
+    /// it is serialized and compressed by the worker pool
     pub(super) fn send_response(
         &mut self,
         srch: &SourceRoutingHeader,
         src_id: NodeId,
         rid: u16,
-        resp: &ResponseMessage,
+        resp: ResponseMessage,
+    ) {
+        let target: ResponseTarget = ResponseTarget {
+            srch: srch.clone(),
+            src_id,
+            rid,
+        };
+        self.submit(target, Work::Encode(resp));
+    }
+
+    /// sends the response containing the file at `path`, see [`GenericServer::file_work`]
+    pub(super) fn send_file_response(
+        &mut self,
+        srch: &SourceRoutingHeader,
+        src_id: NodeId,
+        rid: u16,
+        path: PathBuf,
+        kind: FileKind,
+        compression: Compression,
+    ) {
+        let file: FileReply = FileReply {
+            version: FileVersion::of(&path),
+            path,
+            kind,
+            compression,
+            range: None,
+        };
+        let target: ResponseTarget = ResponseTarget {
+            srch: srch.clone(),
+            src_id,
+            rid,
+        };
+        let work: Work = self.file_work(file);
+        self.submit(target, work);
+    }
+
+    /// work needed to send the file a request was answered with: the requested range, or
+    /// the compressed response taken from the content cache if the file did not change
+    /// since it was cached, otherwise the file is read and compressed by the worker pool.
+    /// The large uncompressed responses are streamed instead, see
+    /// [`super::GenericServerBuilder::streaming_threshold`]
+    pub(super) fn file_work(&mut self, file: FileReply) -> Work {
+        let FileReply {
+            path,
+            kind,
+            version,
+            compression,
+            range,
+        } = file;
+        if let Some(range) = range {
+            info!(target: &self.target_topic, "Sending range {range:?} of {}", path.display());
+            return Work::Range {
+                server_id: self.id,
+                path,
+                kind,
+                compression,
+                range,
+            };
+        }
+        let key: CacheKey = CacheKey::new(path, kind, &compression);
+        if let Some(data) = version.and_then(|v: FileVersion| self.content_cache.get(&key, v)) {
+            info!(target: &self.target_topic, "Cache hit for {}", key.path().display());
+            return Work::Ready(data);
+        }
+        if let Some(version) =
+            version.filter(|&v: &FileVersion| self.should_stream(v, &compression))
+        {
+            let path: PathBuf = key.path().to_path_buf();
+            if let Some(stream) = self.open_stream(path, kind, compression.clone(), version) {
+                return Work::Stream(stream);
+            }
+        }
+
+        info!(target: &self.target_topic, "Cache miss for {}", key.path().display());
+        Work::File {
+            server_id: self.id,
+            key,
+            version,
+            kind,
+            compression,
+        }
+    }
+
+    /// sends the fragments of a response, spread over the available disjoint routes and
+    /// paced by the window of the response. If no route is available the fragments wait
+    /// in the window until the network graph is updated
+    pub(super) fn send_fragments(
+        &mut self,
+        srch: &SourceRoutingHeader,
+        src_id: NodeId,
+        rid: u16,
+        data: Vec<[u8; FRAGMENT_DSIZE]>,
     ) {
-        let mut resp_hdr: SourceRoutingHeader = self.get_routing_hdr_with_hint(srch, src_id);
+        let mut resp_hdr: SourceRoutingHeader = self.get_explore_hdr_with_hint(srch, src_id);
 
         if resp_hdr.len() < 2 {
             error!(target: &self.target_topic, "Error, srch of response inconsistent: {resp_hdr}. Dropping response");
@@ -73,66 +156,45 @@ impl<T: ST> GenericServer<T> {
         }
 
         resp_hdr.increase_hop_index();
-        let serialized: Result<Vec<[u8; FRAGMENT_DSIZE]>, String>;
-        if let Ok(data) = resp.serialize() {
-            info!(target: &self.target_topic, "Serialized response");
-            serialized = Self::compress(data, &resp.compression_type).map(fragment_response);
-            info!(target: &self.target_topic, "Compressed data");
+        let sz: usize = data.len();
+        let hdrs: Vec<SourceRoutingHeader> = if self.packet_send.contains_key(&resp_hdr.hops[1]) {
+            self.get_striped_hdrs(resp_hdr, src_id, sz)
         } else {
-            error!(target: &self.target_topic, "Cannot serialize response {resp:?}, dropping response");
-            return;
-        }
+            error!(target: &self.target_topic, "Unable to find channel of designated nbr! pending response...");
+            vec![resp_hdr; sz]
+        };
+        let fragments: Vec<(SourceRoutingHeader, HistoryEntry)> = data
+            .into_iter()
+            .zip(hdrs)
+            .enumerate()
+            .map(|(i, (frag, hdr))| {
+                let entry: HistoryEntry =
+                    HistoryEntry::new(hdr.hops.clone(), src_id, i as u64, sz as u64, frag);
+                (hdr, entry)
+            })
+            .collect();
+        self.send_windowed(src_id, rid, fragments);
+    }
 
-        match serialized {
-            Ok(data) => {
-                let sz: usize = data.len();
-                if let Some(next_hop) = self.packet_send.get(&resp_hdr.hops[1]) {
-                    for (i, frag) in data.into_iter().enumerate() {
-                        let sid: u64 = network_protocol::generate_response_id(self.session_id, rid);
-                        let packet: Packet = Packet::new_fragment(
-                            resp_hdr.clone(),
-                            sid,
-                            Fragment::new(i as u64, sz as u64, frag),
-                        );
-                        // (src_id, i as u64, sz as u64, frag)
-                        self.sent_history.insert(
-                            sid,
-                            HistoryEntry::new(
-                                resp_hdr.hops.clone(),
-                                src_id,
-                                i as u64,
-                                sz as u64,
-                                frag,
-                            ),
-                        );
-                        info!(target: &self.target_topic, "Sending response: {packet}");
-                        self.session_id = network_protocol::next_sid(self.session_id);
-                        let _ = next_hop.send(packet.clone());
-                        let _ = self.controller_send.send(ServerEvent::PacketSent(packet));
-                    }
-                } else {
-                    // no route, send to pending queue
-                    for (i, frag) in data.into_iter().enumerate() {
-                        let sid: u64 = (self.session_id << 16) | u64::from(rid);
-                        self.sent_history.insert(
-                            sid,
-                            HistoryEntry::new(
-                                resp_hdr.hops.clone(),
-                                src_id,
-                                i as u64,
-                                sz as u64,
-                                frag,
-                            ),
-                        );
-                        info!(target: &self.target_topic, "No path found, sending response to pending");
-                        self.session_id = network_protocol::next_sid(self.session_id);
-                        self.pending_packets.push_back(sid);
-                    }
-                    error!(target: &self.target_topic, "Unable to find channel of designated nbr! pending response...");
-                }
-            }
-            Err(_) => {
-                error!(target: &self.target_topic, "CRITICAL: Error during serialization of reponse, dropping response");
+    /// tries to send again all the packets in the pending queue in one batch, after an
+    /// update of the network graph, then the responses waiting for a route. The packets
+    /// that still have no route stay pending until the next update
+    pub(super) fn resend_pending(&mut self) {
+        self.pump_stalled_windows();
+        let pending: PendingQueue = mem::take(&mut self.pending_packets);
+        info!(target: &self.target_topic, "Trying to resend {} pending packets", pending.len());
+        for sid in pending {
+            if let Some(entry) = self.sent_history.get(&sid) {
+                let HistoryEntry {
+                    receiver_id,
+                    frag_idx,
+                    n_frags,
+                    frag,
+                    ..
+                } = *entry;
+                self.resend_packet(sid, receiver_id, frag_idx, n_frags, frag);
+            } else {
+                warn!(target: &self.target_topic, "CRITICAL: cannot find pending packet in sent history!");
             }
         }
     }
@@ -162,7 +224,11 @@ impl<T: ST> GenericServer<T> {
                         self.pending_packets.push_back(sid);
                     },
                     |c| {
-                        self.sent_history.entry(sid).and_modify(|e: &mut HistoryEntry| e.hops.clone_from(&packet.routing_header.hops));
+                        self.sent_history.entry(sid).and_modify(|e: &mut HistoryEntry| {
+                            e.hops.clone_from(&packet.routing_header.hops);
+                            e.start_timer(&self.retransmission, Instant::now());
+                        });
+                        self.metrics.fragments_sent += 1;
                         let _ = c.send(packet.clone());
                         let _ = self.controller_send.send(ServerEvent::PacketSent(packet));
                     },
@@ -175,8 +241,8 @@ impl<T: ST> GenericServer<T> {
     }
 }
 
-/// [`super::TextServer`] specialization code
-impl RequestHandler for GenericServer<Text> {
+/// every [`GenericServer`] answers the requests with its [`super::RequestService`]
+impl<T: ST> RequestHandler for GenericServer<T> {
     fn handle_request(
         &mut self,
         srch: &SourceRoutingHeader,
@@ -184,96 +250,32 @@ impl RequestHandler for GenericServer<Text> {
         rid: u16,
         data: Vec<[u8; FRAGMENT_DSIZE]>,
     ) {
-        if let Ok(req) = defragment_deserialize_request(data) {
-            let resp: ResponseMessage;
-            #[allow(clippy::match_wildcard_for_single_variants)]
-            match req.content {
-                Request::Type => {
-                    resp = ResponseMessage::new_type_response(
-                        self.id,
-                        req.compression_type,
-                        ServerType::FileServer,
-                    );
-                }
-                Request::Text(tr) => match tr {
-                    TextRequest::TextList => {
-                        resp = ResponseMessage::new_text_list_response(
-                            self.id,
-                            req.compression_type,
-                            list_dir(TEXT_PATH).unwrap_or_default(),
-                        );
-                    }
-                    TextRequest::Text(str) => {
-                        resp = if let Ok(data) = read(str) {
-                            ResponseMessage::new_text_response(self.id, req.compression_type, data)
-                        } else {
-                            ResponseMessage::new_not_found_response(self.id, req.compression_type)
-                        }
-                    }
-                },
-                _ => {
-                    resp = ResponseMessage::new_invalid_request_response(
-                        self.id,
-                        req.compression_type,
-                    );
-                }
-            }
-            info!(target: &self.target_topic, "Sending response");
-            self.send_response(srch, src_id, rid, &resp);
-        } else {
-            error!(target: &self.target_topic, "Received undeserializable request, dropping request...");
-        }
-        // self.session_id = (self.session_id + 1) & SID_MASK;
-    }
-}
+        let Ok(mut req) = defragment_deserialize_request(data) else {
+            self.reply_undeserializable(srch, src_id, rid);
+            return;
+        };
+        self.metrics.count_request(&req.content);
 
-/// [`super::MediaServer`] specialization code
-impl RequestHandler for GenericServer<Media> {
-    fn handle_request(
-        &mut self,
-        srch: &SourceRoutingHeader,
-        src_id: NodeId,
-        rid: u16,
-        data: Vec<[u8; FRAGMENT_DSIZE]>,
-    ) {
-        if let Ok(req) = defragment_deserialize_request(data) {
-            let resp: ResponseMessage;
-            #[allow(clippy::match_wildcard_for_single_variants)]
-            match req.content {
-                Request::Type => {
-                    resp = ResponseMessage::new_type_response(
-                        self.id,
-                        req.compression_type,
-                        ServerType::MediaServer,
-                    );
-                }
-                Request::Media(mr) => match mr {
-                    MediaRequest::MediaList => {
-                        resp = ResponseMessage::new_media_list_response(
-                            self.id,
-                            req.compression_type,
-                            list_dir(MEDIA_PATH).unwrap_or_default(),
-                        );
-                    }
-                    MediaRequest::Media(str) => {
-                        resp = if let Ok(data) = read(str) {
-                            ResponseMessage::new_media_response(self.id, req.compression_type, data)
-                        } else {
-                            ResponseMessage::new_not_found_response(self.id, req.compression_type)
-                        }
-                    }
-                },
-                _ => {
-                    resp = ResponseMessage::new_invalid_request_response(
-                        self.id,
-                        req.compression_type,
-                    );
-                }
-            }
-            info!(target: &self.target_topic, "Sending response");
-            self.send_response(srch, src_id, rid, &resp);
-        } else {
-            error!(target: &self.target_topic, "Received undeserializable request, dropping request...");
-        }
+        // the range is handled by the server, the service only sees the name of the file
+        let Ok(range) = take_range(&mut req.content) else {
+            warn!(target: &self.target_topic, "Received request {rid} with a malformed range");
+            self.record_error(RequestError::Invalid);
+            let resp: ResponseMessage =
+                ResponseMessage::new_invalid_request_response(self.id, req.compression_type);
+            self.send_response(srch, src_id, rid, resp);
+            return;
+        };
+        info!(target: &self.target_topic, "Answering request {rid} from {src_id}");
+        let target: ResponseTarget = ResponseTarget {
+            srch: srch.clone(),
+            src_id,
+            rid,
+        };
+        let work: Work = Work::Request {
+            server_id: self.id,
+            request: req,
+            range,
+        };
+        self.submit(target, work);
     }
 }
@@ -1,6 +1,11 @@
 #[cfg(test)]
 mod request_tests {
-    use std::{fs::read, time::Duration, vec};
+    use std::{
+        env,
+        fs::{self, read},
+        time::Duration,
+        vec,
+    };
 
     use common::{
         slc_commands::{ServerCommand, ServerType},
@@ -11,19 +16,22 @@ mod request_tests {
     use compression::{huffman::HuffmanCompressor, lzw::LZWCompressor, Compressor};
     use serde::{de::DeserializeOwned, Serialize};
     use wg_2024::{
-        network::SourceRoutingHeader,
-        packet::{FloodResponse, Fragment, Nack, NackType, NodeType, PacketType},
+        network::{NodeId, SourceRoutingHeader},
+        packet::{Ack, FloodResponse, Fragment, Nack, NackType, NodeType, PacketType},
     };
 
     use crate::{
         servers::{
             self,
-            requests_handling::list_dir,
+            extensions::{ErrorCounters, ExtendedEvent},
             routing::RoutingTable,
             serialization::fragment_response,
-            test_utils::{get_dummy_server_media, get_dummy_server_text},
-            HistoryEntry, NetworkGraph, RequestHandler, ServerType as ST, INITIAL_PDR, MEDIA_PATH,
-            TEXT_PATH,
+            test_utils::{
+                get_dummy_builder, get_dummy_server_media, get_dummy_server_text,
+                get_routed_server, request_response,
+            },
+            CacheStats, ContentRoot, HistoryEntry, NetworkGraph, Reply, RequestHandler,
+            RequestService, ServerType as ST, INITIAL_PDR, MEDIA_PATH, TEXT_PATH,
         },
         GenericServer,
     };
@@ -59,6 +67,7 @@ mod request_tests {
                 },
             );
         }
+        server.wait_workers();
         assert!(server.fragment_history.is_empty());
         assert!(!server.sent_history.is_empty());
         let mut acks: u64 = 0;
@@ -70,6 +79,13 @@ mod request_tests {
                 PacketType::MsgFragment(f) => {
                     v.push(f.data);
                     _frags += 1;
+                    // acknowledge the fragment so that the window advances
+                    server.handle_ack(
+                        p.session_id,
+                        &Ack {
+                            fragment_index: f.fragment_index,
+                        },
+                    );
                 }
                 _ => panic!(),
             }
@@ -82,20 +98,50 @@ mod request_tests {
         assert!(resp == response);
     }
 
-    /// test correct dir listing
+    /// tests that repeated file requests are served by the cache and that
+    /// edited files are served fresh
+    #[test]
+    fn test_file_request_cache() {
+        let dir = env::temp_dir().join(format!("request_cache_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("page.html"), b"<p>old</p>").unwrap();
+
+        let (mut server, dr): (GenericServer<servers::Text>, _) =
+            get_routed_server(get_dummy_builder(0).content_dir(&dir), &[2]);
+
+        let request: RequestMessage =
+            RequestMessage::new_text_request(1, Compression::LZW, "page.html".to_string());
+        let old: ResponseMessage =
+            ResponseMessage::new_text_response(0, Compression::LZW, b"<p>old</p>".to_vec());
+        assert!(request_response(&mut server, &dr, &request) == old);
+        assert!(request_response(&mut server, &dr, &request) == old);
+        assert_eq!(server.cache_stats(), CacheStats { hits: 1, misses: 1 });
+
+        fs::write(dir.join("page.html"), b"<p>new content</p>").unwrap();
+        let new: ResponseMessage =
+            ResponseMessage::new_text_response(0, Compression::LZW, b"<p>new content</p>".to_vec());
+        assert!(request_response(&mut server, &dr, &request) == new);
+        assert_eq!(server.cache_stats(), CacheStats { hits: 1, misses: 2 });
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// test correct dir listing, the listed names and the names prefixed
+    /// with the content root resolve to the same files
     #[test]
     fn list_dir_test() {
-        let mut l: Vec<String> = list_dir(TEXT_PATH).unwrap_or_default();
-        assert!(
-            l.sort()
-                == vec![
-                    TEXT_PATH.to_string() + "file.html",
-                    TEXT_PATH.to_string() + "file2.html",
-                    TEXT_PATH.to_string() + "index.html",
-                    TEXT_PATH.to_string() + "three.html",
-                ]
-                .sort()
+        let root: ContentRoot = ContentRoot::new(TEXT_PATH);
+        let l: Vec<String> = root.list().unwrap_or_default();
+        assert_eq!(
+            l,
+            vec!["file.html", "file2.html", "index.html", "three.html"]
         );
+        for name in &l {
+            assert_eq!(
+                root.resolve(&(TEXT_PATH.to_string() + name)),
+                root.resolve(name)
+            );
+        }
     }
 
     /// tests correct [Nack] resend behaviour when a routing path is available
@@ -111,16 +157,9 @@ mod request_tests {
             ],
         };
         server.update_network_from_flood(&fr);
-        server.sent_history.insert(
-            0,
-            HistoryEntry {
-                hops: vec![0, 2],
-                receiver_id: 2,
-                frag_idx: 0,
-                n_frags: 1,
-                frag: [0; 128],
-            },
-        );
+        server
+            .sent_history
+            .insert(0, HistoryEntry::new(vec![0, 2], 2, 0, 1, [0; 128]));
         let nack: Nack = Nack {
             fragment_index: 0,
             nack_type: NackType::Dropped,
@@ -136,16 +175,9 @@ mod request_tests {
     #[test]
     fn test_resend_route_no_update() {
         let mut server: GenericServer<servers::Text> = get_dummy_server_text();
-        server.sent_history.insert(
-            0,
-            HistoryEntry {
-                hops: vec![0, 2],
-                receiver_id: 2,
-                frag_idx: 0,
-                n_frags: 1,
-                frag: [0; 128],
-            },
-        );
+        server
+            .sent_history
+            .insert(0, HistoryEntry::new(vec![0, 2], 2, 0, 1, [0; 128]));
         let nack: Nack = Nack {
             fragment_index: 0,
             nack_type: NackType::Dropped,
@@ -172,7 +204,12 @@ mod request_tests {
         let response: ResponseMessage = ResponseMessage::new_text_list_response(
             0,
             Compression::LZW,
-            list_dir(TEXT_PATH).unwrap(),
+            vec![
+                "file.html".to_owned(),
+                "file2.html".to_owned(),
+                "index.html".to_owned(),
+                "three.html".to_owned(),
+            ],
         );
         test_handle_request(get_dummy_server_text(), compressor, request, response);
     }
@@ -205,6 +242,43 @@ mod request_tests {
         test_handle_request(get_dummy_server_text(), compressor, request, response);
     }
 
+    /// specialised [test_handle_request]
+    #[test]
+    fn test_text_server_handle_relative_file_request() {
+        let compressor: LZWCompressor = LZWCompressor::new();
+        let request: RequestMessage =
+            RequestMessage::new_text_request(1, Compression::LZW, "file.html".to_string());
+        let response: ResponseMessage = ResponseMessage::new_text_response(
+            0,
+            Compression::LZW,
+            read(TEXT_PATH.to_owned() + "file.html").unwrap(),
+        );
+        test_handle_request(get_dummy_server_text(), compressor, request, response);
+    }
+
+    /// specialised [test_handle_request], files outside of the content root are refused
+    /// as if they did not exist
+    #[test]
+    fn test_text_server_handle_traversal_request() {
+        let compressor: LZWCompressor = LZWCompressor::new();
+        let request: RequestMessage =
+            RequestMessage::new_text_request(1, Compression::LZW, "../src/lib.rs".to_string());
+        let response: ResponseMessage =
+            ResponseMessage::new_not_found_response(0, Compression::LZW);
+        test_handle_request(get_dummy_server_text(), compressor, request, response);
+    }
+
+    /// specialised [test_handle_request], absolute paths are refused as if they did not exist
+    #[test]
+    fn test_text_server_handle_absolute_request() {
+        let compressor: LZWCompressor = LZWCompressor::new();
+        let request: RequestMessage =
+            RequestMessage::new_text_request(1, Compression::LZW, "/etc/passwd".to_string());
+        let response: ResponseMessage =
+            ResponseMessage::new_not_found_response(0, Compression::LZW);
+        test_handle_request(get_dummy_server_text(), compressor, request, response);
+    }
+
     /// specialised [test_handle_request]
     #[test]
     fn test_text_server_handle_media_request() {
@@ -234,7 +308,12 @@ mod request_tests {
         let response: ResponseMessage = ResponseMessage::new_media_list_response(
             0,
             Compression::LZW,
-            list_dir(MEDIA_PATH).unwrap(),
+            vec![
+                "c++.png".to_owned(),
+                "haskell.jpg".to_owned(),
+                "image.jpg".to_owned(),
+                "rust.png".to_owned(),
+            ],
         );
         test_handle_request(get_dummy_server_media(), compressor, request, response);
     }
@@ -256,6 +335,20 @@ mod request_tests {
         test_handle_request(get_dummy_server_media(), compressor, request, response);
     }
 
+    /// specialised [`test_handle_request`], the media is requested by its logical name
+    #[test]
+    fn test_media_server_handle_logical_file_request() {
+        let compressor: HuffmanCompressor = HuffmanCompressor::new();
+        let request: RequestMessage =
+            RequestMessage::new_media_request(1, Compression::Huffman, "image.jpg".to_owned());
+        let response: ResponseMessage = ResponseMessage::new_media_response(
+            0,
+            Compression::Huffman,
+            read(MEDIA_PATH.to_owned() + "image.jpg").unwrap(),
+        );
+        test_handle_request(get_dummy_server_media(), compressor, request, response);
+    }
+
     /// specialised [test_handle_request]
     #[test]
     fn test_media_server_handle_unknown_file_request() {
@@ -267,6 +360,21 @@ mod request_tests {
         test_handle_request(get_dummy_server_media(), compressor, request, response);
     }
 
+    /// specialised [test_handle_request], files outside of the content root are refused
+    /// as if they did not exist
+    #[test]
+    fn test_media_server_handle_traversal_request() {
+        let compressor: LZWCompressor = LZWCompressor::new();
+        let request: RequestMessage = RequestMessage::new_media_request(
+            1,
+            Compression::LZW,
+            MEDIA_PATH.to_owned() + "../public/file.html",
+        );
+        let response: ResponseMessage =
+            ResponseMessage::new_not_found_response(0, Compression::LZW);
+        test_handle_request(get_dummy_server_media(), compressor, request, response);
+    }
+
     /// specialised [test_handle_request]
     #[test]
     fn test_media_server_handle_text_request() {
@@ -277,4 +385,112 @@ mod request_tests {
             ResponseMessage::new_invalid_request_response(0, Compression::LZW);
         test_handle_request(get_dummy_server_media(), compressor, request, response);
     }
+
+    /// tests that undeserializable requests are answered with an uncompressed error response
+    /// and that the error counters are sent to the controller
+    #[test]
+    fn test_undeserializable_request() {
+        let (ext_send, ext_recv) = crossbeam_channel::unbounded();
+        let (mut server, dr): (GenericServer<servers::Media>, _) =
+            get_routed_server(get_dummy_builder(0).extended_events(ext_send), &[2]);
+
+        server.handle_request(
+            &SourceRoutingHeader::new(vec![2, 1, 0], 2),
+            2,
+            42,
+            vec![[0xFF; 128]],
+        );
+        server.wait_workers();
+        let mut v: Vec<[u8; 128]> = Vec::new();
+        while let Ok(p) = dr.try_recv() {
+            assert_eq!(p.session_id & 0xFFFF, 42);
+            if let PacketType::MsgFragment(f) = p.pack_type {
+                v.push(f.data);
+            }
+        }
+        let resp: ResponseMessage = ResponseMessage::deserialize(v.into_flattened()).unwrap();
+        assert!(resp == ResponseMessage::new_invalid_request_response(0, Compression::None));
+
+        let counters: ErrorCounters = ErrorCounters {
+            undeserializable: 1,
+            invalid: 0,
+            forbidden: 0,
+            rejected: 0,
+        };
+        assert_eq!(server.error_counters(), counters);
+        assert_eq!(
+            ext_recv.try_recv().unwrap(),
+            ExtendedEvent::Errors(counters)
+        );
+    }
+
+    /// tests that unsupported requests are counted as invalid
+    #[test]
+    fn test_invalid_request_counter() {
+        let mut server: GenericServer<servers::Text> = get_dummy_server_text();
+        let request: RequestMessage =
+            RequestMessage::new_media_request(1, Compression::None, "rust.png".to_string());
+        server.handle_request(
+            &SourceRoutingHeader::new(vec![2, 1, 0], 2),
+            2,
+            0,
+            fragment_response(request.serialize().unwrap()),
+        );
+        server.wait_workers();
+        assert_eq!(server.error_counters().invalid, 1);
+        assert_eq!(server.error_counters().undeserializable, 0);
+    }
+
+    /// tests that the requests outside of the content root are counted apart from the invalid ones
+    #[test]
+    fn test_forbidden_request_counter() {
+        let mut server: GenericServer<servers::Text> = get_dummy_server_text();
+        let request: RequestMessage =
+            RequestMessage::new_text_request(1, Compression::None, "../src/lib.rs".to_string());
+        server.handle_request(
+            &SourceRoutingHeader::new(vec![2, 1, 0], 2),
+            2,
+            0,
+            fragment_response(request.serialize().unwrap()),
+        );
+        server.wait_workers();
+        assert_eq!(server.error_counters().forbidden, 1);
+        assert_eq!(server.error_counters().invalid, 0);
+    }
+
+    /// service answering every request with the same text
+    struct EchoService;
+
+    impl RequestService for EchoService {
+        fn server_type(&self) -> ServerType {
+            ServerType::MediaServer
+        }
+
+        fn handle(&mut self, server_id: NodeId, request: &RequestMessage) -> Reply {
+            Reply::Response(ResponseMessage::new_text_response(
+                server_id,
+                request.compression_type.clone(),
+                b"echo".to_vec(),
+            ))
+        }
+    }
+
+    /// tests that a custom service answers the requests of the server
+    #[test]
+    fn test_custom_service() {
+        let (mut server, dr): (GenericServer<servers::Text>, _) =
+            get_routed_server(get_dummy_builder(0).service(EchoService), &[2]);
+
+        let request: RequestMessage =
+            RequestMessage::new_text_request(1, Compression::LZW, "file.html".to_string());
+        assert_eq!(
+            request_response(&mut server, &dr, &request),
+            ResponseMessage::new_text_response(0, Compression::LZW, b"echo".to_vec())
+        );
+        let request: RequestMessage = RequestMessage::new_type_request(1, Compression::LZW);
+        assert_eq!(
+            request_response(&mut server, &dr, &request),
+            ResponseMessage::new_type_response(0, Compression::LZW, ServerType::MediaServer)
+        );
+    }
 }
@@ -0,0 +1,108 @@
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+
+use super::{extensions::ExtendedEvent, GenericServer, HistoryEntry, ServerType};
+
+/// testing module
+#[cfg(test)]
+mod test;
+
+/// default timeout of the first transmission of a fragment
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(500);
+/// default upper bound of the timeout after the exponential backoff
+const DEFAULT_MAX_TIMEOUT: Duration = Duration::from_secs(8);
+/// default number of retransmissions after which a fragment is given up
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+
+/// Configuration of the retransmission timer of the unacknowledged fragments.
+/// The timeout doubles at every retransmission of the same fragment, up to `max_timeout`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RetransmissionConfig {
+    /// timeout of the first transmission of a fragment
+    pub(crate) timeout: Duration,
+    /// upper bound of the timeout after the exponential backoff
+    pub(crate) max_timeout: Duration,
+    /// number of retransmissions after which a fragment is given up
+    pub(crate) max_attempts: u32,
+}
+
+impl Default for RetransmissionConfig {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_TIMEOUT,
+            max_timeout: DEFAULT_MAX_TIMEOUT,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        }
+    }
+}
+
+impl RetransmissionConfig {
+    /// timeout of a fragment that has already been retransmitted `attempts` times
+    #[must_use]
+    pub(crate) fn timeout(&self, attempts: u32) -> Duration {
+        self.timeout
+            .saturating_mul(2_u32.saturating_pow(attempts))
+            .min(self.max_timeout)
+    }
+}
+
+impl HistoryEntry {
+    /// starts the retransmission timer of a fragment that has just been sent
+    pub(super) fn start_timer(&mut self, config: &RetransmissionConfig, now: Instant) {
+        self.deadline = Some(now + config.timeout(self.attempts));
+    }
+}
+
+impl<T: ServerType> GenericServer<T> {
+    /// retransmits the fragments whose timer expired before `now`. The fragments that
+    /// have already been retransmitted the maximum number of times are removed from the
+    /// sent history and reported to the controller
+    pub(super) fn retransmit_expired(&mut self, now: Instant) {
+        let expired: Vec<u64> = self
+            .sent_history
+            .iter()
+            .filter(|(_, e): &(&u64, &HistoryEntry)| e.deadline.is_some_and(|d| d <= now))
+            .map(|(sid, _): (&u64, &HistoryEntry)| *sid)
+            .collect();
+
+        for sid in expired {
+            let Some(entry) = self.sent_history.get_mut(&sid) else {
+                continue;
+            };
+            if entry.attempts >= self.retransmission.max_attempts {
+                let HistoryEntry {
+                    receiver_id,
+                    frag_idx,
+                    n_frags,
+                    attempts,
+                    ..
+                } = *entry;
+                self.sent_history.remove(&sid);
+                warn!(target: &self.target_topic, "Giving up sid: {sid} after {attempts} retransmissions");
+                self.send_extended_event(ExtendedEvent::FragmentGivenUp {
+                    session_id: sid,
+                    receiver_id,
+                    frag_idx,
+                    n_frags,
+                });
+                self.window_on_give_up(receiver_id, sid);
+                continue;
+            }
+
+            entry.attempts += 1;
+            entry.deadline = None;
+            let HistoryEntry {
+                receiver_id,
+                frag_idx,
+                n_frags,
+                frag,
+                attempts,
+                ..
+            } = *entry;
+            info!(target: &self.target_topic, "Timeout of sid: {sid}, retransmission {attempts}");
+            self.metrics.retransmissions += 1;
+            self.resend_packet(sid, receiver_id, frag_idx, n_frags, frag);
+        }
+    }
+}
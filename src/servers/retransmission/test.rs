@@ -0,0 +1,141 @@
+#[cfg(test)]
+mod retransmission_tests {
+    use std::time::{Duration, Instant};
+
+    use crossbeam_channel::Receiver;
+    use wg_2024::packet::{Packet, PacketType, FRAGMENT_DSIZE};
+
+    use crate::{
+        servers::{
+            extensions::ExtendedEvent,
+            retransmission::RetransmissionConfig,
+            test_utils::{get_dummy_builder, get_routed_server},
+            HistoryEntry, Text,
+        },
+        GenericServer,
+    };
+
+    /// get a [`GenericServer<Text>`] with a route to the client 2, the packets sent to the
+    /// drone 1 and the [`ExtendedEvent`]s can be read from the returned channels
+    fn get_server_with_route(
+        max_attempts: u32,
+    ) -> (
+        GenericServer<Text>,
+        Receiver<Packet>,
+        Receiver<ExtendedEvent>,
+    ) {
+        let (ext_send, ext_recv) = crossbeam_channel::unbounded();
+        let (server, dr) = get_routed_server(
+            get_dummy_builder(0)
+                .extended_events(ext_send)
+                .max_retransmissions(max_attempts),
+            &[2],
+        );
+        (server, dr, ext_recv)
+    }
+
+    /// tests the exponential backoff of the timeout
+    #[test]
+    fn test_backoff() {
+        let config: RetransmissionConfig = RetransmissionConfig {
+            timeout: Duration::from_millis(100),
+            max_timeout: Duration::from_millis(500),
+            max_attempts: 5,
+        };
+        assert_eq!(config.timeout(0), Duration::from_millis(100));
+        assert_eq!(config.timeout(1), Duration::from_millis(200));
+        assert_eq!(config.timeout(2), Duration::from_millis(400));
+        assert_eq!(config.timeout(3), Duration::from_millis(500));
+        assert_eq!(config.timeout(u32::MAX), Duration::from_millis(500));
+    }
+
+    /// tests that only the expired fragments are retransmitted
+    #[test]
+    fn test_retransmit_expired() {
+        let (mut server, dr, _) = get_server_with_route(5);
+        let now: Instant = Instant::now();
+        let mut expired: HistoryEntry =
+            HistoryEntry::new(vec![0, 1, 2], 2, 0, 2, [1; FRAGMENT_DSIZE]);
+        expired.deadline = Some(now);
+        let mut waiting: HistoryEntry =
+            HistoryEntry::new(vec![0, 1, 2], 2, 1, 2, [2; FRAGMENT_DSIZE]);
+        waiting.deadline = Some(now + Duration::from_secs(1));
+        server.sent_history.insert(0, expired);
+        server.sent_history.insert(1, waiting);
+
+        server.retransmit_expired(now);
+
+        let packet: Packet = dr.try_recv().unwrap();
+        assert_eq!(packet.session_id, 0);
+        assert_eq!(packet.routing_header.hops, vec![0, 1, 2]);
+        assert!(
+            matches!(packet.pack_type, PacketType::MsgFragment(f) if f.data == [1; FRAGMENT_DSIZE])
+        );
+        assert!(dr.try_recv().is_err());
+
+        let entry: &HistoryEntry = server.sent_history.get(&0).unwrap();
+        assert_eq!(entry.attempts, 1);
+        assert!(entry.deadline.unwrap() >= now + server.retransmission.timeout(1));
+        assert_eq!(server.sent_history.get(&1).unwrap().attempts, 0);
+    }
+
+    /// tests that fragments waiting for a route are not retransmitted by the timer
+    #[test]
+    fn test_pending_not_retransmitted() {
+        let (mut server, dr, _) = get_server_with_route(5);
+        server.sent_history.insert(
+            0,
+            HistoryEntry::new(vec![0, 1, 2], 2, 0, 1, [0; FRAGMENT_DSIZE]),
+        );
+        server.retransmit_expired(Instant::now() + Duration::from_secs(60));
+        assert!(dr.try_recv().is_err());
+        assert_eq!(server.sent_history.get(&0).unwrap().attempts, 0);
+    }
+
+    /// tests that a fragment is given up after the maximum number of retransmissions
+    #[test]
+    fn test_give_up() {
+        let (mut server, dr, ext_recv) = get_server_with_route(2);
+        let mut entry: HistoryEntry =
+            HistoryEntry::new(vec![0, 1, 2], 2, 3, 4, [0; FRAGMENT_DSIZE]);
+        entry.deadline = Some(Instant::now());
+        server.sent_history.insert(7, entry);
+
+        for _ in 0..2 {
+            let deadline: Instant = server.sent_history.get(&7).unwrap().deadline.unwrap();
+            server.retransmit_expired(deadline);
+            assert!(dr.try_recv().is_ok());
+        }
+        let deadline: Instant = server.sent_history.get(&7).unwrap().deadline.unwrap();
+        server.retransmit_expired(deadline);
+        assert!(dr.try_recv().is_err());
+        assert!(server.sent_history.is_empty());
+        assert_eq!(
+            ext_recv.try_recv().unwrap(),
+            ExtendedEvent::FragmentGivenUp {
+                session_id: 7,
+                receiver_id: 2,
+                frag_idx: 3,
+                n_frags: 4,
+            }
+        );
+    }
+
+    /// tests that the timer is started when a response is sent
+    #[test]
+    fn test_timer_started_on_send() {
+        let (mut server, dr, _) = get_server_with_route(5);
+        let before: Instant = Instant::now();
+        server.send_fragments(
+            &wg_2024::network::SourceRoutingHeader::new(vec![2, 1, 0], 2),
+            2,
+            0,
+            vec![[0; FRAGMENT_DSIZE]; 3],
+        );
+        assert_eq!(dr.len(), 3);
+        assert_eq!(server.sent_history.len(), 3);
+        for entry in server.sent_history.values() {
+            assert!(entry.deadline.unwrap() >= before + server.retransmission.timeout(0));
+        }
+    }
+}
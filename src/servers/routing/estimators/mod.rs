@@ -0,0 +1,461 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Debug,
+};
+
+use serde::{Deserialize, Serialize};
+use wg_2024::network::NodeId;
+
+use crate::servers::{DEFAULT_ALPHA, DEFAULT_WINDOW_SZ, INITIAL_PDR};
+
+/// testing module
+#[cfg(test)]
+mod test;
+
+/// Strategy used by the routing table of the [`crate::GenericServer`] to estimate the pdr of the drones.
+///
+/// The routing table collects the acks and nacks of every drone and, every
+/// [`PdrEstimator::window_sz`] samples, asks the estimator for the new pdr of the drone.
+/// The estimator can keep its own state for each drone
+pub trait PdrEstimator: Debug + Send {
+    /// number of samples of a drone collected before each update of its pdr,
+    /// 0 disables the updates
+    fn window_sz(&self) -> u32;
+
+    /// pdr assigned to newly discovered drones
+    fn initial_pdr(&self) -> f64;
+
+    /// new pdr of the drone `id`, given its current pdr and the number of
+    /// acks and nacks collected in the last window
+    fn estimate(&mut self, id: NodeId, old: f64, acks: u32, nacks: u32) -> f64;
+
+    /// moves the state kept for the drone `id` toward the prior, `factor` is the fraction
+    /// of the distance from the prior that is kept. Called when the pdr estimations decay
+    fn decay(&mut self, _id: NodeId, _factor: f64) {}
+
+    /// forgets the state kept for the drone `id`, called when it is removed from the network
+    fn forget(&mut self, _id: NodeId) {}
+
+    /// kind, parameters and state kept for each drone, saved with the routing state
+    /// of the server. By default nothing is saved
+    fn save_state(&self) -> EstimatorState {
+        EstimatorState::None
+    }
+
+    /// restores a state returned by [`PdrEstimator::save_state`], false if it was saved
+    /// by an estimator of another kind or with other parameters, in which case the
+    /// estimator starts from scratch
+    fn restore_state(&mut self, _state: &EstimatorState) -> bool {
+        false
+    }
+}
+
+/// (id, samples and their weight, weight of the initial pdr) of a drone, saved
+/// by the [`WindowEstimator`]
+type SavedSamples = (NodeId, Vec<(bool, f64)>, f64);
+
+/// State of a [`PdrEstimator`] saved with the routing state of the [`crate::GenericServer`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum EstimatorState {
+    /// nothing is saved
+    None,
+    /// [`FnEstimator`] with the given alpha, the function itself is not saved
+    Fn {
+        /// smoothing constant given to the estimator function
+        alpha: f64,
+    },
+    /// [`EwmaEstimator`] with the given alpha
+    Ewma {
+        /// smoothing constant
+        alpha: f64,
+    },
+    /// [`BetaEstimator`] with its prior and the posteriors of the drones
+    Beta {
+        /// prior (a, b) of every drone
+        prior: (f64, f64),
+        /// posterior (a, b) of the sampled drones, sorted by id
+        posteriors: Vec<(NodeId, (f64, f64))>,
+    },
+    /// [`WindowEstimator`] with the last samples of the drones
+    Window {
+        /// number of samples used to compute the pdr
+        samples: usize,
+        /// samples of the drones, sorted by id
+        history: Vec<SavedSamples>,
+    },
+    /// opaque state of an estimator defined outside of this crate
+    Custom(Vec<u8>),
+}
+
+/// Stateless [`PdrEstimator`] that computes the new pdr with a function of
+/// (alpha, old pdr, #acks, #nacks)
+#[derive(Debug, Clone)]
+pub struct FnEstimator {
+    /// window size for time n
+    window_sz: u32,
+    /// smoothing constant given to the estimator function
+    alpha: f64,
+    /// pdr assigned to newly discovered nodes
+    initial_pdr: f64,
+    /// estimator function
+    estimator: fn(alpha: f64, old: f64, acks: u32, nacks: u32) -> f64,
+}
+
+impl FnEstimator {
+    /// creates a new [`FnEstimator`] from the given parameters, alpha and the initial
+    /// pdr are set to their defaults, 0.35 and 0.5
+    #[inline]
+    #[must_use]
+    pub fn new(
+        window_sz: u32,
+        estimator: fn(alpha: f64, old: f64, acks: u32, nacks: u32) -> f64,
+    ) -> Self {
+        Self {
+            window_sz,
+            alpha: DEFAULT_ALPHA,
+            initial_pdr: INITIAL_PDR,
+            estimator,
+        }
+    }
+
+    /// sets the smoothing constant given to the estimator function
+    #[inline]
+    #[must_use]
+    pub fn with_alpha(mut self, alpha: f64) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// sets the pdr assigned to newly discovered nodes
+    #[inline]
+    #[must_use]
+    pub fn with_initial_pdr(mut self, initial_pdr: f64) -> Self {
+        self.initial_pdr = initial_pdr;
+        self
+    }
+}
+
+impl PdrEstimator for FnEstimator {
+    fn window_sz(&self) -> u32 {
+        self.window_sz
+    }
+
+    fn initial_pdr(&self) -> f64 {
+        self.initial_pdr
+    }
+
+    fn estimate(&mut self, _id: NodeId, old: f64, acks: u32, nacks: u32) -> f64 {
+        (self.estimator)(self.alpha, old, acks, nacks)
+    }
+
+    fn save_state(&self) -> EstimatorState {
+        EstimatorState::Fn { alpha: self.alpha }
+    }
+
+    fn restore_state(&mut self, state: &EstimatorState) -> bool {
+        matches!(state, EstimatorState::Fn { alpha } if alpha.to_bits() == self.alpha.to_bits())
+    }
+}
+
+/// Default [`PdrEstimator`]: exponentially weighted moving average (EWMA)
+/// of the pdr measured in each window.
+/// the formula is as follows:
+///     PDR(n) = p(n) * alpha + PDR(n - 1) * (1 - alpha)
+/// where p(n) is the ratio of acks in the last window
+#[derive(Debug, Clone)]
+pub struct EwmaEstimator {
+    /// window size for time n
+    window_sz: u32,
+    /// smoothing constant, i.e. how fast the pdr adapts to change
+    alpha: f64,
+    /// pdr assigned to newly discovered nodes
+    initial_pdr: f64,
+}
+
+impl Default for EwmaEstimator {
+    fn default() -> Self {
+        Self {
+            window_sz: DEFAULT_WINDOW_SZ,
+            alpha: DEFAULT_ALPHA,
+            initial_pdr: INITIAL_PDR,
+        }
+    }
+}
+
+impl EwmaEstimator {
+    /// sets the window size of the [`EwmaEstimator`], 0 disables the updates of the pdr
+    #[inline]
+    #[must_use]
+    pub fn with_window_sz(mut self, window_sz: u32) -> Self {
+        self.window_sz = window_sz;
+        self
+    }
+
+    /// sets the smoothing constant of the [`EwmaEstimator`]
+    #[inline]
+    #[must_use]
+    pub fn with_alpha(mut self, alpha: f64) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// sets the pdr assigned to newly discovered nodes
+    #[inline]
+    #[must_use]
+    pub fn with_initial_pdr(mut self, initial_pdr: f64) -> Self {
+        self.initial_pdr = initial_pdr;
+        self
+    }
+}
+
+impl PdrEstimator for EwmaEstimator {
+    fn window_sz(&self) -> u32 {
+        self.window_sz
+    }
+
+    fn initial_pdr(&self) -> f64 {
+        self.initial_pdr
+    }
+
+    fn estimate(&mut self, _id: NodeId, old: f64, acks: u32, nacks: u32) -> f64 {
+        self.alpha * (f64::from(acks) / f64::from(acks + nacks)) + (1. - self.alpha) * old
+    }
+
+    fn save_state(&self) -> EstimatorState {
+        EstimatorState::Ewma { alpha: self.alpha }
+    }
+
+    fn restore_state(&mut self, state: &EstimatorState) -> bool {
+        matches!(state, EstimatorState::Ewma { alpha } if alpha.to_bits() == self.alpha.to_bits())
+    }
+}
+
+/// Bayesian [`PdrEstimator`]: the pdr of each drone is the mean of a Beta(a, b)
+/// posterior, where a and b are the prior plus the acks and nacks observed so far
+#[derive(Debug, Clone)]
+pub struct BetaEstimator {
+    /// window size for time n
+    window_sz: u32,
+    /// prior (a, b) of every drone
+    prior: (f64, f64),
+    /// posterior (a, b) of the drones that have been sampled
+    posteriors: HashMap<NodeId, (f64, f64)>,
+}
+
+impl Default for BetaEstimator {
+    /// uniform prior Beta(1, 1), updated after every sample
+    fn default() -> Self {
+        Self::new(1., 1.)
+    }
+}
+
+impl BetaEstimator {
+    /// creates a new [`BetaEstimator`] with the prior Beta(`a`, `b`),
+    /// updated after every sample
+    ///
+    /// # Panics
+    /// Panics if `a` or `b` are not positive
+    #[must_use]
+    pub fn new(a: f64, b: f64) -> Self {
+        assert!(a > 0. && b > 0., "the prior parameters must be positive");
+        Self {
+            window_sz: 1,
+            prior: (a, b),
+            posteriors: HashMap::new(),
+        }
+    }
+
+    /// sets the window size of the [`BetaEstimator`]
+    #[inline]
+    #[must_use]
+    pub fn with_window_sz(mut self, window_sz: u32) -> Self {
+        self.window_sz = window_sz;
+        self
+    }
+}
+
+impl PdrEstimator for BetaEstimator {
+    fn window_sz(&self) -> u32 {
+        self.window_sz
+    }
+
+    fn initial_pdr(&self) -> f64 {
+        self.prior.0 / (self.prior.0 + self.prior.1)
+    }
+
+    fn estimate(&mut self, id: NodeId, _old: f64, acks: u32, nacks: u32) -> f64 {
+        let (a, b): &mut (f64, f64) = self.posteriors.entry(id).or_insert(self.prior);
+        *a += f64::from(acks);
+        *b += f64::from(nacks);
+        *a / (*a + *b)
+    }
+
+    fn decay(&mut self, id: NodeId, factor: f64) {
+        let (a0, b0): (f64, f64) = self.prior;
+        if let Some((a, b)) = self.posteriors.get_mut(&id) {
+            *a = a0 + (*a - a0) * factor;
+            *b = b0 + (*b - b0) * factor;
+        }
+    }
+
+    fn forget(&mut self, id: NodeId) {
+        self.posteriors.remove(&id);
+    }
+
+    fn save_state(&self) -> EstimatorState {
+        let mut posteriors: Vec<(NodeId, (f64, f64))> =
+            self.posteriors.iter().map(|(&id, &p)| (id, p)).collect();
+        posteriors.sort_unstable_by_key(|p: &(NodeId, (f64, f64))| p.0);
+        EstimatorState::Beta {
+            prior: self.prior,
+            posteriors,
+        }
+    }
+
+    fn restore_state(&mut self, state: &EstimatorState) -> bool {
+        let EstimatorState::Beta { prior, posteriors } = state else {
+            return false;
+        };
+        let (a0, b0): (f64, f64) = self.prior;
+        if prior.0.to_bits() != a0.to_bits() || prior.1.to_bits() != b0.to_bits() {
+            return false;
+        }
+        self.posteriors = posteriors.iter().copied().collect();
+        true
+    }
+}
+
+/// last samples of a drone kept by the [`WindowEstimator`]
+#[derive(Debug, Clone, Default)]
+struct SampleWindow {
+    /// samples, true if acknowledged, with their weight
+    samples: VecDeque<(bool, f64)>,
+    /// weight of the initial pdr, given back by the decays of the samples
+    prior: f64,
+}
+
+impl SampleWindow {
+    /// total weight of the samples
+    fn weight(&self) -> f64 {
+        self.samples.iter().map(|(_, w): &(bool, f64)| w).sum()
+    }
+}
+
+/// [`PdrEstimator`] using the raw ratio of acks among the last samples of each drone.
+///
+/// The decays reweight the samples, giving the lost weight to the initial pdr,
+/// which is then aged out by the new samples
+#[derive(Debug, Clone)]
+pub struct WindowEstimator {
+    /// number of samples used to compute the pdr
+    samples: usize,
+    /// pdr assigned to newly discovered nodes
+    initial_pdr: f64,
+    /// last samples of the drones
+    history: HashMap<NodeId, SampleWindow>,
+}
+
+impl WindowEstimator {
+    /// creates a new [`WindowEstimator`] using the last `samples` samples of each drone,
+    /// updated after every sample
+    ///
+    /// # Panics
+    /// Panics if `samples` is 0
+    #[must_use]
+    pub fn new(samples: usize) -> Self {
+        assert!(samples > 0, "the number of samples must be positive");
+        Self {
+            samples,
+            initial_pdr: INITIAL_PDR,
+            history: HashMap::new(),
+        }
+    }
+
+    /// sets the pdr assigned to newly discovered nodes
+    #[inline]
+    #[must_use]
+    pub fn with_initial_pdr(mut self, initial_pdr: f64) -> Self {
+        self.initial_pdr = initial_pdr;
+        self
+    }
+}
+
+impl PdrEstimator for WindowEstimator {
+    fn window_sz(&self) -> u32 {
+        1
+    }
+
+    fn initial_pdr(&self) -> f64 {
+        self.initial_pdr
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn estimate(&mut self, id: NodeId, _old: f64, acks: u32, nacks: u32) -> f64 {
+        let window: &mut SampleWindow = self.history.entry(id).or_default();
+        window.samples.extend((0..nacks).map(|_| (false, 1.)));
+        window.samples.extend((0..acks).map(|_| (true, 1.)));
+        while window.samples.len() > self.samples {
+            window.samples.pop_front();
+        }
+        // the weight of the initial pdr fills only the room left by the samples,
+        // the window holds at most `samples` elements
+        let weight: f64 = window.weight();
+        window.prior = window.prior.min(self.samples as f64 - weight).max(0.);
+        let acked: f64 = window
+            .samples
+            .iter()
+            .filter(|(r, _): &&(bool, f64)| *r)
+            .map(|(_, w): &(bool, f64)| w)
+            .sum::<f64>();
+        (acked + window.prior * self.initial_pdr) / (weight + window.prior)
+    }
+
+    fn decay(&mut self, id: NodeId, factor: f64) {
+        if let Some(window) = self.history.get_mut(&id) {
+            let weight: f64 = window.weight();
+            window.prior = window.prior * factor + weight * (1. - factor);
+            for (_, w) in &mut window.samples {
+                *w *= factor;
+            }
+        }
+    }
+
+    fn forget(&mut self, id: NodeId) {
+        self.history.remove(&id);
+    }
+
+    fn save_state(&self) -> EstimatorState {
+        let mut history: Vec<SavedSamples> = self
+            .history
+            .iter()
+            .map(|(&id, w): (&NodeId, &SampleWindow)| {
+                (id, w.samples.iter().copied().collect(), w.prior)
+            })
+            .collect();
+        history.sort_unstable_by_key(|h: &SavedSamples| h.0);
+        EstimatorState::Window {
+            samples: self.samples,
+            history,
+        }
+    }
+
+    fn restore_state(&mut self, state: &EstimatorState) -> bool {
+        let EstimatorState::Window { samples, history } = state else {
+            return false;
+        };
+        if *samples != self.samples {
+            return false;
+        }
+        self.history = history
+            .iter()
+            .map(|(id, samples, prior): &SavedSamples| {
+                let window: SampleWindow = SampleWindow {
+                    samples: samples.iter().copied().collect(),
+                    prior: *prior,
+                };
+                (*id, window)
+            })
+            .collect();
+        true
+    }
+}
@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod estimators_tests {
+    use crate::servers::{
+        routing::{BetaEstimator, EwmaEstimator, FnEstimator, PdrEstimator, WindowEstimator},
+        DEFAULT_WINDOW_SZ, INITIAL_PDR,
+    };
+
+    /// tests that the default EWMA matches the documented formula
+    #[test]
+    fn test_ewma() {
+        let mut estimator: EwmaEstimator = EwmaEstimator::default();
+        assert_eq!(estimator.window_sz(), DEFAULT_WINDOW_SZ);
+        assert!(estimator.initial_pdr() == INITIAL_PDR);
+        let pdr: f64 = estimator.estimate(1, 0.5, 9, 3);
+        assert!((pdr - (0.35 * 0.75 + 0.65 * 0.5)).abs() < 1e-9);
+
+        let mut estimator: EwmaEstimator = EwmaEstimator::default().with_alpha(1.);
+        assert!((estimator.estimate(1, 0.5, 1, 3) - 0.25).abs() < 1e-9);
+    }
+
+    /// tests that the function of the [`FnEstimator`] receives its parameters
+    #[test]
+    fn test_fn_estimator() {
+        let mut estimator: FnEstimator = FnEstimator::new(4, |alpha, old, acks, nacks| {
+            alpha + old + f64::from(acks) * 10. + f64::from(nacks) * 100.
+        })
+        .with_alpha(0.25)
+        .with_initial_pdr(0.75);
+        assert_eq!(estimator.window_sz(), 4);
+        assert!(estimator.initial_pdr() == 0.75);
+        assert!((estimator.estimate(1, 0.5, 1, 2) - 210.75).abs() < 1e-9);
+    }
+
+    /// tests that the [`BetaEstimator`] returns the posterior mean of each drone
+    #[test]
+    fn test_beta() {
+        let mut estimator: BetaEstimator = BetaEstimator::default();
+        assert_eq!(estimator.window_sz(), 1);
+        assert!(estimator.initial_pdr() == INITIAL_PDR);
+        // Beta(1 + 1, 1)
+        assert!((estimator.estimate(1, 0.5, 1, 0) - 2. / 3.).abs() < 1e-9);
+        // Beta(1 + 1, 1 + 1)
+        assert!((estimator.estimate(1, 0.1, 0, 1) - 0.5).abs() < 1e-9);
+        // Beta(2 + 6, 2 + 2)
+        assert!((estimator.estimate(1, 0.5, 6, 2) - 8. / 12.).abs() < 1e-9);
+        // other drones start from the prior
+        assert!((estimator.estimate(2, 0.5, 0, 1) - 1. / 3.).abs() < 1e-9);
+
+        // Beta(1, 1 + 0.5)
+        estimator.decay(2, 0.5);
+        assert!((estimator.estimate(2, 0.5, 0, 0) - 1. / 2.5).abs() < 1e-9);
+
+        estimator.forget(1);
+        assert!((estimator.estimate(1, 0.5, 0, 1) - 1. / 3.).abs() < 1e-9);
+
+        let estimator: BetaEstimator = BetaEstimator::new(3., 1.).with_window_sz(5);
+        assert_eq!(estimator.window_sz(), 5);
+        assert!(estimator.initial_pdr() == 0.75);
+    }
+
+    /// tests the validation of the prior
+    #[test]
+    #[should_panic(expected = "the prior parameters must be positive")]
+    fn test_invalid_beta() {
+        let _ = BetaEstimator::new(0., 1.);
+    }
+
+    /// tests that the [`WindowEstimator`] only uses the last samples of each drone
+    #[test]
+    fn test_window() {
+        let mut estimator: WindowEstimator = WindowEstimator::new(4).with_initial_pdr(0.25);
+        assert_eq!(estimator.window_sz(), 1);
+        assert!(estimator.initial_pdr() == 0.25);
+        assert!(estimator.estimate(1, 0.25, 1, 0) == 1.);
+        assert!(estimator.estimate(1, 1., 0, 1) == 0.5);
+        assert!(estimator.estimate(1, 0.5, 0, 1) == 1. / 3.);
+        assert!(estimator.estimate(1, 1. / 3., 0, 1) == 0.25);
+        // the first ack leaves the window
+        assert!(estimator.estimate(1, 0.25, 0, 1) == 0.);
+        assert!(estimator.estimate(1, 0., 1, 0) == 0.25);
+        assert!(estimator.estimate(2, 0.25, 1, 0) == 1.);
+
+        estimator.forget(1);
+        assert!(estimator.estimate(1, 0.25, 0, 1) == 0.);
+    }
+
+    /// tests that the decays of the [`WindowEstimator`] move the pdr toward the initial
+    /// pdr, until the new samples age it out
+    #[test]
+    fn test_window_decay() {
+        let mut estimator: WindowEstimator = WindowEstimator::new(4).with_initial_pdr(0.5);
+        assert!((estimator.estimate(1, 0.5, 4, 0) - 1.).abs() < 1e-9);
+        // nothing to decay for unknown drones
+        estimator.decay(2, 0.5);
+        assert!((estimator.estimate(2, 0.5, 1, 0) - 1.).abs() < 1e-9);
+
+        // half of the weight moves to the initial pdr
+        estimator.decay(1, 0.5);
+        assert!((estimator.estimate(1, 1., 0, 0) - 0.75).abs() < 1e-9);
+        // the oldest sample leaves, the new one takes room from the initial pdr
+        assert!((estimator.estimate(1, 0.75, 1, 0) - (2.5 + 0.75) / 4.).abs() < 1e-9);
+        assert!((estimator.estimate(1, 0.8, 3, 0) - 1.).abs() < 1e-9);
+
+        estimator.decay(1, 0.);
+        assert!((estimator.estimate(1, 1., 0, 0) - 0.5).abs() < 1e-9);
+        assert!((estimator.estimate(1, 0.5, 0, 4) - 0.).abs() < 1e-9);
+    }
+}
@@ -1,4 +1,7 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
 
 use itertools::Itertools;
 use log::{error, info, warn};
@@ -8,8 +11,21 @@ use wg_2024::{
     packet::{FloodResponse, NodeType},
 };
 
-use crate::servers::{GenericServer, NetworkGraph, ServerType, INITIAL_ETX, INITIAL_PDR};
+use crate::servers::{
+    topology::{EdgeSnapshot, NodeSnapshot, TopologySnapshot},
+    GenericServer, NetworkGraph, ServerType,
+};
+
+pub use estimators::{
+    BetaEstimator, EstimatorState, EwmaEstimator, FnEstimator, PdrEstimator, WindowEstimator,
+};
+pub use persistence::RoutingStateError;
+pub(crate) use persistence::StateFile;
 
+/// Module containing the [`PdrEstimator`] trait and its ready-made implementations
+mod estimators;
+/// Module containing the save and load of the state learned by the [`RoutingTable`]
+mod persistence;
 /// testing module
 #[cfg(test)]
 mod test;
@@ -20,74 +36,144 @@ mod test;
 /// (old, acks, nacks)
 struct PdrEntry(f64, u32, u32);
 
-/// generic pdr estimator, can implement a lot of different stategies
-/// from the given information (`old_etx`, #acks, #nacks)
-#[derive(Debug, Clone)]
-pub(crate) struct PdrEstimator {
-    /// window size for time n
-    window_sz: u32,
-    /// estimator function
-    estimator: fn(old: f64, acks: u32, nacks: u32) -> f64,
-}
-
-impl PdrEstimator {
-    /// creates a new [`PdrEstimator`] from the given parameters
-    #[inline]
-    #[must_use]
-    pub(crate) fn new(
-        window_sz: u32,
-        estimator: fn(old: f64, acks: u32, nacks: u32) -> f64,
-    ) -> Self {
-        Self {
-            window_sz,
-            estimator,
-        }
-    }
-}
-
 /// struct that handles the routing informations and decides the best routing paths based
 /// on the current ETX estimations of the drones.
-#[derive(Debug, Clone)]
+/// The drones are node-weighted: the ETX of a drone is the weight of the edges going into it,
+/// while the edges going into a client or a server have a fixed weight
+#[derive(Debug)]
 pub(crate) struct RoutingTable /* <const WINDOW_SIZE: u8> */ {
     /// graph representing the network
     graph: NetworkGraph,
-    /// `pdr_table` to chace current pdr estimations
+    /// `pdr_table` to chace current pdr estimations of the drones
     pdr_table: HashMap<NodeId, PdrEntry>,
-    /// estimator function
-    pdr_estimator: PdrEstimator,
+    /// clients and servers, which are not part of the `pdr_table`
+    endpoints: HashSet<NodeId>,
+    /// strategy used to estimate the pdr of the drones
+    pdr_estimator: Box<dyn PdrEstimator>,
+    /// half-life of the decay of the pdr estimations toward the initial pdr,
+    /// [None] if the estimations don't decay
+    half_life: Option<Duration>,
+    /// instant of the last decay of the pdr estimations
+    last_decay: Option<Instant>,
+    /// probability of choosing a non-optimal route
+    exploration: f64,
+    /// generator of the choices of the exploration
+    rng: SplitMix64,
 }
 
+/// small seeded pseudo-random generator, so that the exploration of the routes is
+/// reproducible
+#[derive(Debug, Clone)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    /// next pseudo-random number
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z: u64 = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// true with probability `p`.
+    /// The 53 bits used are exactly represented by a [f64]
+    #[allow(clippy::cast_precision_loss)]
+    fn gen_bool(&mut self, p: f64) -> bool {
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+
+    /// pseudo-random index in [0, len).
+    /// The result is smaller than `len`, so it fits in a [usize]
+    #[allow(clippy::cast_possible_truncation)]
+    fn gen_index(&mut self, len: usize) -> usize {
+        (self.next_u64() % len as u64) as usize
+    }
+}
+
+/// default maximum number of disjoint routes used to send a response
+pub(crate) const DEFAULT_MAX_PATHS: usize = 1;
+/// default half-life of the decay of the pdr estimations
+pub(crate) const DEFAULT_HALF_LIFE: Duration = Duration::from_secs(30);
+
 impl RoutingTable {
     /// after this limit the PDR is considered 0
     const EPSILON: f64 = 1e-3;
+    /// weight of the edges going into a client or a server
+    pub(crate) const ENDPOINT_ETX: f64 = 1.;
 
     /// creates a new [`RoutingTable`] from the given parameters
     #[inline]
     #[must_use]
-    pub(crate) fn new(pdr_estimator: PdrEstimator) -> Self {
+    pub(crate) fn new(pdr_estimator: Box<dyn PdrEstimator>) -> Self {
         Self {
             graph: NetworkGraph::new(),
             pdr_table: HashMap::new(),
+            endpoints: HashSet::new(),
             pdr_estimator,
+            half_life: Some(DEFAULT_HALF_LIFE),
+            last_decay: None,
+            exploration: 0.,
+            rng: SplitMix64(0),
         }
     }
 
     /// creates a new [`RoutingTable`] from an existing [`NetworkGraph`]   
     #[must_use]
-    pub(crate) fn new_with_graph(mut graph: NetworkGraph, pdr_estimator: PdrEstimator) -> Self {
-        let it = graph.nodes().map(|n: u8| (n, PdrEntry(INITIAL_PDR, 0, 0)));
+    pub(crate) fn new_with_graph(
+        mut graph: NetworkGraph,
+        pdr_estimator: impl PdrEstimator + 'static,
+    ) -> Self {
+        let initial_pdr: f64 = pdr_estimator.initial_pdr();
+        let it = graph.nodes().map(|n: u8| (n, PdrEntry(initial_pdr, 0, 0)));
         let pdr_table: HashMap<NodeId, PdrEntry> = it.collect();
         // guarantee consistency
         for (_, _, w) in graph.all_edges_mut() {
-            *w = INITIAL_ETX;
+            *w = 1. / initial_pdr;
         }
         Self {
             graph,
             pdr_table,
-            pdr_estimator,
+            endpoints: HashSet::new(),
+            pdr_estimator: Box::new(pdr_estimator),
+            half_life: Some(DEFAULT_HALF_LIFE),
+            last_decay: None,
+            exploration: 0.,
+            rng: SplitMix64(0),
         }
     }
 
+    /// sets the half-life of the decay of the pdr estimations, [None] disables the decay
+    #[inline]
+    #[must_use]
+    pub(crate) fn with_half_life(mut self, half_life: Option<Duration>) -> Self {
+        self.half_life = half_life;
+        self
+    }
+
+    /// sets the probability of choosing a non-optimal route
+    #[inline]
+    #[must_use]
+    pub(crate) fn with_exploration(mut self, exploration: f64) -> Self {
+        self.exploration = exploration;
+        self
+    }
+
+    /// sets the seed of the choices of the exploration
+    #[inline]
+    #[must_use]
+    pub(crate) fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = SplitMix64(seed);
+        self
+    }
+
+    /// ETX assigned to newly discovered nodes, depends on the initial pdr
+    #[inline]
+    #[must_use]
+    fn initial_etx(&self) -> f64 {
+        1. / self.pdr_estimator.initial_pdr()
+    }
+
     /// function used in testing to get the [NetworkGraph]
     #[inline]
     #[cfg(test)]
@@ -95,22 +181,87 @@ impl RoutingTable {
         &self.graph
     }
 
+    /// current topology of the network, as seen by `server_id`
+    pub(crate) fn snapshot(&self, server_id: NodeId) -> TopologySnapshot {
+        let mut nodes: Vec<NodeSnapshot> = self
+            .graph
+            .nodes()
+            .map(|id: NodeId| {
+                let pdr: Option<f64> = self.pdr_table.get(&id).map(|e: &PdrEntry| e.0);
+                NodeSnapshot {
+                    id,
+                    pdr,
+                    etx: pdr.map(Self::etx),
+                }
+            })
+            .collect();
+        nodes.sort_unstable_by_key(|n: &NodeSnapshot| n.id);
+        let mut edges: Vec<EdgeSnapshot> = self
+            .graph
+            .all_edges()
+            .map(|(from, to, w): (NodeId, NodeId, &f64)| EdgeSnapshot {
+                from,
+                to,
+                weight: *w,
+            })
+            .collect();
+        edges.sort_unstable_by_key(|e: &EdgeSnapshot| (e.from, e.to));
+        TopologySnapshot {
+            server_id,
+            nodes,
+            edges,
+        }
+    }
+
     /// checks if the graph contains and edge
     #[inline]
     fn contains_edge(&self, from: NodeId, to: NodeId) -> bool {
         self.graph.contains_edge(from, to)
     }
 
-    /// adds and edge to the graph and the nodes to the `pdr_table`
+    /// ETX of a drone with the given pdr
+    #[inline]
+    fn etx(pdr: f64) -> f64 {
+        if pdr < Self::EPSILON {
+            f64::INFINITY
+        } else {
+            1. / pdr
+        }
+    }
+
+    /// weight of the edges going into the node `id`
+    fn node_etx(&self, id: NodeId) -> f64 {
+        if self.endpoints.contains(&id) {
+            Self::ENDPOINT_ETX
+        } else {
+            self.pdr_table
+                .get(&id)
+                .map_or_else(|| self.initial_etx(), |e: &PdrEntry| Self::etx(e.0))
+        }
+    }
+
+    /// adds and edge to the graph and the drones to the `pdr_table`
     /// if they were not present already
     fn add_edge(&mut self, from: NodeId, to: NodeId) -> Option<f64> {
-        self.pdr_table
-            .entry(from)
-            .or_insert(PdrEntry(INITIAL_PDR, 0, 0));
-        self.pdr_table
-            .entry(to)
-            .or_insert(PdrEntry(INITIAL_PDR, 0, 0));
-        self.graph.add_edge(from, to, INITIAL_ETX)
+        let initial_pdr: f64 = self.pdr_estimator.initial_pdr();
+        for id in [from, to] {
+            if !self.endpoints.contains(&id) {
+                self.pdr_table
+                    .entry(id)
+                    .or_insert(PdrEntry(initial_pdr, 0, 0));
+            }
+        }
+        self.graph.add_edge(from, to, self.node_etx(to))
+    }
+
+    /// marks `id` as a client or a server: it is removed from the `pdr_table` and
+    /// the edges going into it get a fixed weight
+    pub(crate) fn add_endpoint(&mut self, id: NodeId) {
+        if self.endpoints.insert(id) {
+            self.pdr_table.remove(&id);
+            self.pdr_estimator.forget(id);
+            self.set_incoming_weights(id, Self::ENDPOINT_ETX);
+        }
     }
 
     /// adds and edge to the graph if it didn't exist already
@@ -120,8 +271,12 @@ impl RoutingTable {
             .is_some()
     }
 
-    /// updates the `pdr_table` after a measurement
+    /// updates the `pdr_table` after a measurement. The measurements are not counted
+    /// if the estimator disables the updates with a window of 0 samples
     pub(super) fn update_pdr(&mut self, id: NodeId, recv: bool) -> bool {
+        if self.pdr_estimator.window_sz() == 0 {
+            return self.pdr_table.contains_key(&id);
+        }
         if self.pdr_table.contains_key(&id) {
             let entry: &mut PdrEntry = self.pdr_table.get_mut(&id).unwrap();
             // update count
@@ -132,20 +287,12 @@ impl RoutingTable {
             }
 
             // update pdr and etx, if needed
-            if entry.1 + entry.2 == self.pdr_estimator.window_sz {
-                entry.0 = (self.pdr_estimator.estimator)(entry.0, entry.1, entry.2);
+            if entry.1 + entry.2 == self.pdr_estimator.window_sz() {
+                entry.0 = self.pdr_estimator.estimate(id, entry.0, entry.1, entry.2);
                 entry.1 = 0;
                 entry.2 = 0;
-                let etx: f64 = if entry.0 < Self::EPSILON {
-                    f64::INFINITY
-                } else {
-                    1. / entry.0
-                };
-                for (from, _, w) in self.graph.all_edges_mut() {
-                    if from == id {
-                        *w = etx;
-                    }
-                }
+                let pdr: f64 = entry.0;
+                self.update_etx(id, pdr);
             }
             true
         } else {
@@ -153,8 +300,122 @@ impl RoutingTable {
         }
     }
 
-    /// get best routing path from start to end
+    /// updates the weight of the edges going into the drone `id` after a change of its pdr
+    #[inline]
+    fn update_etx(&mut self, id: NodeId, pdr: f64) {
+        self.set_incoming_weights(id, Self::etx(pdr));
+    }
+
+    /// sets the weight of the edges going into `id`
+    fn set_incoming_weights(&mut self, id: NodeId, weight: f64) {
+        for (_, to, w) in self.graph.all_edges_mut() {
+            if to == id {
+                *w = weight;
+            }
+        }
+    }
+
+    /// moves the pdr estimations toward the initial pdr, halving their distance every
+    /// half-life. In this way the drones that are no longer used because of a bad
+    /// estimation are eventually tried again
+    pub(crate) fn decay(&mut self, now: Instant) {
+        let Some(half_life) = self.half_life else {
+            return;
+        };
+        let last: Instant = self.last_decay.replace(now).unwrap_or(now);
+        let elapsed: Duration = now.saturating_duration_since(last);
+        if elapsed.is_zero() {
+            return;
+        }
+        let factor: f64 = 0.5_f64.powf(elapsed.as_secs_f64() / half_life.as_secs_f64());
+        let prior: f64 = self.pdr_estimator.initial_pdr();
+        let decayed: Vec<(NodeId, f64)> = self
+            .pdr_table
+            .iter_mut()
+            .filter(|(_, e): &(&NodeId, &mut PdrEntry)| (e.0 - prior).abs() > f64::EPSILON)
+            .map(|(id, e): (&NodeId, &mut PdrEntry)| {
+                e.0 = prior + (e.0 - prior) * factor;
+                (*id, e.0)
+            })
+            .collect();
+        for (id, pdr) in decayed {
+            self.pdr_estimator.decay(id, factor);
+            self.update_etx(id, pdr);
+        }
+    }
+
+    /// get the best routing path from start to end
+    #[inline]
     pub(super) fn get_route(&self, start: NodeId, dest: NodeId) -> Option<Vec<NodeId>> {
+        self.get_best_route(start, dest)
+    }
+
+    /// get a routing path from start to end, the best one unless the table decides
+    /// to explore a different one. Used only for the fragments of new responses,
+    /// so that acks, nacks and retransmissions always take the best route
+    pub(super) fn get_explore_route(&mut self, start: NodeId, dest: NodeId) -> Option<Vec<NodeId>> {
+        if self.exploration > 0. && self.rng.gen_bool(self.exploration) {
+            let via: Vec<NodeId> = self
+                .graph
+                .nodes()
+                .filter(|n: &NodeId| self.pdr_table.contains_key(n))
+                .filter(|n: &NodeId| *n != start && *n != dest)
+                .collect();
+            if !via.is_empty() {
+                let via: NodeId = via[self.rng.gen_index(via.len())];
+                if let Some(path) = self.get_route_via(start, via, dest) {
+                    return Some(path);
+                }
+            }
+        }
+        self.get_best_route(start, dest)
+    }
+
+    /// get at most `k` routing paths from start to end that don't share any drone, in order
+    /// of cost, with their cost. A path through drones considered lost is returned only
+    /// if it is the first one
+    pub(super) fn get_disjoint_routes(
+        &self,
+        start: NodeId,
+        dest: NodeId,
+        k: usize,
+    ) -> Vec<(Vec<NodeId>, f64)> {
+        let mut graph: NetworkGraph = self.graph.clone();
+        let mut routes: Vec<(Vec<NodeId>, f64)> = Vec::new();
+        while routes.len() < k {
+            let Some((cost, path)) = astar(
+                &graph,
+                start,
+                |finish: u8| finish == dest,
+                |e: (u8, u8, &f64)| *e.weight(),
+                |_| 0.,
+            ) else {
+                break;
+            };
+            if path.len() < 2 || (cost.is_infinite() && !routes.is_empty()) {
+                break;
+            }
+            if path.len() == 2 {
+                graph.remove_edge(start, dest);
+            }
+            for &id in &path[1..path.len() - 1] {
+                graph.remove_node(id);
+            }
+            routes.push((path, cost));
+        }
+        routes
+    }
+
+    /// get the best routing path from start to end that goes through `via`,
+    /// [None] if it doesn't exist or if it contains a loop
+    fn get_route_via(&self, start: NodeId, via: NodeId, dest: NodeId) -> Option<Vec<NodeId>> {
+        let mut path: Vec<NodeId> = self.get_best_route(start, via)?;
+        path.extend(self.get_best_route(via, dest)?.into_iter().skip(1));
+        path.iter().all_unique().then_some(path)
+    }
+
+    /// get best routing path from start to end
+    fn get_best_route(&self, start: NodeId, dest: NodeId) -> Option<Vec<NodeId>> {
         astar(
             &self.graph,
             start,
@@ -165,14 +426,42 @@ impl RoutingTable {
         .map(|(_, path)| path)
     }
 
+    /// checks if there is a route from `start` to every known client and server,
+    /// false if none of them is known
+    pub(crate) fn reaches_endpoints(&self, start: NodeId) -> bool {
+        let mut endpoints = self.endpoints.iter().filter(|&&id| id != start).peekable();
+        endpoints.peek().is_some()
+            && endpoints.all(|&id: &NodeId| self.get_best_route(start, id).is_some())
+    }
+
     /// remove a node from the [`RoutingTable`]
     #[inline]
     pub(crate) fn remove_node(&mut self, id: NodeId) -> bool {
         self.pdr_table.remove(&id);
+        self.endpoints.remove(&id);
+        self.pdr_estimator.forget(id);
         self.graph.remove_node(id)
     }
 }
 
+/// assigns each of the `n_frags` fragments of a response to one of the routes with the
+/// given costs, so that every route gets a number of fragments inversely proportional
+/// to its cost. Ties are assigned to the first route
+#[allow(clippy::cast_precision_loss)]
+fn stripe(costs: &[f64], n_frags: usize) -> Vec<usize> {
+    let mut assigned: Vec<usize> = vec![0; costs.len()];
+    (0..n_frags)
+        .map(|_| {
+            let load = |r: usize| (assigned[r] + 1) as f64 * costs[r];
+            let route: usize = (0..costs.len())
+                .min_by(|a: &usize, b: &usize| load(*a).total_cmp(&load(*b)))
+                .unwrap_or(0);
+            assigned[route] += 1;
+            route
+        })
+        .collect()
+}
+
 impl<T: ServerType> GenericServer<T> {
     /// wrapper of [`RoutingTable::check_and_add_edge`]
     #[inline]
@@ -180,7 +469,8 @@ impl<T: ServerType> GenericServer<T> {
         self.network_graph.check_and_add_edge(from, to)
     }
 
-    /// updates pdrs after a successful send
+    /// updates pdrs after a successful send: every drone of the route forwarded the fragment,
+    /// `hops` is the route of the acknowledged fragment
     pub(crate) fn update_pdr_from_ack(&mut self, hops: &[u8]) {
         if hops.len() < 3 {
             warn!(target: &self.target_topic, "warning, received valid ack with invalid routing header, skipping pdr update...");
@@ -192,7 +482,8 @@ impl<T: ServerType> GenericServer<T> {
         }
     }
 
-    /// updates pdrs after a unsuccessful send
+    /// updates pdrs after a unsuccessful send: `hops` is the route of the nack, the first drone
+    /// dropped the fragment while the drones between it and the server forwarded it
     pub(crate) fn update_pdr_from_nack(&mut self, hops: &[u8]) {
         if hops.len() < 2 {
             warn!(target: &self.target_topic, "warning, received valid nack with invalid routing header, skipping pdr update...");
@@ -207,6 +498,11 @@ impl<T: ServerType> GenericServer<T> {
 
     /// updates the graph from the info received from a [`FloodResponse`]
     pub(crate) fn update_network_from_flood(&mut self, fr: &FloodResponse) {
+        for (id, node_type) in &fr.path_trace {
+            if !matches!(node_type, NodeType::Drone) {
+                self.network_graph.add_endpoint(*id);
+            }
+        }
         for ((prev_id, prev_type), (next_id, next_type)) in fr.path_trace.iter().tuple_windows() {
             match (prev_type, next_type) {
                 (NodeType::Drone, NodeType::Drone) => {
@@ -242,6 +538,8 @@ impl<T: ServerType> GenericServer<T> {
             error!(target: &self.target_topic, "Found wrong src header o client/server directly connected: {srch}");
             return;
         }
+        self.network_graph.add_endpoint(srch.hops[0]);
+        self.network_graph.add_endpoint(srch.hops[sz - 1]);
         for (prev_id, next_id) in srch.hops[1..srch.hops.len() - 1].iter().tuple_windows() {
             self.check_and_add_edge(*prev_id, *next_id);
             self.check_and_add_edge(*next_id, *prev_id);
@@ -250,11 +548,52 @@ impl<T: ServerType> GenericServer<T> {
         self.check_and_add_edge(srch.hops[sz - 1], srch.hops[sz - 2]);
     }
 
+    /// current topology of the network as seen by the [`GenericServer`], with
+    /// the estimations of the drones
+    #[must_use]
+    pub fn topology_snapshot(&self) -> TopologySnapshot {
+        self.network_graph.snapshot(self.id)
+    }
+
     /// wrapper of [`RoutingTable::get_route`]
     pub(crate) fn get_route(&self, dest: NodeId) -> Option<Vec<NodeId>> {
         self.network_graph.get_route(self.id, dest)
     }
 
+    /// wrapper of [`RoutingTable::get_explore_route`]
+    pub(crate) fn get_explore_route(&mut self, dest: NodeId) -> Option<Vec<NodeId>> {
+        self.network_graph.get_explore_route(self.id, dest)
+    }
+
+    /// headers used to send each of the `n_frags` fragments of a response to `dest`.
+    /// The fragments are spread over at most `max_paths` routes that don't share any drone,
+    /// weighted by their ETX, `best` is used if no other route is available
+    pub(crate) fn get_striped_hdrs(
+        &self,
+        best: SourceRoutingHeader,
+        dest: NodeId,
+        n_frags: usize,
+    ) -> Vec<SourceRoutingHeader> {
+        if self.max_paths < 2 || n_frags < 2 {
+            return vec![best; n_frags];
+        }
+        let (hdrs, costs): (Vec<SourceRoutingHeader>, Vec<f64>) = self
+            .network_graph
+            .get_disjoint_routes(self.id, dest, self.max_paths)
+            .into_iter()
+            .filter(|(path, _): &(Vec<NodeId>, f64)| self.packet_send.contains_key(&path[1]))
+            .map(|(path, cost): (Vec<NodeId>, f64)| (SourceRoutingHeader::new(path, 1), cost))
+            .unzip();
+        if hdrs.len() < 2 {
+            return vec![best; n_frags];
+        }
+        info!(target: &self.target_topic, "Striping {n_frags} fragments over {} routes", hdrs.len());
+        stripe(&costs, n_frags)
+            .into_iter()
+            .map(|r: usize| hdrs[r].clone())
+            .collect()
+    }
+
     /// tries to get a path for the response, if it fails it inverts the [`SourceRoutingHeader`]
     /// of the received message
     pub(crate) fn get_routing_hdr_with_hint(
@@ -262,7 +601,29 @@ impl<T: ServerType> GenericServer<T> {
         srch: &SourceRoutingHeader,
         src_id: NodeId,
     ) -> SourceRoutingHeader {
-        if let Some(p) = self.get_route(src_id) {
+        let route: Option<Vec<NodeId>> = self.get_route(src_id);
+        self.routing_hdr_or_hint(route, srch)
+    }
+
+    /// like [`GenericServer::get_routing_hdr_with_hint`], but the path can be one explored
+    /// by the [`RoutingTable`]. Used for the fragments of new responses
+    pub(crate) fn get_explore_hdr_with_hint(
+        &mut self,
+        srch: &SourceRoutingHeader,
+        src_id: NodeId,
+    ) -> SourceRoutingHeader {
+        let route: Option<Vec<NodeId>> = self.get_explore_route(src_id);
+        self.routing_hdr_or_hint(route, srch)
+    }
+
+    /// header following the given path or, if it is [None], the inverted
+    /// [`SourceRoutingHeader`] of the received message
+    fn routing_hdr_or_hint(
+        &mut self,
+        route: Option<Vec<NodeId>>,
+        srch: &SourceRoutingHeader,
+    ) -> SourceRoutingHeader {
+        if let Some(p) = route {
             SourceRoutingHeader::initialize(p)
         } else {
             self.update_network_from_header(srch);
@@ -0,0 +1,256 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use wg_2024::network::NodeId;
+
+use super::{EstimatorState, PdrEntry, RoutingTable};
+use crate::servers::{GenericServer, ServerType};
+
+/// testing module
+#[cfg(test)]
+mod test;
+
+/// version of the format of the saved [`RoutingState`]
+const STATE_VERSION: u32 = 2;
+/// period of the automatic saves of the [`RoutingState`]
+const SAVE_PERIOD: Duration = Duration::from_secs(5);
+
+/// reasons why the routing state of a [`GenericServer`] cannot be saved or loaded
+#[derive(Debug)]
+pub enum RoutingStateError {
+    /// the file cannot be read or written
+    Io(io::Error),
+    /// the file doesn't contain a valid routing state, or it was saved
+    /// with an incompatible version
+    Format,
+    /// the routing state was saved by the server with the given id
+    WrongServer(NodeId),
+}
+
+impl From<io::Error> for RoutingStateError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// State learned by a [`RoutingTable`]: the known topology, the pdr estimations of
+/// the drones and the estimator that produced them, with its parameters and state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct RoutingState {
+    /// version of the format
+    version: u32,
+    /// id of the server that saved the state
+    server_id: NodeId,
+    /// next session id of the server, so that the floods started after a
+    /// restart are not mistaken for the ones already seen by the drones
+    session_id: u64,
+    /// edges of the graph, their weight depends on the pdr estimations
+    edges: Vec<(NodeId, NodeId)>,
+    /// clients and servers
+    endpoints: Vec<NodeId>,
+    /// entries of the `pdr_table`: (id, pdr, #acks, #nacks)
+    pdrs: Vec<(NodeId, f64, u32, u32)>,
+    /// window size of the estimator
+    window_sz: u32,
+    /// pdr assigned by the estimator to newly discovered drones
+    initial_pdr: f64,
+    /// kind, parameters and state of the estimator
+    estimator: EstimatorState,
+}
+
+impl RoutingState {
+    /// reads a [`RoutingState`] from a file
+    ///
+    /// # Errors
+    /// Returns [`RoutingStateError::Io`] if the file cannot be read and
+    /// [`RoutingStateError::Format`] if it doesn't contain a valid state
+    pub(crate) fn load(path: &Path) -> Result<Self, RoutingStateError> {
+        let mut reader: BufReader<File> = BufReader::new(File::open(path)?);
+        let state: Self =
+            bincode::serde::decode_from_std_read(&mut reader, bincode::config::standard())
+                .map_err(|_| RoutingStateError::Format)?;
+        if state.version == STATE_VERSION {
+            Ok(state)
+        } else {
+            Err(RoutingStateError::Format)
+        }
+    }
+
+    /// writes the [`RoutingState`] to a file, the file is replaced only once
+    /// the new state is completely written
+    ///
+    /// # Errors
+    /// Returns [`RoutingStateError::Io`] if the file cannot be written and
+    /// [`RoutingStateError::Format`] if the state cannot be encoded
+    pub(crate) fn save(&self, path: &Path) -> Result<(), RoutingStateError> {
+        let mut tmp: PathBuf = path.to_path_buf();
+        tmp.as_mut_os_string().push(".tmp");
+        let mut writer: BufWriter<File> = BufWriter::new(File::create(&tmp)?);
+        bincode::serde::encode_into_std_write(self, &mut writer, bincode::config::standard())
+            .map_err(|_| RoutingStateError::Format)?;
+        writer
+            .into_inner()
+            .map_err(io::IntoInnerError::into_error)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+impl RoutingTable {
+    /// state learned by the [`RoutingTable`], sorted so that equal tables have equal states
+    pub(crate) fn state(&self, server_id: NodeId, session_id: u64) -> RoutingState {
+        let mut edges: Vec<(NodeId, NodeId)> =
+            self.graph.all_edges().map(|(a, b, _)| (a, b)).collect();
+        edges.sort_unstable();
+        let mut endpoints: Vec<NodeId> = self.endpoints.iter().copied().collect();
+        endpoints.sort_unstable();
+        let mut pdrs: Vec<(NodeId, f64, u32, u32)> = self
+            .pdr_table
+            .iter()
+            .map(|(&id, e): (&NodeId, &PdrEntry)| (id, e.0, e.1, e.2))
+            .collect();
+        pdrs.sort_unstable_by_key(|e: &(NodeId, f64, u32, u32)| e.0);
+        RoutingState {
+            version: STATE_VERSION,
+            server_id,
+            session_id,
+            edges,
+            endpoints,
+            pdrs,
+            window_sz: self.pdr_estimator.window_sz(),
+            initial_pdr: self.pdr_estimator.initial_pdr(),
+            estimator: self.pdr_estimator.save_state(),
+        }
+    }
+
+    /// warm-starts the [`RoutingTable`] from a saved state. The samples of the current
+    /// window are discarded if the estimator uses a different window size, the state of
+    /// the estimator if it is of another kind or has other parameters. The weights of the
+    /// edges are recomputed from the restored estimations.
+    /// Returns false if the state of the estimator was not restored
+    pub(crate) fn restore(&mut self, state: &RoutingState) -> bool {
+        let same_window: bool = state.window_sz == self.pdr_estimator.window_sz();
+        self.graph.clear();
+        self.endpoints = state.endpoints.iter().copied().collect();
+        self.pdr_table = state
+            .pdrs
+            .iter()
+            .filter(|e: &&(NodeId, f64, u32, u32)| !self.endpoints.contains(&e.0))
+            .map(|&(id, pdr, acks, nacks)| {
+                let entry: PdrEntry = if same_window {
+                    PdrEntry(pdr, acks, nacks)
+                } else {
+                    PdrEntry(pdr, 0, 0)
+                };
+                (id, entry)
+            })
+            .collect();
+        for &id in &state.endpoints {
+            self.graph.add_node(id);
+        }
+        for &(from, to) in &state.edges {
+            self.add_edge(from, to);
+        }
+        self.pdr_estimator.restore_state(&state.estimator)
+    }
+
+    /// makes the edges leaving `server_id` match its current neighbors, returns
+    /// false if the restored topology was not consistent with them
+    pub(crate) fn check_neighbors(&mut self, server_id: NodeId, neighbors: &[NodeId]) -> bool {
+        let stale: Vec<NodeId> = self
+            .graph
+            .neighbors(server_id)
+            .filter(|id: &NodeId| !neighbors.contains(id))
+            .collect();
+        for &id in &stale {
+            self.graph.remove_edge(server_id, id);
+        }
+        let mut consistent: bool = stale.is_empty();
+        for &id in neighbors {
+            consistent &= !self.check_and_add_edge(server_id, id);
+        }
+        consistent
+    }
+}
+
+/// File where the [`GenericServer`] periodically saves its [`RoutingState`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct StateFile {
+    /// path of the file
+    pub(crate) path: PathBuf,
+    /// instant of the last save, if any
+    last_save: Option<Instant>,
+}
+
+impl StateFile {
+    /// creates a new [`StateFile`] at `path`
+    pub(crate) fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            last_save: None,
+        }
+    }
+}
+
+impl<T: ServerType> GenericServer<T> {
+    /// saves the topology and the pdr estimations learned by the [`GenericServer`] to
+    /// a file, which can be given to [`crate::GenericServerBuilder::routing_state_file`]
+    /// to warm-start a server with the same id
+    ///
+    /// # Errors
+    /// See [`RoutingStateError`]
+    pub fn save_routing_state(&self, path: impl AsRef<Path>) -> Result<(), RoutingStateError> {
+        self.network_graph
+            .state(self.id, self.session_id)
+            .save(path.as_ref())
+    }
+
+    /// warm-starts the [`GenericServer`] from the [`RoutingState`] saved in a file,
+    /// the restored topology is then made consistent with the current neighbors
+    ///
+    /// # Errors
+    /// Returns [`RoutingStateError::WrongServer`] if the state was saved by another server,
+    /// in addition to the errors of [`RoutingState::load`]
+    pub(crate) fn load_routing_state(&mut self, path: &Path) -> Result<(), RoutingStateError> {
+        let state: RoutingState = RoutingState::load(path)?;
+        if state.server_id != self.id {
+            return Err(RoutingStateError::WrongServer(state.server_id));
+        }
+        if !self.network_graph.restore(&state) {
+            warn!(target: &self.target_topic, "Saved estimator differs from the current one, its state is discarded");
+        }
+        self.network_graph.add_endpoint(self.id);
+        self.session_id = self.session_id.max(state.session_id);
+        let neighbors: Vec<NodeId> = self.packet_send.keys().copied().collect();
+        if !self.network_graph.check_neighbors(self.id, &neighbors) {
+            warn!(target: &self.target_topic, "Restored topology doesn't match the current neighbors, fixed");
+        }
+        info!(target: &self.target_topic, "Routing state restored from {}", path.display());
+        Ok(())
+    }
+
+    /// saves the [`RoutingState`] to the [`StateFile`], if any, every [`SAVE_PERIOD`]
+    pub(crate) fn autosave_routing_state(&mut self, now: Instant) {
+        let Some(file) = &self.state_file else {
+            return;
+        };
+        if file
+            .last_save
+            .is_some_and(|t: Instant| now < t + SAVE_PERIOD)
+        {
+            return;
+        }
+        if let Err(e) = self.save_routing_state(&file.path) {
+            warn!(target: &self.target_topic, "Unable to save the routing state: {e:?}");
+        }
+        if let Some(file) = &mut self.state_file {
+            file.last_save = Some(now);
+        }
+    }
+}
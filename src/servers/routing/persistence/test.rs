@@ -0,0 +1,175 @@
+#[cfg(test)]
+mod persistence_tests {
+    use std::{env, fs, path::PathBuf};
+
+    use wg_2024::network::{NodeId, SourceRoutingHeader};
+
+    use crate::servers::{
+        self,
+        routing::{
+            persistence::{RoutingState, RoutingStateError},
+            PdrEstimator, RoutingTable,
+        },
+        test_utils::{get_neighbors_builder, graphmap_eq},
+        BetaEstimator, EstimatorState, EwmaEstimator, GenericServer, GenericServerBuilder,
+        NetworkGraph, Text, WindowEstimator, INITIAL_ETX,
+    };
+
+    /// path of a temporary file used by a test
+    fn temp_file(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("routing_state_{}_{name}", std::process::id()))
+    }
+
+    /// get a [`RoutingTable`] for the network 0 -> 1 -> 2, where 0 is a server and
+    /// 2 a client, with some measurements of the drone 1
+    fn get_table() -> RoutingTable {
+        let mut table: RoutingTable = RoutingTable::new_with_graph(
+            NetworkGraph::from_edges([(0, 1, INITIAL_ETX), (1, 2, INITIAL_ETX)]),
+            EwmaEstimator::default().with_window_sz(4),
+        );
+        table.add_endpoint(0);
+        table.add_endpoint(2);
+        for recv in [true, true, true, true, false] {
+            table.update_pdr(1, recv);
+        }
+        table
+    }
+
+    /// get a [`GenericServerBuilder<Text>`] with the drone 1 as neighbor
+    fn get_builder(id: NodeId) -> GenericServerBuilder<Text> {
+        get_neighbors_builder(id, &[1]).0
+    }
+
+    /// tests that a saved state restores the same [`RoutingTable`]
+    #[test]
+    fn test_save_and_load() {
+        let path: PathBuf = temp_file("save_and_load");
+        let table: RoutingTable = get_table();
+        let state: RoutingState = table.state(0, 42);
+        state.save(&path).unwrap();
+        let loaded: RoutingState = RoutingState::load(&path).unwrap();
+        assert_eq!(loaded, state);
+
+        let mut restored: RoutingTable =
+            RoutingTable::new(Box::new(EwmaEstimator::default().with_window_sz(4)));
+        assert!(restored.restore(&loaded));
+        assert!(graphmap_eq(restored.get_graph(), table.get_graph()));
+        assert_eq!(restored.state(0, 42), state);
+        fs::remove_file(&path).unwrap();
+    }
+
+    /// tests that the samples of the current window are discarded if the
+    /// window size changed
+    #[test]
+    fn test_window_mismatch() {
+        let state: RoutingState = get_table().state(0, 0);
+        let mut restored: RoutingTable = RoutingTable::new(Box::new(servers::default_estimator()));
+        restored.restore(&state);
+        let pdr: f64 = state.pdrs.iter().find(|e| e.0 == 1).unwrap().1;
+        assert_eq!(restored.state(0, 0).pdrs, vec![(1, pdr, 0, 0)]);
+    }
+
+    /// tests that the state of the estimator is restored only by an estimator of the
+    /// same kind with the same parameters
+    #[test]
+    fn test_estimator_state() {
+        let mut beta: BetaEstimator = BetaEstimator::new(2., 1.);
+        beta.estimate(1, 0., 3, 1);
+        let mut table: RoutingTable = RoutingTable::new_with_graph(
+            NetworkGraph::from_edges([(0, 1, INITIAL_ETX), (1, 2, INITIAL_ETX)]),
+            beta,
+        );
+        let state: RoutingState = table.state(0, 0);
+        assert_eq!(
+            state.estimator,
+            EstimatorState::Beta {
+                prior: (2., 1.),
+                posteriors: vec![(1, (5., 2.))]
+            }
+        );
+        let mut restored: RoutingTable = RoutingTable::new(Box::new(BetaEstimator::new(2., 1.)));
+        assert!(restored.restore(&state));
+        assert!(
+            (restored.pdr_estimator.estimate(1, 0., 1, 0)
+                - table.pdr_estimator.estimate(1, 0., 1, 0))
+            .abs()
+                < 1e-9
+        );
+
+        let mut other: RoutingTable = RoutingTable::new(Box::new(BetaEstimator::default()));
+        assert!(!other.restore(&state));
+        assert!(
+            other.state(0, 0).estimator
+                == EstimatorState::Beta {
+                    prior: (1., 1.),
+                    posteriors: Vec::new()
+                }
+        );
+        let mut other: RoutingTable =
+            RoutingTable::new(Box::new(EwmaEstimator::default().with_alpha(0.5)));
+        assert!(!other.restore(&get_table().state(0, 0)));
+        assert!(other.state(0, 0).estimator == EstimatorState::Ewma { alpha: 0.5 });
+
+        let mut window: WindowEstimator = WindowEstimator::new(4);
+        window.estimate(1, 0., 3, 1);
+        window.decay(1, 0.5);
+        let state: EstimatorState = window.save_state();
+        let mut restored: WindowEstimator = WindowEstimator::new(4);
+        assert!(restored.restore_state(&state));
+        assert_eq!(restored.save_state(), state);
+        assert!((restored.estimate(1, 0., 1, 0) - window.estimate(1, 0., 1, 0)).abs() < 1e-9);
+        assert!(!WindowEstimator::new(5).restore_state(&state));
+    }
+
+    /// tests the consistency check against the current neighbors
+    #[test]
+    fn test_check_neighbors() {
+        let mut table: RoutingTable = get_table();
+        assert!(table.check_neighbors(0, &[1]));
+        assert!(!table.check_neighbors(0, &[3]));
+        assert!(!table.get_graph().contains_edge(0, 1));
+        assert!(table.get_graph().contains_edge(0, 3));
+        assert!(table.get_graph().contains_edge(1, 2));
+    }
+
+    /// tests the warm-start of a [`GenericServer`]
+    #[test]
+    fn test_warm_start() {
+        let path: PathBuf = temp_file("warm_start");
+        let mut server: GenericServer<Text> = get_builder(0).build();
+        server.update_network_from_header(&SourceRoutingHeader::initialize(vec![2, 1, 0]));
+        server.session_id = 7;
+        server.save_routing_state(&path).unwrap();
+
+        let restarted: GenericServer<Text> = get_builder(0).routing_state_file(&path).build();
+        assert_eq!(restarted.get_route(2), Some(vec![0, 1, 2]));
+        assert_eq!(restarted.session_id, 7);
+        assert!(restarted.need_flood);
+
+        let other: GenericServer<Text> = get_builder(5).routing_state_file(&path).build();
+        assert_eq!(other.get_route(2), None);
+        assert!(matches!(
+            get_builder(5).build().load_routing_state(&path),
+            Err(RoutingStateError::WrongServer(0))
+        ));
+        fs::remove_file(&path).unwrap();
+    }
+
+    /// tests that invalid files are rejected
+    #[test]
+    fn test_invalid_file() {
+        let path: PathBuf = temp_file("invalid");
+        assert!(matches!(
+            RoutingState::load(&path),
+            Err(RoutingStateError::Io(_))
+        ));
+        fs::write(&path, b"not a routing state").unwrap();
+        assert!(matches!(
+            RoutingState::load(&path),
+            Err(RoutingStateError::Format)
+        ));
+        let server: GenericServer<Text> = get_builder(0).routing_state_file(&path).build();
+        assert!(server.network_graph.get_graph().contains_edge(0, 1));
+        fs::remove_file(&path).unwrap();
+    }
+}
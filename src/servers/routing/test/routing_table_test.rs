@@ -1,7 +1,10 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
 
 use crate::servers::{
-    routing::{PdrEntry, PdrEstimator, RoutingTable},
+    routing::{EwmaEstimator, FnEstimator, PdrEntry, RoutingTable},
     NetworkGraph, INITIAL_ETX, INITIAL_PDR,
 };
 
@@ -14,7 +17,7 @@ fn get_dummy_graph() -> NetworkGraph {
 #[test]
 fn test_new_with_graph() {
     let graph: NetworkGraph = get_dummy_graph();
-    let estimator: PdrEstimator = PdrEstimator::new(2, |_, _, _| 0.);
+    let estimator: FnEstimator = FnEstimator::new(2, |_, _, _, _| 0.);
     let table: RoutingTable = RoutingTable::new_with_graph(graph, estimator);
     assert_eq!(
         table.pdr_table,
@@ -35,7 +38,7 @@ fn test_new_with_graph() {
 /// tests the correct behaviour and consistency of new edges with the pdr_table
 #[test]
 fn test_add_edge() {
-    let estimator: PdrEstimator = PdrEstimator::new(2, |_, _, _| 0.);
+    let estimator: FnEstimator = FnEstimator::new(2, |_, _, _, _| 0.);
     let mut table: RoutingTable = RoutingTable::new_with_graph(NetworkGraph::new(), estimator);
     table.add_edge(1, 2);
     table.add_edge(2, 1);
@@ -50,7 +53,7 @@ fn test_add_edge() {
 /// tests correct node removal
 #[test]
 fn test_remove_node() {
-    let estimator: PdrEstimator = PdrEstimator::new(2, |_, _, _| 0.);
+    let estimator: FnEstimator = FnEstimator::new(2, |_, _, _, _| 0.);
     let mut table: RoutingTable = RoutingTable::new_with_graph(NetworkGraph::new(), estimator);
     table.add_edge(1, 2);
     table.remove_node(1);
@@ -63,7 +66,7 @@ fn test_remove_node() {
 #[test]
 fn test_contains_edge() {
     let graph: NetworkGraph = get_dummy_graph();
-    let estimator: PdrEstimator = PdrEstimator::new(2, |_, _, _| 0.);
+    let estimator: FnEstimator = FnEstimator::new(2, |_, _, _, _| 0.);
     let table: RoutingTable = RoutingTable::new_with_graph(graph, estimator);
     assert!(table.graph.contains_edge(1, 2) == table.contains_edge(1, 2));
     assert!(table.graph.contains_edge(1, 3) == table.contains_edge(1, 3));
@@ -75,8 +78,8 @@ fn test_contains_edge() {
 /// tests correct pdr_table update after k samples
 #[test]
 fn test_update_pdr_table() {
-    let estimator: PdrEstimator =
-        PdrEstimator::new(10, |_, acks, nacks| acks as f64 / (acks + nacks) as f64);
+    let estimator: FnEstimator =
+        FnEstimator::new(10, |_, _, acks, nacks| acks as f64 / (acks + nacks) as f64);
     let mut table: RoutingTable = RoutingTable::new_with_graph(NetworkGraph::new(), estimator);
     table.add_edge(1, 2);
     assert!(table.pdr_table.get(&1).unwrap().0 == INITIAL_PDR);
@@ -94,36 +97,51 @@ fn test_update_pdr_table() {
     assert!((table.pdr_table.get(&1).unwrap().0 - 0.8).abs() < 1e-6);
 }
 
+/// tests that a window of 0 samples disables the updates without counting the samples
+#[test]
+fn test_update_pdr_disabled() {
+    let mut table: RoutingTable = RoutingTable::new_with_graph(
+        NetworkGraph::new(),
+        EwmaEstimator::default().with_window_sz(0),
+    );
+    table.add_edge(1, 2);
+    for i in 0..100 {
+        assert!(table.update_pdr(1, i % 2 == 0));
+    }
+    assert_eq!(table.pdr_table[&1], PdrEntry(INITIAL_PDR, 0, 0));
+    assert!(!table.update_pdr(5, true));
+}
+
 /// tests correct graph weights update afteer a pdr_table update
 #[test]
 fn test_update_pdr_graph() {
-    let estimator: PdrEstimator =
-        PdrEstimator::new(10, |_, acks, nacks| acks as f64 / (acks + nacks) as f64);
+    let estimator: FnEstimator =
+        FnEstimator::new(10, |_, _, acks, nacks| acks as f64 / (acks + nacks) as f64);
     let mut table: RoutingTable = RoutingTable::new_with_graph(NetworkGraph::new(), estimator);
-    table.add_edge(1, 2);
     table.add_edge(2, 1);
-    table.add_edge(1, 3);
-    assert!(*table.graph.edge_weight(1, 2).unwrap() == INITIAL_ETX);
-    assert!(*table.graph.edge_weight(1, 3).unwrap() == INITIAL_ETX);
+    table.add_edge(1, 2);
+    table.add_edge(3, 1);
     assert!(*table.graph.edge_weight(2, 1).unwrap() == INITIAL_ETX);
+    assert!(*table.graph.edge_weight(3, 1).unwrap() == INITIAL_ETX);
+    assert!(*table.graph.edge_weight(1, 2).unwrap() == INITIAL_ETX);
     for i in 0..10 {
         table.update_pdr(1, i < 2);
     }
-    assert!((*table.graph.edge_weight(1, 2).unwrap() - 1. / 0.2).abs() < RoutingTable::EPSILON);
-    assert!((*table.graph.edge_weight(1, 3).unwrap() - 1. / 0.2).abs() < RoutingTable::EPSILON);
-    assert!(*table.graph.edge_weight(2, 1).unwrap() == INITIAL_ETX);
+    assert!((*table.graph.edge_weight(2, 1).unwrap() - 1. / 0.2).abs() < RoutingTable::EPSILON);
+    assert!((*table.graph.edge_weight(3, 1).unwrap() - 1. / 0.2).abs() < RoutingTable::EPSILON);
+    assert!(*table.graph.edge_weight(1, 2).unwrap() == INITIAL_ETX);
     for i in 0..10 {
         table.update_pdr(1, i < 8);
     }
-    assert!((*table.graph.edge_weight(1, 2).unwrap() - 1. / 0.8).abs() < RoutingTable::EPSILON);
-    assert!((*table.graph.edge_weight(1, 3).unwrap() - 1. / 0.8).abs() < RoutingTable::EPSILON);
-    assert!(*table.graph.edge_weight(2, 1).unwrap() == INITIAL_ETX);
+    assert!((*table.graph.edge_weight(2, 1).unwrap() - 1. / 0.8).abs() < RoutingTable::EPSILON);
+    assert!((*table.graph.edge_weight(3, 1).unwrap() - 1. / 0.8).abs() < RoutingTable::EPSILON);
+    assert!(*table.graph.edge_weight(1, 2).unwrap() == INITIAL_ETX);
 }
 
 /// tests consistency in weights after possible adge insertion
 #[test]
 fn test_check_and_add_edge() {
-    let estimator: PdrEstimator = PdrEstimator::new(2, |_, _, _| 0.);
+    let estimator: FnEstimator = FnEstimator::new(2, |_, _, _, _| 0.);
     let mut table: RoutingTable = RoutingTable::new_with_graph(NetworkGraph::new(), estimator);
     table.check_and_add_edge(1, 2);
     assert!(table.graph.contains_edge(1, 2));
@@ -136,18 +154,18 @@ fn test_check_and_add_edge() {
 /// tests infinity convergence of ETX
 #[test]
 fn test_infinite_etx() {
-    let estimator: PdrEstimator =
-        PdrEstimator::new(10, |_, acks, nacks| acks as f64 / (acks + nacks) as f64);
+    let estimator: FnEstimator =
+        FnEstimator::new(10, |_, _, acks, nacks| acks as f64 / (acks + nacks) as f64);
     let mut table: RoutingTable = RoutingTable::new_with_graph(NetworkGraph::new(), estimator);
-    table.check_and_add_edge(1, 2);
+    table.check_and_add_edge(2, 1);
     for _ in 0..10 {
         table.update_pdr(1, false);
     }
-    assert!(*table.graph.edge_weight(1, 2).unwrap() == f64::INFINITY);
+    assert!(*table.graph.edge_weight(2, 1).unwrap() == f64::INFINITY);
     for i in 0..10 {
         table.update_pdr(1, i < 5);
     }
-    assert!(*table.graph.edge_weight(1, 2).unwrap() == 2.);
+    assert!(*table.graph.edge_weight(2, 1).unwrap() == 2.);
 }
 
 /// tests routing path generation
@@ -170,8 +188,8 @@ fn test_get_route() {
         (7, 9, 5.),
         (8, 9, 3.),
     ]);
-    let estimator: PdrEstimator = PdrEstimator::new(2, |_, _, _| 0.);
-    let mut table: RoutingTable = RoutingTable::new(estimator);
+    let estimator: FnEstimator = FnEstimator::new(2, |_, _, _, _| 0.);
+    let mut table: RoutingTable = RoutingTable::new(Box::new(estimator));
     table.graph = graph;
     assert_eq!(table.get_route(0, 9).unwrap(), vec![0, 2, 3, 5, 6, 8, 9]);
     assert!(table.get_route(0, 43).is_none());
@@ -197,8 +215,8 @@ fn test_get_route_infinite_etx() {
         (7, 9, 5.),
         (8, 9, 3.),
     ]);
-    let estimator: PdrEstimator = PdrEstimator::new(2, |_, _, _| 0.);
-    let mut table: RoutingTable = RoutingTable::new(estimator);
+    let estimator: FnEstimator = FnEstimator::new(2, |_, _, _, _| 0.);
+    let mut table: RoutingTable = RoutingTable::new(Box::new(estimator));
     table.graph = graph;
     assert_eq!(table.get_route(0, 9).unwrap(), vec![0, 2, 4, 6, 8, 9]);
     assert!(table.get_route(0, 43).is_none());
@@ -208,8 +226,134 @@ fn test_get_route_infinite_etx() {
 #[test]
 fn test_get_route_infinite_cost() {
     let graph: NetworkGraph = NetworkGraph::from_edges([(0, 1, 4.), (1, 2, f64::INFINITY)]);
-    let estimator: PdrEstimator = PdrEstimator::new(2, |_, _, _| 0.);
-    let mut table: RoutingTable = RoutingTable::new(estimator);
+    let estimator: FnEstimator = FnEstimator::new(2, |_, _, _, _| 0.);
+    let mut table: RoutingTable = RoutingTable::new(Box::new(estimator));
     table.graph = graph;
     assert_eq!(table.get_route(0, 2).unwrap(), vec![0, 1, 2]);
 }
+
+/// tests that the pdr of an idle drone decays toward the initial pdr
+#[test]
+fn test_decay() {
+    let estimator: FnEstimator =
+        FnEstimator::new(10, |_, _, acks, nacks| acks as f64 / (acks + nacks) as f64);
+    let mut table: RoutingTable = RoutingTable::new_with_graph(NetworkGraph::new(), estimator)
+        .with_half_life(Some(Duration::from_secs(10)));
+    table.check_and_add_edge(2, 1);
+    table.check_and_add_edge(1, 2);
+    for _ in 0..10 {
+        table.update_pdr(1, false);
+    }
+    assert!(*table.graph.edge_weight(2, 1).unwrap() == f64::INFINITY);
+
+    let now: Instant = Instant::now();
+    table.decay(now);
+    assert!(*table.graph.edge_weight(2, 1).unwrap() == f64::INFINITY);
+    table.decay(now + Duration::from_secs(10));
+    assert!((table.pdr_table.get(&1).unwrap().0 - INITIAL_PDR / 2.).abs() < 1e-9);
+    assert!((*table.graph.edge_weight(2, 1).unwrap() - 2. / INITIAL_PDR).abs() < 1e-9);
+    table.decay(now + Duration::from_secs(20));
+    assert!((table.pdr_table.get(&1).unwrap().0 - INITIAL_PDR * 3. / 4.).abs() < 1e-9);
+    // drones at the initial pdr are not affected
+    assert!(table.pdr_table.get(&2).unwrap().0 == INITIAL_PDR);
+    assert!(*table.graph.edge_weight(1, 2).unwrap() == INITIAL_ETX);
+}
+
+/// tests that the estimations don't decay if the decay is disabled
+#[test]
+fn test_no_decay() {
+    let estimator: FnEstimator = FnEstimator::new(1, |_, _, _, _| 0.);
+    let mut table: RoutingTable =
+        RoutingTable::new_with_graph(NetworkGraph::new(), estimator).with_half_life(None);
+    table.check_and_add_edge(2, 1);
+    table.update_pdr(1, false);
+    let now: Instant = Instant::now();
+    table.decay(now);
+    table.decay(now + Duration::from_secs(3600));
+    assert!(table.pdr_table.get(&1).unwrap().0 == 0.);
+    assert!(*table.graph.edge_weight(2, 1).unwrap() == f64::INFINITY);
+}
+
+/// tests the routes forced through a drone
+#[test]
+fn test_get_route_via() {
+    let graph: NetworkGraph = NetworkGraph::from_edges([
+        (0, 1, 1.),
+        (0, 2, 1.),
+        (1, 3, 1.),
+        (2, 3, 5.),
+        (2, 1, 1.),
+        (1, 2, 1.),
+        (3, 4, 1.),
+    ]);
+    let estimator: FnEstimator = FnEstimator::new(2, |_, _, _, _| 0.);
+    let mut table: RoutingTable = RoutingTable::new(Box::new(estimator));
+    table.graph = graph;
+    assert_eq!(table.get_route_via(0, 2, 4).unwrap(), vec![0, 2, 1, 3, 4]);
+    assert_eq!(table.get_route_via(0, 1, 4).unwrap(), vec![0, 1, 3, 4]);
+    // 0 -> 3 -> 1 is not possible
+    assert!(table.get_route_via(0, 4, 3).is_none());
+}
+
+/// tests that exploration eventually chooses a non-optimal route, only for the explored
+/// routes, and that the choices depend only on the seed
+#[test]
+fn test_exploration() {
+    let graph: NetworkGraph =
+        NetworkGraph::from_edges([(0, 1, 1.), (0, 2, 1.), (1, 3, 1.), (2, 3, 1.)]);
+    let estimator: FnEstimator = FnEstimator::new(2, |_, _, _, _| 0.);
+    let mut table: RoutingTable = RoutingTable::new_with_graph(graph, estimator);
+    *table.graph.edge_weight_mut(0, 2).unwrap() = f64::INFINITY;
+    assert_eq!(table.get_explore_route(0, 3).unwrap(), vec![0, 1, 3]);
+
+    let mut table: RoutingTable = table.with_exploration(1.).with_seed(7);
+    let routes: Vec<Vec<u8>> = (0..64)
+        .map(|_| table.get_explore_route(0, 3).unwrap())
+        .collect();
+    assert!(routes.contains(&vec![0, 2, 3]));
+    assert!(routes
+        .iter()
+        .all(|r| *r == vec![0, 1, 3] || *r == vec![0, 2, 3]));
+    // the best route is not affected by the exploration
+    assert!((0..64).all(|_| table.get_route(0, 3).unwrap() == vec![0, 1, 3]));
+
+    table = table.with_seed(7);
+    let replayed: Vec<Vec<u8>> = (0..64)
+        .map(|_| table.get_explore_route(0, 3).unwrap())
+        .collect();
+    assert_eq!(routes, replayed);
+    table.exploration = 0.;
+    assert_eq!(table.get_explore_route(0, 3).unwrap(), vec![0, 1, 3]);
+}
+
+/// tests the computation of the routes that don't share any drone
+#[test]
+fn test_get_disjoint_routes() {
+    let graph: NetworkGraph = NetworkGraph::from_edges([
+        (0, 1, 1.),
+        (0, 4, 1.),
+        (1, 2, 1.),
+        (2, 3, 1.),
+        (1, 5, 1.),
+        (4, 5, 2.),
+        (5, 3, 1.),
+        (4, 6, f64::INFINITY),
+        (6, 3, 1.),
+    ]);
+    let estimator: FnEstimator = FnEstimator::new(2, |_, _, _, _| 0.);
+    let mut table: RoutingTable = RoutingTable::new(Box::new(estimator));
+    table.graph = graph;
+    let routes: Vec<(Vec<u8>, f64)> = table.get_disjoint_routes(0, 3, 3);
+    assert_eq!(routes, vec![(vec![0, 1, 2, 3], 3.), (vec![0, 4, 5, 3], 4.)]);
+    assert_eq!(
+        table.get_disjoint_routes(0, 3, 1),
+        vec![(vec![0, 1, 2, 3], 3.)]
+    );
+    assert!(table.get_disjoint_routes(0, 43, 2).is_empty());
+
+    // a lost route is returned only if there are no others
+    table.graph = NetworkGraph::from_edges([(0, 1, f64::INFINITY), (1, 2, 1.)]);
+    let routes: Vec<(Vec<u8>, f64)> = table.get_disjoint_routes(0, 2, 2);
+    assert_eq!(routes.len(), 1);
+    assert_eq!(routes[0].0, vec![0, 1, 2]);
+}
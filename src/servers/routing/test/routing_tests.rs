@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use common::{slc_commands::ServerCommand, Server};
-use crossbeam_channel::Sender;
+use crossbeam_channel::{Receiver, Sender};
 use petgraph::Graph;
 use wg_2024::{
     network::{NodeId, SourceRoutingHeader},
@@ -9,7 +9,10 @@ use wg_2024::{
 };
 
 use crate::servers::{
-    self, routing::RoutingTable, test_utils::get_dummy_server_text, test_utils::graphmap_eq,
+    self,
+    routing::{stripe, RoutingTable},
+    test_utils::get_dummy_server_text,
+    test_utils::{get_neighbors_builder, graphmap_eq},
     GenericServer, HistoryEntry, NetworkGraph, Text, DEFAULT_WINDOW_SZ, INITIAL_ETX, INITIAL_PDR,
 };
 
@@ -49,7 +52,7 @@ fn add_edge_test1() {
         server.network_graph.get_graph(),
         &NetworkGraph::from_edges([
             (0, 1, INITIAL_ETX),
-            (1, 0, INITIAL_ETX),
+            (1, 0, RoutingTable::ENDPOINT_ETX),
             (1, 2, INITIAL_ETX),
         ])
     ));
@@ -82,7 +85,7 @@ fn test_update_from_flood() {
         (2, 1, INITIAL_ETX),
         (3, 2, INITIAL_ETX),
         (2, 3, INITIAL_ETX),
-        (3, 4, INITIAL_ETX),
+        (3, 4, RoutingTable::ENDPOINT_ETX),
     ]);
     assert!(graphmap_eq(&server.network_graph.get_graph(), &res,));
     fr.path_trace = vec![(0, NodeType::Server), (5, NodeType::Drone)];
@@ -98,7 +101,7 @@ fn test_update_from_hdr() {
     let hdr: SourceRoutingHeader = SourceRoutingHeader::new(vec![1u8, 3u8, 4u8, 5u8, 0u8], 0);
     server.update_network_from_header(&hdr);
     let mut res: NetworkGraph = NetworkGraph::from_edges([
-        (3, 1, INITIAL_ETX),
+        (3, 1, RoutingTable::ENDPOINT_ETX),
         (3, 4, INITIAL_ETX),
         (4, 3, INITIAL_ETX),
         (5, 4, INITIAL_ETX),
@@ -111,7 +114,7 @@ fn test_update_from_hdr() {
     let hdr: SourceRoutingHeader = SourceRoutingHeader::new(vec![1u8, 2u8, 0u8], 0);
     server.update_network_from_header(&hdr);
     res.add_edge(0, 2, INITIAL_ETX);
-    res.add_edge(2, 1, INITIAL_ETX);
+    res.add_edge(2, 1, RoutingTable::ENDPOINT_ETX);
     assert!(graphmap_eq(&server.network_graph.get_graph(), &res));
 }
 
@@ -135,6 +138,37 @@ fn test_get_srch_from_graph() {
     );
 }
 
+/// tests that only the headers of new responses explore non-optimal routes
+#[test]
+fn test_explore_srch() {
+    let mut server: GenericServer<Text> = get_dummy_server_text();
+    let hdr: SourceRoutingHeader = SourceRoutingHeader::new(vec![1, 3, 0], 0);
+    server.network_graph = RoutingTable::new_with_graph(
+        NetworkGraph::from_edges([
+            (0, 3, INITIAL_PDR),
+            (0, 4, INITIAL_PDR),
+            (3, 1, INITIAL_PDR),
+            (4, 1, INITIAL_PDR),
+        ]),
+        servers::default_estimator(),
+    )
+    .with_exploration(1.);
+    // 4 becomes worse than 3
+    for _ in 0..DEFAULT_WINDOW_SZ {
+        server.network_graph.update_pdr(4, false);
+    }
+    let explored: Vec<Vec<NodeId>> = (0..64)
+        .map(|_| server.get_explore_hdr_with_hint(&hdr, 1).hops)
+        .collect();
+    assert!(explored.contains(&vec![0, 4, 1]));
+    for _ in 0..64 {
+        assert_eq!(
+            server.get_routing_hdr_with_hint(&hdr, 1).hops,
+            vec![0, 3, 1]
+        );
+    }
+}
+
 /// tests whether the [GenericServer] correctly fallbacks to the [SourceRoutingHeader] after
 /// failing to find a proper routing path
 #[test]
@@ -185,10 +219,10 @@ fn test_graph_consistency() {
             (4, 3, INITIAL_ETX),
             (3, 4, INITIAL_ETX),
             (0, 3, INITIAL_ETX),
-            (4, 5, INITIAL_ETX),
+            (4, 5, RoutingTable::ENDPOINT_ETX),
             (0, 6, INITIAL_ETX),
-            (6, 7, INITIAL_ETX),
-            (8, 7, INITIAL_ETX),
+            (6, 7, RoutingTable::ENDPOINT_ETX),
+            (8, 7, RoutingTable::ENDPOINT_ETX),
             (8, 9, INITIAL_ETX),
             (9, 8, INITIAL_ETX),
         ])
@@ -209,16 +243,9 @@ fn test_etx_nacks_update_to_inf_and_back() {
         ],
     };
     server.update_network_from_flood(&fr);
-    server.sent_history.insert(
-        0,
-        HistoryEntry {
-            hops: vec![0, 2],
-            receiver_id: 2,
-            frag_idx: 0,
-            n_frags: 1,
-            frag: [0; 128],
-        },
-    );
+    server
+        .sent_history
+        .insert(0, HistoryEntry::new(vec![0, 2], 2, 0, 1, [0; 128]));
     let nack: Nack = Nack {
         fragment_index: 0,
         nack_type: NackType::Dropped,
@@ -230,27 +257,21 @@ fn test_etx_nacks_update_to_inf_and_back() {
     for _ in 0..DEFAULT_WINDOW_SZ * 15 {
         server.handle_nack(0, &SourceRoutingHeader::initialize(vec![1, 0]), &nack);
     }
-    assert!(*server.network_graph.get_graph().edge_weight(1, 2).unwrap() == f64::INFINITY);
+    assert!(*server.network_graph.get_graph().edge_weight(0, 1).unwrap() == f64::INFINITY);
     assert!(server.sent_history.get(&0).unwrap().hops == vec![0, 1, 2]);
     server.handle_nack(0, &SourceRoutingHeader::initialize(vec![1, 0]), &nack);
     assert!(server.sent_history.get(&0).unwrap().hops == vec![0, 1, 2]);
     for i in 1..DEFAULT_WINDOW_SZ * 2 {
         server.sent_history.insert(
             i as u64,
-            HistoryEntry {
-                hops: vec![0, 1, 2],
-                receiver_id: 2,
-                frag_idx: 0,
-                n_frags: 1,
-                frag: [0; 128],
-            },
+            HistoryEntry::new(vec![0, 1, 2], 2, 0, 1, [0; 128]),
         );
     }
     let ack: Ack = Ack { fragment_index: 0 };
     for i in 0..DEFAULT_WINDOW_SZ * 2 {
         server.handle_ack(i as u64, &ack);
     }
-    assert!(*server.network_graph.get_graph().edge_weight(1, 2).unwrap() != f64::INFINITY);
+    assert!(*server.network_graph.get_graph().edge_weight(0, 1).unwrap() != f64::INFINITY);
 }
 
 /// tests whether the ETX corretly converges to 1. or not
@@ -270,13 +291,7 @@ fn test_etx_acks_update_to_1() {
     for i in 0..(DEFAULT_WINDOW_SZ * 15) {
         server.sent_history.insert(
             i as u64,
-            HistoryEntry {
-                hops: vec![0, 1, 2],
-                receiver_id: 2,
-                frag_idx: 0,
-                n_frags: 1,
-                frag: [0; 128],
-            },
+            HistoryEntry::new(vec![0, 1, 2], 2, 0, 1, [0; 128]),
         );
     }
     let ack: Ack = Ack { fragment_index: 0 };
@@ -288,7 +303,7 @@ fn test_etx_acks_update_to_1() {
     for i in 0..DEFAULT_WINDOW_SZ * 15 {
         server.handle_ack(i as u64, &ack);
     }
-    assert!((*server.network_graph.get_graph().edge_weight(1, 2).unwrap() - 1.).abs() < 1e-3);
+    assert!((*server.network_graph.get_graph().edge_weight(0, 1).unwrap() - 1.).abs() < 1e-3);
 }
 
 /// tests whether the [GenericServer] correctly switches route after an ETX update or not
@@ -314,16 +329,9 @@ fn test_route_after_etx_update() {
         ],
     };
     server.update_network_from_flood(&fr);
-    server.sent_history.insert(
-        0,
-        HistoryEntry {
-            hops: vec![0, 2],
-            receiver_id: 2,
-            frag_idx: 0,
-            n_frags: 1,
-            frag: [0; 128],
-        },
-    );
+    server
+        .sent_history
+        .insert(0, HistoryEntry::new(vec![0, 2], 2, 0, 1, [0; 128]));
     let nack: Nack = Nack {
         fragment_index: 0,
         nack_type: NackType::Dropped,
@@ -333,16 +341,9 @@ fn test_route_after_etx_update() {
     server.handle_command(cmd);
     let cmd: ServerCommand = ServerCommand::AddSender(2, ds.clone());
     server.handle_command(cmd);
-    server.sent_history.insert(
-        0,
-        HistoryEntry {
-            hops: vec![0, 1, 3],
-            receiver_id: 3,
-            frag_idx: 0,
-            n_frags: 1,
-            frag: [0; 128],
-        },
-    );
+    server
+        .sent_history
+        .insert(0, HistoryEntry::new(vec![0, 1, 3], 3, 0, 1, [0; 128]));
     for _ in 0..DEFAULT_WINDOW_SZ * 2 {
         assert!(server.sent_history.get(&0).unwrap().hops == vec![0, 1, 3]);
         server.handle_nack(0, &SourceRoutingHeader::initialize(vec![1, 0]), &nack);
@@ -350,3 +351,135 @@ fn test_route_after_etx_update() {
     server.handle_nack(0, &SourceRoutingHeader::initialize(vec![1, 0]), &nack);
     assert!(server.sent_history.get(&0).unwrap().hops == vec![0, 2, 4, 3]);
 }
+
+/// tests that the fragments are assigned to the routes inversely to their cost
+#[test]
+fn test_stripe() {
+    assert_eq!(stripe(&[3.], 3), vec![0, 0, 0]);
+    assert_eq!(stripe(&[3., 3.], 4), vec![0, 1, 0, 1]);
+    let assigned: Vec<usize> = stripe(&[2., 4.], 6);
+    assert_eq!(assigned.iter().filter(|r| **r == 0).count(), 4);
+    assert_eq!(assigned.iter().filter(|r| **r == 1).count(), 2);
+    assert!(stripe(&[1., 1.], 0).is_empty());
+}
+
+/// tests that the fragments of a response are spread over the disjoint routes
+#[test]
+fn test_striped_response() {
+    let (builder, receivers) = get_neighbors_builder(0, &[1, 2]);
+    let [d1_recv, d2_recv]: [Receiver<Packet>; 2] = receivers.try_into().unwrap();
+    let mut server: GenericServer<Text> = builder.max_paths(2).build();
+    server.network_graph = RoutingTable::new_with_graph(
+        NetworkGraph::from_edges([
+            (0, 1, INITIAL_PDR),
+            (0, 2, INITIAL_PDR),
+            (1, 3, INITIAL_PDR),
+            (2, 3, INITIAL_PDR),
+        ]),
+        servers::default_estimator(),
+    );
+    assert_eq!(
+        server.get_striped_hdrs(SourceRoutingHeader::new(vec![0, 1, 3], 1), 3, 1),
+        vec![SourceRoutingHeader::new(vec![0, 1, 3], 1)]
+    );
+    server.send_fragments(
+        &SourceRoutingHeader::new(vec![3, 1, 0], 2),
+        3,
+        0,
+        vec![[0; 128]; 4],
+    );
+    let hops: Vec<Vec<NodeId>> = d1_recv
+        .try_iter()
+        .chain(d2_recv.try_iter())
+        .map(|p: Packet| p.routing_header.hops)
+        .collect();
+    assert_eq!(
+        hops,
+        vec![vec![0, 1, 3], vec![0, 1, 3], vec![0, 2, 3], vec![0, 2, 3]]
+    );
+    assert_eq!(server.sent_history.len(), 4);
+    assert_eq!(
+        server
+            .sent_history
+            .values()
+            .filter(|e: &&HistoryEntry| e.hops == vec![0, 2, 3])
+            .count(),
+        2
+    );
+
+    // without multipath all the fragments use the best route
+    server.max_paths = 1;
+    server.send_fragments(
+        &SourceRoutingHeader::new(vec![3, 1, 0], 2),
+        3,
+        1,
+        vec![[0; 128]; 4],
+    );
+    assert_eq!(d1_recv.try_iter().count() + d2_recv.try_iter().count(), 4);
+}
+
+/// tests the exact ETX of the drones after a known sequence of acks and nacks, the losses
+/// are attributed to the drone that sent the nack and the ETX is the weight of the edges
+/// going into the drone
+#[test]
+fn test_exact_etx_after_acks_and_nacks() {
+    let mut server: GenericServer<Text> = get_dummy_server_text();
+    let fr: FloodResponse = FloodResponse {
+        flood_id: 0,
+        path_trace: vec![
+            (0, NodeType::Server),
+            (1, NodeType::Drone),
+            (2, NodeType::Drone),
+            (3, NodeType::Client),
+        ],
+    };
+    server.update_network_from_flood(&fr);
+    let mut drones: Vec<NodeId> = server.network_graph.pdr_table.keys().copied().collect();
+    drones.sort_unstable();
+    assert_eq!(drones, vec![1, 2]);
+    let etx = |server: &GenericServer<Text>, from: NodeId, to: NodeId| {
+        *server
+            .network_graph
+            .get_graph()
+            .edge_weight(from, to)
+            .unwrap()
+    };
+
+    // drone 2 drops every fragment forwarded by drone 1
+    for _ in 0..DEFAULT_WINDOW_SZ {
+        server.update_pdr_from_nack(&[2, 1, 0]);
+    }
+    // PDR(1) = 0.35 * 1 + 0.65 * 0.5, PDR(2) = 0.35 * 0 + 0.65 * 0.5
+    assert!((etx(&server, 0, 1) - 1. / 0.675).abs() < 1e-9);
+    assert!((etx(&server, 2, 1) - 1. / 0.675).abs() < 1e-9);
+    assert!((etx(&server, 1, 2) - 1. / 0.325).abs() < 1e-9);
+    assert!(etx(&server, 2, 3) == RoutingTable::ENDPOINT_ETX);
+
+    for _ in 0..DEFAULT_WINDOW_SZ {
+        server.update_pdr_from_ack(&[0, 1, 2, 3]);
+    }
+    // PDR(1) = 0.35 + 0.65 * 0.675, PDR(2) = 0.35 + 0.65 * 0.325
+    assert!((etx(&server, 0, 1) - 1. / 0.788_75).abs() < 1e-9);
+    assert!((etx(&server, 1, 2) - 1. / 0.561_25).abs() < 1e-9);
+    assert!(etx(&server, 2, 3) == RoutingTable::ENDPOINT_ETX);
+
+    // acks and nacks never create entries for the endpoints
+    server.update_pdr_from_nack(&[3, 2, 1, 0]);
+    assert!(!server.network_graph.pdr_table.contains_key(&3));
+    assert!(!server.network_graph.pdr_table.contains_key(&0));
+}
+
+/// tests that nodes first seen as drones are removed from the pdr table
+/// once they are known to be clients or servers
+#[test]
+fn test_add_endpoint() {
+    let mut server: GenericServer<Text> = get_dummy_server_text();
+    server.check_and_add_edge(1, 5);
+    assert!(server.network_graph.pdr_table.contains_key(&5));
+    assert!(*server.network_graph.get_graph().edge_weight(1, 5).unwrap() == INITIAL_ETX);
+    server.update_network_from_header(&SourceRoutingHeader::new(vec![5, 1, 0], 2));
+    assert!(!server.network_graph.pdr_table.contains_key(&5));
+    assert!(
+        *server.network_graph.get_graph().edge_weight(1, 5).unwrap() == RoutingTable::ENDPOINT_ETX
+    );
+}
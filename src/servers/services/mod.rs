@@ -0,0 +1,210 @@
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use common::{
+    slc_commands::ServerType,
+    web_messages::{
+        Compression, MediaRequest, Request, RequestMessage, ResponseMessage, TextRequest,
+    },
+};
+use wg_2024::network::NodeId;
+
+use super::content::{ContentError, ContentRoot};
+
+/// testing module
+#[cfg(test)]
+mod test;
+
+/// Kind of the content of a file, decides the response sent by the [`super::GenericServer`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileKind {
+    /// the file is sent with a text response
+    Text,
+    /// the file is sent with a media response
+    Media,
+}
+
+impl FileKind {
+    /// creates the response of this kind containing `data`
+    pub(crate) fn response(
+        self,
+        server_id: NodeId,
+        compression: Compression,
+        data: Vec<u8>,
+    ) -> ResponseMessage {
+        match self {
+            FileKind::Text => ResponseMessage::new_text_response(server_id, compression, data),
+            FileKind::Media => ResponseMessage::new_media_response(server_id, compression, data),
+        }
+    }
+}
+
+/// Answer of a [`RequestService`] to a request
+#[derive(Debug, PartialEq)]
+pub enum Reply {
+    /// response sent as is to the client
+    Response(ResponseMessage),
+    /// the response contains the file at `path`, which is read by the [`super::GenericServer`].
+    /// The compressed response is cached until the file changes
+    File {
+        /// path of the file, it must already be validated by the service
+        path: PathBuf,
+        /// kind of the content of the file
+        kind: FileKind,
+    },
+    /// the requested content does not exist
+    NotFound,
+    /// the requested name points outside of the content root, the client is
+    /// answered as if the file did not exist
+    Forbidden,
+    /// the request is not supported by the service
+    Invalid,
+}
+
+/// Service that answers the requests received by a [`super::GenericServer`].
+///
+/// The [`super::GenericServer`] takes care of the transport, routing, compression and
+/// of the [`Request::Type`] requests (answered with [`RequestService::server_type`]),
+/// the service only decides the content of the responses. The requests are answered by
+/// the worker threads of the server (see [`super::GenericServerBuilder::workers`]),
+/// so that reading the content directories doesn't stop the handling of the packets
+pub trait RequestService: Send {
+    /// type of the server, sent to the clients asking for it
+    fn server_type(&self) -> ServerType;
+
+    /// answers a request received by the server `server_id`, the responses built
+    /// by the service should use the compression requested by the client
+    fn handle(&mut self, server_id: NodeId, request: &RequestMessage) -> Reply;
+}
+
+/// [`RequestService`] shared by the workers of a [`super::GenericServer`], the requests
+/// are answered one at a time
+pub(super) type SharedService = Arc<Mutex<Box<dyn RequestService>>>;
+
+/// maps the resolution of a requested name to the [`Reply`] containing the file
+fn file_reply(content: &ContentRoot, name: &str, kind: FileKind) -> Reply {
+    match content.resolve(name) {
+        Ok(path) => Reply::File { path, kind },
+        Err(ContentError::Forbidden) => Reply::Forbidden,
+        Err(ContentError::NotFound) => Reply::NotFound,
+    }
+}
+
+/// Default service of the [`super::TextServer`]: answers the text requests
+/// with the files in its [`ContentRoot`]
+#[derive(Debug, Clone)]
+pub struct TextService {
+    /// directory containing the served files
+    content: ContentRoot,
+}
+
+impl TextService {
+    /// creates a new [`TextService`] serving the files in `content`
+    #[inline]
+    #[must_use]
+    pub fn new(content: ContentRoot) -> Self {
+        Self { content }
+    }
+}
+
+impl RequestService for TextService {
+    fn server_type(&self) -> ServerType {
+        ServerType::FileServer
+    }
+
+    fn handle(&mut self, server_id: NodeId, request: &RequestMessage) -> Reply {
+        match &request.content {
+            Request::Text(TextRequest::TextList) => {
+                Reply::Response(ResponseMessage::new_text_list_response(
+                    server_id,
+                    request.compression_type.clone(),
+                    self.content.list().unwrap_or_default(),
+                ))
+            }
+            Request::Text(TextRequest::Text(name)) => {
+                file_reply(&self.content, name, FileKind::Text)
+            }
+            _ => Reply::Invalid,
+        }
+    }
+}
+
+/// Default service of the [`super::MediaServer`]: answers the media requests
+/// with the files in its [`ContentRoot`]
+#[derive(Debug, Clone)]
+pub struct MediaService {
+    /// directory containing the served files
+    content: ContentRoot,
+}
+
+impl MediaService {
+    /// creates a new [`MediaService`] serving the files in `content`
+    #[inline]
+    #[must_use]
+    pub fn new(content: ContentRoot) -> Self {
+        Self { content }
+    }
+}
+
+impl RequestService for MediaService {
+    fn server_type(&self) -> ServerType {
+        ServerType::MediaServer
+    }
+
+    fn handle(&mut self, server_id: NodeId, request: &RequestMessage) -> Reply {
+        match &request.content {
+            Request::Media(MediaRequest::MediaList) => {
+                Reply::Response(ResponseMessage::new_media_list_response(
+                    server_id,
+                    request.compression_type.clone(),
+                    self.content.list().unwrap_or_default(),
+                ))
+            }
+            Request::Media(MediaRequest::Media(name)) => {
+                file_reply(&self.content, name, FileKind::Media)
+            }
+            _ => Reply::Invalid,
+        }
+    }
+}
+
+/// Default service of the [`super::TextMediaServer`]: answers the text requests with the
+/// files in its text [`ContentRoot`] and the media requests with the files in its
+/// media [`ContentRoot`]
+#[derive(Debug, Clone)]
+pub struct TextMediaService {
+    /// service answering the text requests
+    text: TextService,
+    /// service answering the media requests
+    media: MediaService,
+}
+
+impl TextMediaService {
+    /// creates a new [`TextMediaService`] serving the text files in `text`
+    /// and the media in `media`
+    #[inline]
+    #[must_use]
+    pub fn new(text: ContentRoot, media: ContentRoot) -> Self {
+        Self {
+            text: TextService::new(text),
+            media: MediaService::new(media),
+        }
+    }
+}
+
+impl RequestService for TextMediaService {
+    /// the protocol has no type for servers offering both kinds of content, the server
+    /// is reported as a file server since the media are linked by its text files
+    fn server_type(&self) -> ServerType {
+        ServerType::FileServer
+    }
+
+    fn handle(&mut self, server_id: NodeId, request: &RequestMessage) -> Reply {
+        match &request.content {
+            Request::Media(_) => self.media.handle(server_id, request),
+            _ => self.text.handle(server_id, request),
+        }
+    }
+}
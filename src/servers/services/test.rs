@@ -0,0 +1,140 @@
+#[cfg(test)]
+mod services_tests {
+    use std::path::PathBuf;
+
+    use common::{
+        slc_commands::ServerType,
+        web_messages::{Compression, RequestMessage, ResponseMessage},
+    };
+
+    use crate::servers::{
+        content::ContentRoot,
+        services::{FileKind, MediaService, Reply, RequestService, TextMediaService, TextService},
+        MEDIA_PATH, TEXT_PATH,
+    };
+
+    /// canonical path of the file `name` in `dir`
+    fn canonical(dir: &str, name: &str) -> PathBuf {
+        PathBuf::from(dir).join(name).canonicalize().unwrap()
+    }
+
+    /// tests the replies of the [`TextService`]
+    #[test]
+    fn test_text_service() {
+        let mut service: TextService = TextService::new(ContentRoot::new(TEXT_PATH));
+        assert_eq!(service.server_type(), ServerType::FileServer);
+
+        let list: RequestMessage = RequestMessage::new_text_list_request(1, Compression::LZW);
+        assert_eq!(
+            service.handle(0, &list),
+            Reply::Response(ResponseMessage::new_text_list_response(
+                0,
+                Compression::LZW,
+                vec![
+                    "file.html".to_owned(),
+                    "file2.html".to_owned(),
+                    "index.html".to_owned(),
+                    "three.html".to_owned(),
+                ]
+            ))
+        );
+
+        let file =
+            |name: &str| RequestMessage::new_text_request(1, Compression::None, name.to_owned());
+        assert_eq!(
+            service.handle(0, &file("file.html")),
+            Reply::File {
+                path: canonical(TEXT_PATH, "file.html"),
+                kind: FileKind::Text
+            }
+        );
+        assert_eq!(service.handle(0, &file("missing.html")), Reply::NotFound);
+        assert_eq!(service.handle(0, &file("../Cargo.toml")), Reply::Forbidden);
+
+        let media: RequestMessage = RequestMessage::new_media_list_request(1, Compression::None);
+        assert_eq!(service.handle(0, &media), Reply::Invalid);
+    }
+
+    /// tests the replies of the [`MediaService`]
+    #[test]
+    fn test_media_service() {
+        let mut service: MediaService = MediaService::new(ContentRoot::new(MEDIA_PATH));
+        assert_eq!(service.server_type(), ServerType::MediaServer);
+
+        let list: RequestMessage = RequestMessage::new_media_list_request(1, Compression::None);
+        assert_eq!(
+            service.handle(0, &list),
+            Reply::Response(ResponseMessage::new_media_list_response(
+                0,
+                Compression::None,
+                vec![
+                    "c++.png".to_owned(),
+                    "haskell.jpg".to_owned(),
+                    "image.jpg".to_owned(),
+                    "rust.png".to_owned(),
+                ]
+            ))
+        );
+
+        let file =
+            |name: &str| RequestMessage::new_media_request(1, Compression::None, name.to_owned());
+        assert_eq!(
+            service.handle(0, &file("rust.png")),
+            Reply::File {
+                path: canonical(MEDIA_PATH, "rust.png"),
+                kind: FileKind::Media
+            }
+        );
+        assert_eq!(service.handle(0, &file("missing.png")), Reply::NotFound);
+        assert_eq!(service.handle(0, &file("../Cargo.toml")), Reply::Forbidden);
+
+        let text: RequestMessage = RequestMessage::new_text_list_request(1, Compression::None);
+        assert_eq!(service.handle(0, &text), Reply::Invalid);
+    }
+
+    /// tests that the [`TextMediaService`] answers both kinds of requests from their roots
+    #[test]
+    fn test_text_media_service() {
+        let mut service: TextMediaService =
+            TextMediaService::new(ContentRoot::new(TEXT_PATH), ContentRoot::new(MEDIA_PATH));
+        assert_eq!(service.server_type(), ServerType::FileServer);
+
+        let text: RequestMessage =
+            RequestMessage::new_text_request(1, Compression::None, "file.html".to_owned());
+        assert_eq!(
+            service.handle(0, &text),
+            Reply::File {
+                path: canonical(TEXT_PATH, "file.html"),
+                kind: FileKind::Text
+            }
+        );
+        let media: RequestMessage =
+            RequestMessage::new_media_request(1, Compression::None, "rust.png".to_owned());
+        assert_eq!(
+            service.handle(0, &media),
+            Reply::File {
+                path: canonical(MEDIA_PATH, "rust.png"),
+                kind: FileKind::Media
+            }
+        );
+        // each kind of request is served only from its own root
+        let text: RequestMessage =
+            RequestMessage::new_text_request(1, Compression::None, "rust.png".to_owned());
+        assert_eq!(service.handle(0, &text), Reply::NotFound);
+
+        let list: RequestMessage = RequestMessage::new_media_list_request(1, Compression::None);
+        assert_eq!(
+            service.handle(0, &list),
+            Reply::Response(ResponseMessage::new_media_list_response(
+                0,
+                Compression::None,
+                vec![
+                    "c++.png".to_owned(),
+                    "haskell.jpg".to_owned(),
+                    "image.jpg".to_owned(),
+                    "rust.png".to_owned(),
+                ]
+            ))
+        );
+    }
+}
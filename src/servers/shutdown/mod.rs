@@ -0,0 +1,76 @@
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+
+use super::{GenericServer, ServerType};
+
+/// testing module
+#[cfg(test)]
+mod test;
+
+/// default maximum time waited for the in-flight responses during a graceful shutdown
+pub(crate) const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Phase of the life of a [`GenericServer`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum Lifecycle {
+    /// the server is running normally
+    #[default]
+    Running,
+    /// the server is waiting for the in-flight responses to be acknowledged or
+    /// given up, it stops once they are all done or at `deadline`
+    Draining {
+        /// instant after which the remaining responses are abandoned
+        deadline: Instant,
+    },
+    /// the server must stop as soon as possible, abandoning the in-flight responses
+    Stopped,
+}
+
+impl<T: ServerType> GenericServer<T> {
+    /// starts a graceful shutdown: the server keeps handling packets until every
+    /// in-flight response is acknowledged or given up, for at most the shutdown timeout
+    pub(super) fn begin_shutdown(&mut self, now: Instant) {
+        if self.lifecycle == Lifecycle::Running {
+            info!(target: &self.target_topic, "Shutting down, waiting for {} fragments", self.sent_history.len());
+            self.lifecycle = Lifecycle::Draining {
+                deadline: now + self.shutdown_timeout,
+            };
+        }
+    }
+
+    /// stops the server immediately, abandoning the in-flight responses
+    pub(super) fn crash(&mut self) {
+        warn!(target: &self.target_topic, "Crashing, abandoning {} fragments", self.sent_history.len());
+        self.lifecycle = Lifecycle::Stopped;
+    }
+
+    /// checks if the main loop must return. A graceful shutdown completes when no
+    /// response is being encoded and no fragment is waiting to be sent or acknowledged,
+    /// then the routing state is saved
+    pub(super) fn should_stop(&mut self, now: Instant) -> bool {
+        match self.lifecycle {
+            Lifecycle::Running => false,
+            Lifecycle::Draining { deadline } => {
+                let done: bool = self.workers.is_idle()
+                    && self.sent_history.is_empty()
+                    && self.send_windows.is_empty();
+                if !done && now < deadline {
+                    return false;
+                }
+                if !done {
+                    warn!(target: &self.target_topic, "Shutdown timeout expired, abandoning {} fragments", self.sent_history.len());
+                }
+                if let Some(file) = &self.state_file {
+                    if let Err(e) = self.save_routing_state(&file.path) {
+                        warn!(target: &self.target_topic, "Unable to save the routing state: {e:?}");
+                    }
+                }
+                info!(target: &self.target_topic, "Shutdown completed");
+                self.lifecycle = Lifecycle::Stopped;
+                true
+            }
+            Lifecycle::Stopped => true,
+        }
+    }
+}
@@ -0,0 +1,150 @@
+#[cfg(test)]
+mod shutdown_tests {
+    use std::{
+        collections::HashMap,
+        env, fs,
+        path::PathBuf,
+        thread::{self, JoinHandle},
+        time::{Duration, Instant},
+    };
+
+    use common::{
+        slc_commands::{ServerCommand, ServerEvent},
+        Server,
+    };
+    use crossbeam_channel::{Receiver, Sender};
+    use wg_2024::packet::Packet;
+
+    use crate::servers::{
+        extensions::ExtendedCommand, shutdown::Lifecycle, GenericServer, GenericServerBuilder,
+        HistoryEntry, Text,
+    };
+
+    /// channels used to control a [`GenericServer`] in the tests
+    struct Channels {
+        /// channel to send [`ServerCommand`]s
+        ctrl_send: Sender<ServerCommand>,
+        /// channel to receive [`ServerEvent`]s
+        _ctrl_events: Receiver<ServerEvent>,
+        /// channel to send [Packet]s
+        packet_send: Sender<Packet>,
+        /// channel to send [`ExtendedCommand`]s
+        ext_send: Sender<ExtendedCommand>,
+    }
+
+    /// get a [`GenericServerBuilder<Text>`] with the channels used to control it
+    fn get_builder() -> (GenericServerBuilder<Text>, Channels) {
+        let (ctrl_event_send, ctrl_events) = crossbeam_channel::unbounded();
+        let (ctrl_send, ctrl_recv) = crossbeam_channel::unbounded();
+        let (packet_send, server_recv) = crossbeam_channel::unbounded();
+        let (ext_send, ext_recv) = crossbeam_channel::unbounded();
+        let builder: GenericServerBuilder<Text> =
+            GenericServerBuilder::new(0, ctrl_event_send, ctrl_recv, server_recv, HashMap::new())
+                .extended_commands(ext_recv);
+        let channels: Channels = Channels {
+            ctrl_send,
+            _ctrl_events: ctrl_events,
+            packet_send,
+            ext_send,
+        };
+        (builder, channels)
+    }
+
+    /// runs the server in a new thread
+    fn spawn(mut server: GenericServer<Text>) -> JoinHandle<()> {
+        thread::spawn(move || server.run())
+    }
+
+    /// waits for the server thread to return
+    fn join_within(handle: JoinHandle<()>, timeout: Duration) {
+        let start: Instant = Instant::now();
+        while !handle.is_finished() {
+            assert!(start.elapsed() < timeout, "the server did not stop");
+            thread::sleep(Duration::from_millis(10));
+        }
+        handle.join().unwrap();
+    }
+
+    /// tests that a graceful shutdown waits for the in-flight fragments
+    #[test]
+    fn test_drain() {
+        let (builder, _channels) = get_builder();
+        let mut server: GenericServer<Text> =
+            builder.shutdown_timeout(Duration::from_secs(1)).build();
+        server
+            .sent_history
+            .insert(0, HistoryEntry::new(vec![0, 1, 2], 2, 0, 1, [0; 128]));
+        let now: Instant = Instant::now();
+        assert!(!server.should_stop(now));
+        server.begin_shutdown(now);
+        assert!(!server.should_stop(now));
+        server.sent_history.clear();
+        assert!(server.should_stop(now));
+        assert_eq!(server.lifecycle, Lifecycle::Stopped);
+
+        let (builder, _channels) = get_builder();
+        let mut server: GenericServer<Text> =
+            builder.shutdown_timeout(Duration::from_secs(1)).build();
+        server
+            .sent_history
+            .insert(0, HistoryEntry::new(vec![0, 1, 2], 2, 0, 1, [0; 128]));
+        server.begin_shutdown(now);
+        server.begin_shutdown(now + Duration::from_secs(1));
+        assert!(!server.should_stop(now + Duration::from_millis(500)));
+        assert!(server.should_stop(now + Duration::from_secs(1)));
+    }
+
+    /// tests that [`ExtendedCommand::Shutdown`] makes `run` return
+    #[test]
+    fn test_shutdown_command() {
+        let (builder, channels) = get_builder();
+        let handle: JoinHandle<()> = spawn(builder.build());
+        channels.ext_send.send(ExtendedCommand::Shutdown).unwrap();
+        join_within(handle, Duration::from_secs(2));
+    }
+
+    /// tests that [`ExtendedCommand::Crash`] abandons the in-flight fragments
+    #[test]
+    fn test_crash_command() {
+        let (builder, channels) = get_builder();
+        let mut server: GenericServer<Text> =
+            builder.shutdown_timeout(Duration::from_secs(100)).build();
+        server
+            .sent_history
+            .insert(0, HistoryEntry::new(vec![0, 1, 2], 2, 0, 1, [0; 128]));
+        let handle: JoinHandle<()> = spawn(server);
+        channels.ext_send.send(ExtendedCommand::Crash).unwrap();
+        join_within(handle, Duration::from_secs(2));
+    }
+
+    /// tests that the server stops when the controller disconnects
+    #[test]
+    fn test_controller_disconnected() {
+        let (builder, channels) = get_builder();
+        let handle: JoinHandle<()> = spawn(builder.build());
+        drop(channels.ctrl_send);
+        join_within(handle, Duration::from_secs(2));
+    }
+
+    /// tests that the server stops when no packet can be received anymore
+    #[test]
+    fn test_packets_disconnected() {
+        let (builder, channels) = get_builder();
+        let handle: JoinHandle<()> = spawn(builder.build());
+        drop(channels.packet_send);
+        join_within(handle, Duration::from_secs(2));
+        drop(channels.ctrl_send);
+    }
+
+    /// tests that the routing state is saved by a graceful shutdown
+    #[test]
+    fn test_shutdown_saves_state() {
+        let path: PathBuf = env::temp_dir().join(format!("shutdown_state_{}", std::process::id()));
+        let (builder, channels) = get_builder();
+        let handle: JoinHandle<()> = spawn(builder.routing_state_file(&path).build());
+        channels.ext_send.send(ExtendedCommand::Shutdown).unwrap();
+        join_within(handle, Duration::from_secs(2));
+        assert!(path.exists());
+        fs::remove_file(&path).unwrap();
+    }
+}
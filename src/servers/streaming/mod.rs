@@ -0,0 +1,329 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::PathBuf,
+};
+
+use common::web_messages::{Compression, ResponseMessage};
+use log::{error, info, warn};
+use wg_2024::{
+    network::{NodeId, SourceRoutingHeader},
+    packet::FRAGMENT_DSIZE,
+};
+
+use super::{
+    auto_compression::CompressionPolicy,
+    metrics,
+    requests_handling::{CacheKey, FileVersion},
+    services::FileKind,
+    workers::{encode_with_policy, ResponseTarget, Work},
+    GenericServer, HistoryEntry, ServerType,
+};
+
+/// testing module
+#[cfg(test)]
+mod test;
+
+/// default size of the files whose uncompressed responses are streamed, in bytes
+pub(crate) const DEFAULT_STREAMING_THRESHOLD: u64 = 1 << 20;
+/// number of fragments over which the routes of a streamed response are spread,
+/// the same pattern is repeated for the following fragments
+const STRIPE_LEN: usize = 64;
+/// content of the response encoded to check the layout of the streamed responses
+const LAYOUT_PROBE: u8 = 0xA5;
+
+/// encodes the length of the content of a response, as done by the serialization
+fn encode_len(len: u64) -> Option<Vec<u8>> {
+    bincode::encode_to_vec(len, bincode::config::standard()).ok()
+}
+
+/// Layout of an uncompressed file response once encoded: `prefix`, length of the
+/// file, content of the file and `suffix`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Layout {
+    /// bytes preceding the length of the file
+    prefix: Vec<u8>,
+    /// bytes following the content of the file
+    suffix: Vec<u8>,
+}
+
+/// layouts of the streamed responses of each kind, computed once by the server
+pub(super) type StreamLayouts = HashMap<FileKind, Layout>;
+
+impl Layout {
+    /// finds the layout of the responses of the given kind, encoded following the policy,
+    /// from the encoding of an empty response: the content of the file is the last field
+    /// of the response, so the encoding ends with its length. The layout is checked against
+    /// the encoding of a response of one byte, [None] if it doesn't match
+    pub(super) fn new(
+        server_id: NodeId,
+        kind: FileKind,
+        policy: CompressionPolicy,
+        topic: &str,
+    ) -> Option<Self> {
+        let resp: ResponseMessage = kind.response(server_id, Compression::None, Vec::new());
+        let (empty, _) = encode_with_policy(resp, topic, policy)?;
+        let resp: ResponseMessage = kind.response(server_id, Compression::None, vec![LAYOUT_PROBE]);
+        let (one, _) = encode_with_policy(resp, topic, policy)?;
+        let layout: Option<Self> =
+            empty
+                .strip_suffix(encode_len(0)?.as_slice())
+                .map(|prefix: &[u8]| Self {
+                    prefix: prefix.to_vec(),
+                    suffix: Vec::new(),
+                });
+        match layout {
+            Some(layout) if one == layout.encode(&encode_len(1)?, &[LAYOUT_PROBE]) => Some(layout),
+            _ => {
+                error!(target: topic, "Unexpected encoding of the {kind:?} responses, they are not streamed");
+                None
+            }
+        }
+    }
+
+    /// encoding of the response with the given encoded length and content
+    fn encode(&self, len: &[u8], content: &[u8]) -> Vec<u8> {
+        [self.prefix.as_slice(), len, content, &self.suffix].concat()
+    }
+}
+
+/// Uncompressed file response whose fragments are read from the file when they are
+/// sent, so that only the fragments waiting to be acknowledged are kept in memory
+#[derive(Debug)]
+pub(super) struct FileStream {
+    /// file being sent
+    file: File,
+    /// path of the file
+    path: PathBuf,
+    /// kind of the response
+    kind: FileKind,
+    /// compression requested by the client, used to answer it again if the streaming fails
+    compression: Compression,
+    /// version of the file when the response started, the response is abandoned
+    /// if the open file changes
+    version: FileVersion,
+    /// bytes of the response preceding the content of the file
+    head: Vec<u8>,
+    /// bytes of the response following the content of the file
+    suffix: Vec<u8>,
+    /// total number of fragments
+    n_frags: u64,
+    /// index of the next fragment to send
+    next: u64,
+    /// routing headers of the fragments, repeated cyclically
+    hdrs: Vec<SourceRoutingHeader>,
+    /// routing header of the request, used to answer it again if the streaming fails
+    srch: SourceRoutingHeader,
+}
+
+impl FileStream {
+    /// opens the response of the given kind and layout containing the file at `path`,
+    /// requested with the given compression. [None] if the file cannot be opened
+    pub(super) fn open(
+        path: PathBuf,
+        kind: FileKind,
+        compression: Compression,
+        version: FileVersion,
+        layout: &Layout,
+    ) -> Option<Self> {
+        let Layout { mut prefix, suffix } = layout.clone();
+        prefix.extend(encode_len(version.size())?);
+        let file: File = File::open(&path).ok()?;
+        let total: u64 = prefix.len() as u64 + version.size() + suffix.len() as u64;
+        Some(Self {
+            file,
+            path,
+            kind,
+            compression,
+            version,
+            head: prefix,
+            suffix,
+            n_frags: total.div_ceil(FRAGMENT_DSIZE as u64),
+            next: 0,
+            hdrs: Vec::new(),
+            srch: SourceRoutingHeader::empty_route(),
+        })
+    }
+
+    /// size of the encoded response, in bytes
+    fn total(&self) -> u64 {
+        self.head.len() as u64 + self.version.size() + self.suffix.len() as u64
+    }
+
+    /// number of fragments of the response
+    #[inline]
+    pub(super) fn n_frags(&self) -> u64 {
+        self.n_frags
+    }
+
+    /// fails if the open file changed since the response started. A file replaced or
+    /// removed by path is still read through the open handle, so only the changes of
+    /// its content are detected
+    fn check_version(&self) -> io::Result<()> {
+        if FileVersion::of_file(&self.file) == Some(self.version) {
+            Ok(())
+        } else {
+            Err(io::Error::other("the file changed"))
+        }
+    }
+
+    /// reads the fragment `idx` of the response.
+    /// The positions are converted only after being bounded by the in-memory parts
+    #[allow(clippy::cast_possible_truncation)]
+    fn read_fragment(&mut self, idx: u64) -> io::Result<[u8; FRAGMENT_DSIZE]> {
+        let mut frag: [u8; FRAGMENT_DSIZE] = [0; FRAGMENT_DSIZE];
+        let start: u64 = idx * FRAGMENT_DSIZE as u64;
+        let end: u64 = (start + FRAGMENT_DSIZE as u64).min(self.total());
+        let head: u64 = self.head.len() as u64;
+        let body_end: u64 = head + self.version.size();
+        for pos in start..end {
+            let byte: &mut u8 = &mut frag[(pos - start) as usize];
+            if pos < head {
+                *byte = self.head[pos as usize];
+            } else if pos >= body_end {
+                *byte = self.suffix[(pos - body_end) as usize];
+            }
+        }
+        let (from, to) = (start.max(head), end.min(body_end));
+        if from < to {
+            self.file.seek(SeekFrom::Start(from - head))?;
+            self.file
+                .read_exact(&mut frag[(from - start) as usize..(to - start) as usize])?;
+        }
+        Ok(frag)
+    }
+
+    /// reads the next fragment of the response, with its routing header and the entry
+    /// kept in the sent history, checking first that the file didn't change if
+    /// `check_version`. [None] once the whole response was read
+    pub(super) fn next_fragment(
+        &mut self,
+        receiver_id: NodeId,
+        check_version: bool,
+    ) -> Option<io::Result<(SourceRoutingHeader, HistoryEntry)>> {
+        if self.next >= self.n_frags || self.hdrs.is_empty() {
+            return None;
+        }
+        if check_version {
+            if let Err(e) = self.check_version() {
+                return Some(Err(e));
+            }
+        }
+        let idx: u64 = self.next;
+        self.next += 1;
+        let hdr: SourceRoutingHeader = self.hdrs[0].clone();
+        self.hdrs.rotate_left(1);
+        Some(self.read_fragment(idx).map(|frag: [u8; FRAGMENT_DSIZE]| {
+            let entry: HistoryEntry =
+                HistoryEntry::new(hdr.hops.clone(), receiver_id, idx, self.n_frags, frag);
+            (hdr, entry)
+        }))
+    }
+
+    /// work needed to send the response without streaming it
+    fn into_work(self, server_id: NodeId) -> Work {
+        Work::File {
+            server_id,
+            key: CacheKey::new(self.path, self.kind, &self.compression),
+            version: Some(self.version),
+            kind: self.kind,
+            compression: self.compression,
+        }
+    }
+}
+
+impl<T: ServerType> GenericServer<T> {
+    /// opens the streamed response of the given kind containing the file at `path`,
+    /// the layout of the responses of each kind is computed only once. [None] if the file
+    /// cannot be opened or the responses cannot be streamed
+    pub(super) fn open_stream(
+        &mut self,
+        path: PathBuf,
+        kind: FileKind,
+        compression: Compression,
+        version: FileVersion,
+    ) -> Option<FileStream> {
+        if !self.stream_layouts.contains_key(&kind) {
+            let layout: Layout =
+                Layout::new(self.id, kind, self.compression_policy, &self.target_topic)?;
+            self.stream_layouts.insert(kind, layout);
+        }
+        FileStream::open(
+            path,
+            kind,
+            compression,
+            version,
+            &self.stream_layouts[&kind],
+        )
+    }
+
+    /// checks if the response containing a file of the given version, requested with the
+    /// given compression, must be streamed: only the large uncompressed responses are
+    /// streamed, since the client decodes the response with the compression it requested
+    pub(super) fn should_stream(&self, version: FileVersion, compression: &Compression) -> bool {
+        matches!(compression, Compression::None)
+            && self
+                .streaming_threshold
+                .is_some_and(|threshold: u64| version.size() >= threshold)
+    }
+
+    /// starts sending a streamed response, its fragments are read when the window of
+    /// the response has room. If no route is known the whole file is read and the
+    /// response waits for a route in its window
+    pub(super) fn send_streamed(&mut self, target: ResponseTarget, mut stream: FileStream) {
+        let ResponseTarget { srch, src_id, rid } = target;
+        let mut resp_hdr: SourceRoutingHeader = self.get_explore_hdr_with_hint(&srch, src_id);
+        if resp_hdr.len() < 2 {
+            error!(target: &self.target_topic, "Error, srch of response inconsistent: {resp_hdr}. Dropping response");
+            return;
+        }
+        resp_hdr.increase_hop_index();
+        if !self.packet_send.contains_key(&resp_hdr.hops[1]) {
+            warn!(target: &self.target_topic, "No path found, the file {} is not streamed", stream.path.display());
+            let target: ResponseTarget = ResponseTarget { srch, src_id, rid };
+            let work: Work = stream.into_work(self.id);
+            self.submit(target, work);
+            return;
+        }
+
+        info!(target: &self.target_topic, "Streaming {} in {} fragments", stream.path.display(), stream.n_frags());
+        let stripe: usize =
+            usize::try_from(stream.n_frags()).map_or(STRIPE_LEN, |n| n.min(STRIPE_LEN));
+        stream.hdrs = self.get_striped_hdrs(resp_hdr, src_id, stripe);
+        stream.srch = srch;
+        let total: usize = usize::try_from(stream.total()).unwrap_or(usize::MAX);
+        let file_type: String = metrics::file_type(&stream.path);
+        self.metrics.count_bytes(Some(&file_type), total, total);
+        self.stream_windowed(src_id, rid, stream);
+    }
+
+    /// answers again the request of a response whose streaming failed, with the same rid
+    /// and compression: the current file is sent if it still exists, otherwise a not found
+    /// response. The fragments of the abandoned response are forgotten first, so that none
+    /// of them is sent again along with the new response
+    pub(super) fn stream_failed(&mut self, receiver_id: NodeId, rid: u16, stream: FileStream) {
+        let FileStream {
+            path,
+            kind,
+            compression,
+            srch,
+            ..
+        } = stream;
+        self.abandon_response(receiver_id, rid);
+        if FileVersion::of(&path).is_some() {
+            info!(target: &self.target_topic, "Restarting the response {rid} with the new version of {}", path.display());
+            self.send_file_response(&srch, receiver_id, rid, path, kind, compression);
+        } else {
+            warn!(target: &self.target_topic, "The file {} was removed while streamed", path.display());
+            let target: ResponseTarget = ResponseTarget {
+                srch,
+                src_id: receiver_id,
+                rid,
+            };
+            let resp: ResponseMessage =
+                ResponseMessage::new_not_found_response(self.id, compression);
+            self.submit(target, Work::Encode(resp));
+        }
+    }
+}
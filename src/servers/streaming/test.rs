@@ -0,0 +1,256 @@
+#[cfg(test)]
+mod streaming_tests {
+    use std::{env, fs, path::PathBuf};
+
+    use common::web_messages::{Compression, ResponseMessage, Serializable};
+    use crossbeam_channel::Receiver;
+    use wg_2024::{
+        network::SourceRoutingHeader,
+        packet::{Ack, Packet, PacketType},
+    };
+
+    use crate::servers::{
+        requests_handling::FileVersion,
+        serialization::fragment_response,
+        streaming::{FileStream, Layout},
+        test_utils::{get_dummy_builder, get_routed_server},
+        workers::encode_with_policy,
+        CompressionPolicy, FileKind, GenericServer, HistoryEntry, Media,
+    };
+
+    /// writes a file of `len` bytes in a new temporary directory, named after the test
+    fn write_file(test: &str, len: usize) -> (PathBuf, Vec<u8>) {
+        let dir: PathBuf = env::temp_dir().join(format!("streaming_{test}_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let data: Vec<u8> = (0..=u8::MAX).cycle().skip(7).take(len).collect();
+        let path: PathBuf = dir.join("image.png");
+        fs::write(&path, &data).unwrap();
+        (path, data)
+    }
+
+    /// get a [`GenericServer<Media>`] streaming the files of at least 1000 bytes, with a window
+    /// of at most 8 fragments and a route to the client 2. The packets sent to the drone 1 can be read from the returned channel
+    fn get_server() -> (GenericServer<Media>, Receiver<Packet>) {
+        get_routed_server(
+            get_dummy_builder(0)
+                .streaming_threshold(Some(1000))
+                .max_send_window(8)
+                .workers(0),
+            &[2],
+        )
+    }
+
+    /// sends the uncompressed response containing the file at `path` to the client 2
+    fn send_file(server: &mut GenericServer<Media>, path: PathBuf) {
+        server.send_file_response(
+            &SourceRoutingHeader::new(vec![2, 1, 0], 2),
+            2,
+            5,
+            path,
+            FileKind::Media,
+            Compression::None,
+        );
+    }
+
+    /// acknowledges the fragments sent to the drone 1 until none is sent anymore,
+    /// returns them by fragment index and the largest number of fragments in flight
+    fn ack_all(
+        server: &mut GenericServer<Media>,
+        dr: &Receiver<Packet>,
+    ) -> (Vec<[u8; 128]>, usize) {
+        let mut fragments: Vec<(u64, [u8; 128])> = Vec::new();
+        let mut max_in_flight: usize = 0;
+        loop {
+            max_in_flight = max_in_flight.max(server.sent_history.len());
+            let sent: Vec<Packet> = dr.try_iter().collect();
+            if sent.is_empty() {
+                break;
+            }
+            for p in sent {
+                if let PacketType::MsgFragment(f) = p.pack_type {
+                    fragments.push((f.fragment_index, f.data));
+                    server.handle_ack(
+                        p.session_id,
+                        &Ack {
+                            fragment_index: f.fragment_index,
+                        },
+                    );
+                }
+            }
+        }
+        fragments.sort_by_key(|f| f.0);
+        (fragments.into_iter().map(|f| f.1).collect(), max_in_flight)
+    }
+
+    /// tests that the streamed fragments are the same of the encoded response, with
+    /// both policies
+    #[test]
+    fn test_stream_matches_encoding() {
+        for policy in [CompressionPolicy::Requested, CompressionPolicy::Auto] {
+            for len in [0, 1, 127, 128, 5000] {
+                let (path, data) = write_file(&format!("match{len}"), len);
+                let version: FileVersion = FileVersion::of(&path).unwrap();
+                let layout: Layout = Layout::new(0, FileKind::Media, policy, "test").unwrap();
+                let mut stream: FileStream = FileStream::open(
+                    path.clone(),
+                    FileKind::Media,
+                    Compression::None,
+                    version,
+                    &layout,
+                )
+                .unwrap();
+                stream.hdrs = vec![SourceRoutingHeader::new(vec![0, 1, 2], 1)];
+                let mut streamed: Vec<[u8; 128]> = Vec::new();
+                while let Some(fragment) = stream.next_fragment(2, true) {
+                    let (_, entry) = fragment.unwrap();
+                    assert!(entry.frag_idx == streamed.len() as u64);
+                    assert!(entry.n_frags == stream.n_frags());
+                    streamed.push(entry.frag);
+                }
+
+                let resp: ResponseMessage =
+                    ResponseMessage::new_media_response(0, Compression::None, data);
+                let (encoded, _) = encode_with_policy(resp, "test", policy).unwrap();
+                assert_eq!(streamed, fragment_response(encoded));
+                fs::remove_dir_all(path.parent().unwrap()).unwrap();
+            }
+        }
+    }
+
+    /// tests that a large file is sent without reading it at once, keeping only the
+    /// window of the response in memory
+    #[test]
+    fn test_streamed_response() {
+        let (path, data) = write_file("response", 20_000);
+        let (mut server, dr) = get_server();
+        send_file(&mut server, path.clone());
+        assert!(server.send_windows.get(&(2, 5)).unwrap().stream.is_some());
+        // the layout is computed once
+        assert!(server.stream_layouts.len() == 1);
+        assert!(server.stream_layouts.contains_key(&FileKind::Media));
+
+        let (fragments, max_in_flight) = ack_all(&mut server, &dr);
+        assert!(max_in_flight <= 8);
+        assert!(fragments.len() > 150);
+        assert!(server.send_windows.is_empty());
+        assert!(server.sent_history.is_empty());
+        let resp: ResponseMessage =
+            ResponseMessage::deserialize(fragments.into_flattened()).unwrap();
+        assert!(resp == ResponseMessage::new_media_response(0, Compression::None, data));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    /// tests that the small files and the compressed responses are not streamed
+    #[test]
+    fn test_not_streamed() {
+        let (path, _) = write_file("small", 999);
+        let (mut server, _dr) = get_server();
+        send_file(&mut server, path.clone());
+        assert!(server.send_windows.get(&(2, 5)).unwrap().stream.is_none());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        let (path, _) = write_file("compressed", 5000);
+        let version: FileVersion = FileVersion::of(&path).unwrap();
+        assert!(server.should_stream(version, &Compression::None));
+        assert!(!server.should_stream(version, &Compression::LZW));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    /// tests that with the [`CompressionPolicy::Auto`] policy the large files requested with
+    /// a compression are not streamed, since the client decodes them with that compression
+    #[test]
+    fn test_auto_streamed() {
+        let (image, _) = write_file("auto", 5000);
+        let (mut server, _dr): (GenericServer<Media>, _) = get_routed_server(
+            get_dummy_builder(0)
+                .streaming_threshold(Some(1000))
+                .compression_policy(CompressionPolicy::Auto)
+                .workers(0),
+            &[2],
+        );
+        let version: FileVersion = FileVersion::of(&image).unwrap();
+        assert!(!server.should_stream(version, &Compression::LZW));
+        assert!(server.should_stream(version, &Compression::None));
+
+        for (rid, compression) in [(5, Compression::None), (6, Compression::Huffman)] {
+            server.send_file_response(
+                &SourceRoutingHeader::new(vec![2, 1, 0], 2),
+                2,
+                rid,
+                image.clone(),
+                FileKind::Media,
+                compression,
+            );
+        }
+        assert!(server.send_windows.get(&(2, 5)).unwrap().stream.is_some());
+        assert!(server.send_windows.get(&(2, 6)).unwrap().stream.is_none());
+        fs::remove_dir_all(image.parent().unwrap()).unwrap();
+    }
+
+    /// tests that the request is answered again, with the same rid, if the file changes
+    /// while it is streamed, and that only the changes of the open file are detected
+    #[test]
+    fn test_file_changed() {
+        let (path, _) = write_file("changed", 20_000);
+        let (mut server, dr) = get_server();
+        send_file(&mut server, path.clone());
+        let first: Vec<Packet> = dr.try_iter().collect();
+        assert!(first.len() == 8);
+        fs::write(&path, b"shorter").unwrap();
+
+        // the next fragment cannot be read, the new version of the file is sent and
+        // the fragments of the old one still in flight are forgotten
+        let PacketType::MsgFragment(f) = &first[0].pack_type else {
+            panic!("expected a fragment");
+        };
+        server.handle_ack(
+            first[0].session_id,
+            &Ack {
+                fragment_index: f.fragment_index,
+            },
+        );
+        assert!(server.sent_history.len() == 1);
+        assert!(server
+            .sent_history
+            .values()
+            .all(|e: &HistoryEntry| e.n_frags == 1));
+        let (fragments, _) = ack_all(&mut server, &dr);
+        let resp: ResponseMessage =
+            ResponseMessage::deserialize(fragments.into_flattened()).unwrap();
+        assert!(
+            resp == ResponseMessage::new_media_response(0, Compression::None, b"shorter".to_vec())
+        );
+        assert!(server.send_windows.is_empty());
+        assert!(server.sent_history.is_empty());
+
+        // a removed file is still read through the open handle
+        let (path, data) = write_file("removed", 20_000);
+        send_file(&mut server, path.clone());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        let (fragments, _) = ack_all(&mut server, &dr);
+        let resp: ResponseMessage =
+            ResponseMessage::deserialize(fragments.into_flattened()).unwrap();
+        assert!(resp == ResponseMessage::new_media_response(0, Compression::None, data));
+
+        // the file cannot be read and it was removed
+        let (path, _) = write_file("failed", 20_000);
+        let version: FileVersion = FileVersion::of(&path).unwrap();
+        let stream: FileStream = server
+            .open_stream(path.clone(), FileKind::Media, Compression::None, version)
+            .unwrap();
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        server.stream_failed(2, 5, stream);
+        let resent: Vec<Packet> = dr.try_iter().collect();
+        assert!(resent.iter().all(|p: &Packet| p.session_id & 0xFFFF == 5));
+        let PacketType::MsgFragment(f) = &resent[0].pack_type else {
+            panic!("expected a fragment");
+        };
+        assert!(resent.len() == 1 && f.total_n_fragments == 1);
+        let resp: ResponseMessage = ResponseMessage::deserialize(f.data.to_vec()).unwrap();
+        assert!(resp == ResponseMessage::new_not_found_response(0, Compression::None));
+        fs::remove_dir_all(
+            env::temp_dir().join(format!("streaming_changed_{}", std::process::id())),
+        )
+        .unwrap();
+    }
+}
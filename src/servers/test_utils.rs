@@ -1,8 +1,20 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-use common::Server;
+use common::{
+    web_messages::{Compression, RequestMessage, ResponseMessage, Serializable},
+    Server,
+};
+use crossbeam_channel::{Receiver, Sender};
+use wg_2024::{
+    network::{NodeId, SourceRoutingHeader},
+    packet::{Ack, Fragment, Packet, PacketType},
+};
 
-use super::{GenericServer, Media, Text};
+use super::{
+    default_estimator, routing::RoutingTable, serialization::fragment_response, workers,
+    GenericServer, GenericServerBuilder, Media, NetworkGraph, RequestHandler, ServerType, Text,
+    INITIAL_PDR,
+};
 
 use itertools::Itertools;
 use petgraph::prelude::GraphMap;
@@ -48,3 +60,122 @@ pub(super) fn get_dummy_server_media() -> GenericServer<Media> {
         GenericServer::new(0, ctrl_send, ctrl_recv, server_recv, HashMap::new());
     server
 }
+
+/// get a [`GenericServerBuilder`] with dummy channels
+#[must_use]
+pub(super) fn get_dummy_builder<T: ServerType>(id: NodeId) -> GenericServerBuilder<T> {
+    get_neighbors_builder(id, &[]).0
+}
+
+/// get a [`GenericServerBuilder`] with dummy channels and a channel to each of the
+/// `neighbors`, the packets sent to them can be read from the returned receivers
+#[must_use]
+pub(super) fn get_neighbors_builder<T: ServerType>(
+    id: NodeId,
+    neighbors: &[NodeId],
+) -> (GenericServerBuilder<T>, Vec<Receiver<Packet>>) {
+    let (ctrl_send, _) = crossbeam_channel::unbounded();
+    let (_, ctrl_recv) = crossbeam_channel::unbounded();
+    let (_, server_recv) = crossbeam_channel::unbounded();
+    let (packet_send, receivers): (HashMap<NodeId, Sender<Packet>>, Vec<Receiver<Packet>>) =
+        neighbors
+            .iter()
+            .map(|&n: &NodeId| {
+                let (ds, dr) = crossbeam_channel::unbounded();
+                ((n, ds), dr)
+            })
+            .unzip();
+    (
+        GenericServerBuilder::new(id, ctrl_send, ctrl_recv, server_recv, packet_send),
+        receivers,
+    )
+}
+
+/// builds the server 0 with a route to each of the `clients` through the drone 1,
+/// the packets sent to the drone 1 can be read from the returned channel
+#[must_use]
+pub(super) fn get_routed_server<T: ServerType>(
+    builder: GenericServerBuilder<T>,
+    clients: &[NodeId],
+) -> (GenericServer<T>, Receiver<Packet>) {
+    let mut server: GenericServer<T> = builder.build();
+    server.network_graph = RoutingTable::new_with_graph(
+        NetworkGraph::from_edges(
+            std::iter::once((0, 1, INITIAL_PDR))
+                .chain(clients.iter().map(|&c: &NodeId| (1, c, INITIAL_PDR))),
+        ),
+        default_estimator(),
+    );
+    let (ds, dr) = crossbeam_channel::unbounded();
+    server.packet_send.insert(1, ds);
+    (server, dr)
+}
+
+/// decompresses the data of a response, as done by the clients
+#[must_use]
+pub(super) fn decompress(data: Vec<u8>, compression: &Compression) -> Vec<u8> {
+    workers::decompress(data, compression).unwrap()
+}
+
+/// delivers the request of the client 2 to a server built by [`get_routed_server`] and
+/// returns the data of its response, still compressed. Every fragment is acknowledged,
+/// so that the whole response is sent and then forgotten by the server
+pub(super) fn request_data<T: ServerType>(
+    server: &mut GenericServer<T>,
+    dr: &Receiver<Packet>,
+    request: &RequestMessage,
+) -> Vec<u8>
+where
+    GenericServer<T>: RequestHandler,
+{
+    let data: Vec<[u8; 128]> = fragment_response(request.serialize().unwrap());
+    let total: u64 = u64::try_from(data.len()).unwrap();
+    for (i, frag) in (0..).zip(data) {
+        server.handle_fragment(
+            &SourceRoutingHeader::new(vec![2, 1, 0], 2),
+            0,
+            &Fragment {
+                fragment_index: i,
+                total_n_fragments: total,
+                length: 128,
+                data: frag,
+            },
+        );
+    }
+    server.wait_workers();
+    let mut fragments: BTreeMap<u64, [u8; 128]> = BTreeMap::new();
+    loop {
+        let sent: Vec<Packet> = dr.try_iter().collect();
+        if sent.is_empty() {
+            break;
+        }
+        for p in sent {
+            if let PacketType::MsgFragment(f) = p.pack_type {
+                fragments.insert(f.fragment_index, f.data);
+                server.handle_ack(
+                    p.session_id,
+                    &Ack {
+                        fragment_index: f.fragment_index,
+                    },
+                );
+            }
+        }
+    }
+    assert!(server.send_windows.is_empty(), "the response was not sent");
+    assert!(server.sent_history.is_empty());
+    fragments.into_values().collect::<Vec<_>>().into_flattened()
+}
+
+/// like [`request_data`], but returns the response decompressed with the compression
+/// requested
+pub(super) fn request_response<T: ServerType>(
+    server: &mut GenericServer<T>,
+    dr: &Receiver<Packet>,
+    request: &RequestMessage,
+) -> ResponseMessage
+where
+    GenericServer<T>: RequestHandler,
+{
+    let data: Vec<u8> = request_data(server, dr, request);
+    ResponseMessage::deserialize(decompress(data, &request.compression_type)).unwrap()
+}
@@ -0,0 +1,123 @@
+use std::fmt::Write;
+
+use wg_2024::network::NodeId;
+
+/// testing module
+#[cfg(test)]
+mod test;
+
+/// Node of a [`TopologySnapshot`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeSnapshot {
+    /// id of the node
+    pub id: NodeId,
+    /// estimated pdr of the drone, [None] if the node is a client or a server
+    pub pdr: Option<f64>,
+    /// ETX of the drone, i.e. the weight of the edges going into it,
+    /// [None] if the node is a client or a server
+    pub etx: Option<f64>,
+}
+
+/// Directed edge of a [`TopologySnapshot`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct EdgeSnapshot {
+    /// source of the edge
+    pub from: NodeId,
+    /// destination of the edge
+    pub to: NodeId,
+    /// weight used by the routing algorithm
+    pub weight: f64,
+}
+
+/// Network topology as seen by a [`super::GenericServer`], with the current
+/// estimations of the drones. Nodes and edges are sorted by id
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopologySnapshot {
+    /// id of the server that took the snapshot
+    pub server_id: NodeId,
+    /// known nodes of the network
+    pub nodes: Vec<NodeSnapshot>,
+    /// known edges of the network
+    pub edges: Vec<EdgeSnapshot>,
+}
+
+/// formats a number as a JSON value, non finite numbers are not valid JSON
+/// so they become null
+fn json_number(n: f64) -> String {
+    if n.is_finite() {
+        format!("{n}")
+    } else {
+        "null".to_string()
+    }
+}
+
+/// formats an optional number as a JSON value
+fn json_option(n: Option<f64>) -> String {
+    n.map_or_else(|| "null".to_string(), json_number)
+}
+
+impl TopologySnapshot {
+    /// exports the snapshot in the DOT format, drones are drawn as circles labelled
+    /// with their pdr and ETX while clients and servers are drawn as boxes
+    #[must_use]
+    pub fn to_dot(&self) -> String {
+        let mut dot: String = format!("digraph \"Server[{}]\" {{\n", self.server_id);
+        for node in &self.nodes {
+            let _ = match (node.pdr, node.etx) {
+                (Some(pdr), Some(etx)) => writeln!(
+                    dot,
+                    "    {} [shape=circle, label=\"{}\\npdr={pdr:.3}\\netx={etx:.3}\"];",
+                    node.id, node.id
+                ),
+                _ => writeln!(dot, "    {} [shape=box, label=\"{}\"];", node.id, node.id),
+            };
+        }
+        for edge in &self.edges {
+            let _ = writeln!(
+                dot,
+                "    {} -> {} [label=\"{:.3}\"];",
+                edge.from, edge.to, edge.weight
+            );
+        }
+        dot.push('}');
+        dot.push('\n');
+        dot
+    }
+
+    /// exports the snapshot as a JSON object with the fields `server_id`, `nodes`
+    /// (`id`, `pdr`, `etx`) and `edges` (`from`, `to`, `weight`). Infinite values,
+    /// i.e. drones considered lost, and the values of clients and servers are null
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let nodes: Vec<String> = self
+            .nodes
+            .iter()
+            .map(|n: &NodeSnapshot| {
+                format!(
+                    "{{\"id\":{},\"pdr\":{},\"etx\":{}}}",
+                    n.id,
+                    json_option(n.pdr),
+                    json_option(n.etx)
+                )
+            })
+            .collect();
+        let edges: Vec<String> = self
+            .edges
+            .iter()
+            .map(|e: &EdgeSnapshot| {
+                format!(
+                    "{{\"from\":{},\"to\":{},\"weight\":{}}}",
+                    e.from,
+                    e.to,
+                    json_number(e.weight)
+                )
+            })
+            .collect();
+        format!(
+            "{{\"server_id\":{},\"nodes\":[{}],\"edges\":[{}]}}",
+            self.server_id,
+            nodes.join(","),
+            edges.join(",")
+        )
+    }
+}
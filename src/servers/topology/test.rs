@@ -0,0 +1,127 @@
+#[cfg(test)]
+mod topology_tests {
+    use crate::servers::{
+        extensions::{ExtendedCommand, ExtendedEvent},
+        routing::RoutingTable,
+        test_utils::get_dummy_builder,
+        EdgeSnapshot, GenericServer, NodeSnapshot, Text, TopologySnapshot, INITIAL_ETX,
+        INITIAL_PDR,
+    };
+
+    /// get a [`GenericServer<Text>`] knowing the route 0 -> 1 -> 2, where 2 is a client
+    fn get_server() -> GenericServer<Text> {
+        let mut server: GenericServer<Text> = get_dummy_builder(0).window_size(1).build();
+        server.network_graph.add_endpoint(2);
+        server.check_and_add_edge(0, 1);
+        server.check_and_add_edge(1, 2);
+        server.check_and_add_edge(2, 1);
+        server
+    }
+
+    /// get the [`TopologySnapshot`] of the server built by `get_server`
+    fn get_snapshot() -> TopologySnapshot {
+        TopologySnapshot {
+            server_id: 0,
+            nodes: vec![
+                NodeSnapshot {
+                    id: 0,
+                    pdr: None,
+                    etx: None,
+                },
+                NodeSnapshot {
+                    id: 1,
+                    pdr: Some(INITIAL_PDR),
+                    etx: Some(INITIAL_ETX),
+                },
+                NodeSnapshot {
+                    id: 2,
+                    pdr: None,
+                    etx: None,
+                },
+            ],
+            edges: vec![
+                EdgeSnapshot {
+                    from: 0,
+                    to: 1,
+                    weight: INITIAL_ETX,
+                },
+                EdgeSnapshot {
+                    from: 1,
+                    to: 2,
+                    weight: RoutingTable::ENDPOINT_ETX,
+                },
+                EdgeSnapshot {
+                    from: 2,
+                    to: 1,
+                    weight: INITIAL_ETX,
+                },
+            ],
+        }
+    }
+
+    /// tests the content of the snapshot
+    #[test]
+    fn test_snapshot() {
+        let mut server: GenericServer<Text> = get_server();
+        assert_eq!(server.topology_snapshot(), get_snapshot());
+        server.update_pdr_from_ack(&[0, 1, 2]);
+        let snapshot: TopologySnapshot = server.topology_snapshot();
+        let drone: &NodeSnapshot = &snapshot.nodes[1];
+        assert!(drone.pdr.unwrap() > INITIAL_PDR);
+        assert_eq!(drone.etx, drone.pdr.map(|pdr: f64| 1. / pdr));
+        assert_eq!(Some(snapshot.edges[0].weight), drone.etx);
+    }
+
+    /// tests the DOT export
+    #[test]
+    fn test_to_dot() {
+        assert_eq!(
+            get_snapshot().to_dot(),
+            "digraph \"Server[0]\" {\n\
+            \x20   0 [shape=box, label=\"0\"];\n\
+            \x20   1 [shape=circle, label=\"1\\npdr=0.500\\netx=2.000\"];\n\
+            \x20   2 [shape=box, label=\"2\"];\n\
+            \x20   0 -> 1 [label=\"2.000\"];\n\
+            \x20   1 -> 2 [label=\"1.000\"];\n\
+            \x20   2 -> 1 [label=\"2.000\"];\n\
+            }\n"
+        );
+    }
+
+    /// tests the JSON export, infinite values are not valid JSON
+    #[test]
+    fn test_to_json() {
+        let mut snapshot: TopologySnapshot = get_snapshot();
+        snapshot.nodes[1].pdr = Some(0.);
+        snapshot.nodes[1].etx = Some(f64::INFINITY);
+        snapshot.edges[0].weight = f64::INFINITY;
+        snapshot.edges.truncate(2);
+        assert_eq!(
+            snapshot.to_json(),
+            "{\"server_id\":0,\"nodes\":[\
+            {\"id\":0,\"pdr\":null,\"etx\":null},\
+            {\"id\":1,\"pdr\":0,\"etx\":null},\
+            {\"id\":2,\"pdr\":null,\"etx\":null}],\"edges\":[\
+            {\"from\":0,\"to\":1,\"weight\":null},\
+            {\"from\":1,\"to\":2,\"weight\":1}]}"
+        );
+    }
+
+    /// tests that the snapshot command is answered with an [`ExtendedEvent::Topology`]
+    #[test]
+    fn test_snapshot_command() {
+        let (ext_send, ext_recv) = crossbeam_channel::unbounded();
+        let (_, cmd_recv) = crossbeam_channel::unbounded();
+        let mut server: GenericServer<Text> = get_dummy_builder(0)
+            .extended_events(ext_send)
+            .extended_commands(cmd_recv)
+            .build();
+        server.check_and_add_edge(0, 1);
+        server.handle_extended_command(&ExtendedCommand::TopologySnapshot);
+        assert_eq!(
+            ext_recv.try_recv().unwrap(),
+            ExtendedEvent::Topology(server.topology_snapshot())
+        );
+        assert!(ext_recv.try_recv().is_err());
+    }
+}
@@ -0,0 +1,599 @@
+use std::{
+    collections::BTreeMap,
+    fs, mem,
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    sync::{MutexGuard, PoisonError},
+    thread::{self, JoinHandle},
+};
+
+use common::web_messages::{
+    Compression, Request, RequestMessage, ResponseMessage, Serializable, SerializableSerde,
+};
+use compression::{
+    bypass::BypassCompressor, huffman::HuffmanCompressor, lzw::LZWCompressor, Compressor,
+};
+use crossbeam_channel::{never, Receiver, Sender};
+use log::{error, info, warn};
+use wg_2024::{
+    network::{NodeId, SourceRoutingHeader},
+    packet::FRAGMENT_DSIZE,
+};
+
+use super::{
+    auto_compression::{encode_auto, CompressionPolicy},
+    extensions::RequestError,
+    metrics,
+    ranges::{range_response, ByteRange},
+    requests_handling::{CacheKey, FileVersion},
+    serialization::fragment_response,
+    services::{FileKind, Reply, RequestService, SharedService},
+    streaming::FileStream,
+    GenericServer, ServerType,
+};
+
+/// testing module
+#[cfg(test)]
+mod test;
+
+/// default number of threads encoding the responses
+pub(crate) const DEFAULT_WORKERS: usize = 2;
+
+/// fragments of an encoded response
+type Fragments = Vec<[u8; FRAGMENT_DSIZE]>;
+
+/// Client and request id a response is sent to, kept with the work needed to produce it
+#[derive(Debug, Clone)]
+pub(super) struct ResponseTarget {
+    /// routing header of the request
+    pub(super) srch: SourceRoutingHeader,
+    /// id of the client
+    pub(super) src_id: NodeId,
+    /// request id, the response uses the same one
+    pub(super) rid: u16,
+}
+
+/// Work needed to produce the fragments of a response
+#[derive(Debug)]
+pub(super) enum Work {
+    /// the fragments are already available, e.g. taken from the content cache
+    Ready(Fragments),
+    /// the response is streamed, its fragments are read while it is sent
+    Stream(FileStream),
+    /// a range of the file must be read and sent with a response of the given kind
+    Range {
+        /// id of the server, sender of the response
+        server_id: NodeId,
+        /// path of the file
+        path: PathBuf,
+        /// kind of the response
+        kind: FileKind,
+        /// compression requested by the client
+        compression: Compression,
+        /// requested range
+        range: ByteRange,
+    },
+    /// the response must be serialized, compressed and fragmented
+    Encode(ResponseMessage),
+    /// the request must be answered by the service, a range of the requested file
+    /// is sent if given
+    Request {
+        /// id of the server, sender of the response
+        server_id: NodeId,
+        /// request to answer
+        request: RequestMessage,
+        /// requested range of the file, if any
+        range: Option<ByteRange>,
+    },
+    /// the file must be read and sent with a response of the given kind,
+    /// the encoded response is cached if the version of the file is known
+    File {
+        /// id of the server, sender of the response
+        server_id: NodeId,
+        /// key of the response in the content cache, contains the path of the file
+        key: CacheKey,
+        /// version of the file when the request was handled
+        version: Option<FileVersion>,
+        /// kind of the response
+        kind: FileKind,
+        /// compression requested by the client
+        compression: Compression,
+    },
+}
+
+/// File a request was answered with by the service, the work needed to send it
+/// is decided by the server
+#[derive(Debug)]
+pub(super) struct FileReply {
+    /// path of the file
+    pub(super) path: PathBuf,
+    /// kind of the response
+    pub(super) kind: FileKind,
+    /// version of the file when the request was answered, [None] if it cannot be read
+    pub(super) version: Option<FileVersion>,
+    /// compression requested by the client
+    pub(super) compression: Compression,
+    /// requested range of the file, if any
+    pub(super) range: Option<ByteRange>,
+}
+
+/// Work submitted to the pool, numbered in submission order
+#[derive(Debug)]
+struct Job {
+    /// submission number
+    seq: u64,
+    /// destination of the response
+    target: ResponseTarget,
+    /// work to do
+    work: Work,
+}
+
+/// Response produced by a [`Job`]
+#[derive(Debug)]
+pub(super) struct Encoded {
+    /// submission number of the job
+    seq: u64,
+    /// destination of the response
+    target: ResponseTarget,
+    /// fragments of the response, [None] if it could not be encoded
+    fragments: Option<Fragments>,
+    /// size of the serialized response before and after the compression, if it was encoded
+    sizes: Option<(usize, usize)>,
+    /// type of the file contained in the response, if any
+    file_type: Option<String>,
+    /// key and version under which the fragments are cached, if any
+    cache: Option<(CacheKey, FileVersion)>,
+    /// streamed response, sent instead of the fragments
+    stream: Option<FileStream>,
+    /// error of the request found while doing the work, counted when the response is sent
+    error: Option<RequestError>,
+    /// file the request was answered with, sent instead of the fragments
+    file: Option<FileReply>,
+}
+
+impl Encoded {
+    /// result of a job that produced nothing to send
+    fn empty(seq: u64, target: ResponseTarget) -> Self {
+        Self {
+            seq,
+            target,
+            fragments: None,
+            sizes: None,
+            file_type: None,
+            cache: None,
+            stream: None,
+            error: None,
+            file: None,
+        }
+    }
+}
+
+/// What the jobs need to produce the responses, shared by the workers
+#[derive(Clone)]
+struct JobContext {
+    /// target topic of the server, used in logs
+    target_topic: String,
+    /// policy used to choose the compression of the responses
+    policy: CompressionPolicy,
+    /// service answering the requests
+    service: SharedService,
+}
+
+/// Answer to a request prepared by a worker
+enum Answer {
+    /// response to send, with the error of the request to count if any
+    Response(ResponseMessage, Option<RequestError>),
+    /// file to send, the work needed to send it is decided by the server
+    File(FileReply),
+}
+
+impl JobContext {
+    /// locks the service to answer a request. A service that panicked is still used,
+    /// the panic only gave up the response it was answering
+    fn service(&self) -> MutexGuard<'_, Box<dyn RequestService>> {
+        self.service.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// answers the request of the client with the service
+    fn answer(
+        &self,
+        target: &ResponseTarget,
+        server_id: NodeId,
+        request: &RequestMessage,
+        range: Option<ByteRange>,
+    ) -> Answer {
+        let compression: Compression = request.compression_type.clone();
+        if let Request::Type = request.content {
+            let server_type = self.service().server_type();
+            let resp: ResponseMessage =
+                ResponseMessage::new_type_response(server_id, compression, server_type);
+            return Answer::Response(resp, None);
+        }
+        let reply: Reply = self.service().handle(server_id, request);
+        match reply {
+            Reply::Response(resp) => Answer::Response(resp, None),
+            Reply::File { path, kind } => Answer::File(FileReply {
+                version: FileVersion::of(&path),
+                path,
+                kind,
+                compression,
+                range,
+            }),
+            Reply::NotFound => Answer::Response(
+                ResponseMessage::new_not_found_response(server_id, compression),
+                None,
+            ),
+            Reply::Forbidden => {
+                warn!(target: &self.target_topic, "Refused request {} from {}: outside of the content root", target.rid, target.src_id);
+                Answer::Response(
+                    ResponseMessage::new_not_found_response(server_id, compression),
+                    Some(RequestError::Forbidden),
+                )
+            }
+            Reply::Invalid => {
+                warn!(target: &self.target_topic, "Received invalid request {} from {}", target.rid, target.src_id);
+                Answer::Response(
+                    ResponseMessage::new_invalid_request_response(server_id, compression),
+                    Some(RequestError::Invalid),
+                )
+            }
+        }
+    }
+}
+
+/// compresses the data based on the requested type
+pub(super) fn compress(data: Vec<u8>, comp: &Compression) -> Result<Vec<u8>, String> {
+    match comp {
+        Compression::Huffman => HuffmanCompressor::new()
+            .compress(data)?
+            .serialize()
+            .map_err(|_| "Error during compression".to_string()),
+        Compression::LZW => Serializable::serialize(&LZWCompressor::new().compress(data)?)
+            .map_err(|_| "Error during compression".to_string()),
+        Compression::None => BypassCompressor::new().compress(data),
+    }
+}
+
+/// decompresses the data compressed by [`compress`] with the given type
+pub(super) fn decompress(data: Vec<u8>, comp: &Compression) -> Result<Vec<u8>, String> {
+    match comp {
+        Compression::Huffman => HuffmanCompressor::new().decompress(
+            SerializableSerde::deserialize(data)
+                .map_err(|_| "Error during decompression".to_string())?,
+        ),
+        Compression::LZW => LZWCompressor::new().decompress(
+            Serializable::deserialize(data)
+                .map_err(|_| "Error during decompression".to_string())?,
+        ),
+        Compression::None => BypassCompressor::new().decompress(data),
+    }
+}
+
+/// serializes and compresses a response, [None] if any step fails.
+/// Returns also the size of the response before the compression
+pub(super) fn encode_bytes(resp: &ResponseMessage, target_topic: &str) -> Option<(Vec<u8>, usize)> {
+    let Ok(data) = resp.serialize() else {
+        error!(target: target_topic, "Cannot serialize response {resp:?}, dropping response");
+        return None;
+    };
+    info!(target: target_topic, "Serialized response");
+    let uncompressed: usize = data.len();
+    if let Ok(compressed) = compress(data, &resp.compression_type) {
+        info!(target: target_topic, "Compressed data");
+        Some((compressed, uncompressed))
+    } else {
+        error!(target: target_topic, "CRITICAL: Error during serialization of reponse, dropping response");
+        None
+    }
+}
+
+/// serializes and compresses a response following the policy, [None] if any step fails.
+/// Returns also the size of the response before the compression
+pub(super) fn encode_with_policy(
+    resp: ResponseMessage,
+    target_topic: &str,
+    policy: CompressionPolicy,
+) -> Option<(Vec<u8>, usize)> {
+    match policy {
+        CompressionPolicy::Requested => encode_bytes(&resp, target_topic),
+        CompressionPolicy::Auto => encode_auto(resp, target_topic),
+    }
+}
+
+/// serializes, compresses following the policy and fragments a response, [None] if any
+/// step fails. Returns also the size of the response before and after the compression
+fn encode(
+    resp: ResponseMessage,
+    target_topic: &str,
+    policy: CompressionPolicy,
+) -> Option<(Fragments, (usize, usize))> {
+    let (compressed, uncompressed) = encode_with_policy(resp, target_topic, policy)?;
+    let sizes: (usize, usize) = (uncompressed, compressed.len());
+    Some((fragment_response(compressed), sizes))
+}
+
+impl Job {
+    /// does the work of the job like [`Job::run`], but if the work panics the
+    /// response is given up instead of being lost with its submission number,
+    /// which would stall the following responses
+    fn run_guarded(self, context: &JobContext) -> Encoded {
+        let (seq, target): (u64, ResponseTarget) = (self.seq, self.target.clone());
+        panic::catch_unwind(AssertUnwindSafe(|| self.run(context))).unwrap_or_else(|_| {
+            error!(target: &context.target_topic, "CRITICAL: the encoding of response {} panicked, dropping response", target.rid);
+            Encoded::empty(seq, target)
+        })
+    }
+
+    /// does the work of the job, compressing the response following the policy
+    fn run(self, context: &JobContext) -> Encoded {
+        let Job { seq, target, work } = self;
+        let (target_topic, policy): (&str, CompressionPolicy) =
+            (&context.target_topic, context.policy);
+        let mut cache: Option<(CacheKey, FileVersion)> = None;
+        let mut file_type: Option<String> = None;
+        let mut error: Option<RequestError> = None;
+        let encoded = match work {
+            Work::Ready(fragments) => {
+                return Encoded {
+                    fragments: Some(fragments),
+                    ..Encoded::empty(seq, target)
+                };
+            }
+            Work::Stream(stream) => {
+                return Encoded {
+                    stream: Some(stream),
+                    ..Encoded::empty(seq, target)
+                };
+            }
+            Work::Encode(resp) => encode(resp, target_topic, policy),
+            Work::Request {
+                server_id,
+                request,
+                range,
+            } => match context.answer(&target, server_id, &request, range) {
+                Answer::Response(resp, request_error) => {
+                    error = request_error;
+                    encode(resp, target_topic, policy)
+                }
+                Answer::File(file) => {
+                    return Encoded {
+                        file: Some(file),
+                        ..Encoded::empty(seq, target)
+                    };
+                }
+            },
+            Work::File {
+                server_id,
+                key,
+                version,
+                kind,
+                compression,
+            } => {
+                let resp: ResponseMessage = match fs::read(key.path()) {
+                    Ok(data) => {
+                        file_type = Some(metrics::file_type(key.path()));
+                        cache = version.map(|v: FileVersion| (key, v));
+                        kind.response(server_id, compression, data)
+                    }
+                    Err(_) => ResponseMessage::new_not_found_response(server_id, compression),
+                };
+                encode(resp, target_topic, policy)
+            }
+            Work::Range {
+                server_id,
+                path,
+                kind,
+                compression,
+                range,
+            } => {
+                file_type = Some(metrics::file_type(&path));
+                let (resp, range_error) =
+                    range_response(server_id, &path, kind, compression, range);
+                error = range_error;
+                encode(resp, target_topic, policy)
+            }
+        };
+        let (fragments, sizes) = encoded.unzip();
+        Encoded {
+            cache: cache.filter(|_| fragments.is_some()),
+            fragments,
+            sizes,
+            file_type,
+            error,
+            ..Encoded::empty(seq, target)
+        }
+    }
+}
+
+/// Pool of threads that answer the requests and serialize, compress and fragment the
+/// responses, so that the main loop keeps handling packets meanwhile. The responses
+/// are sent in the order in which they were submitted
+pub(super) struct WorkerPool {
+    /// channel to submit the jobs, [None] if the responses are encoded on the main loop
+    jobs: Option<Sender<Job>>,
+    /// channel receiving the finished jobs
+    pub(super) results: Receiver<Encoded>,
+    /// threads of the pool, joined when the server stops
+    threads: Vec<JoinHandle<()>>,
+    /// what the jobs run on the main loop need
+    context: JobContext,
+    /// submission number of the next job
+    next_seq: u64,
+    /// submission number of the next response to send
+    next_release: u64,
+    /// finished jobs waiting for the ones submitted before them
+    ready: BTreeMap<u64, Encoded>,
+}
+
+impl WorkerPool {
+    /// starts a pool of `n` threads answering the requests with the service and compressing
+    /// the responses following the policy, with 0 threads the work is done on the main loop
+    pub(super) fn new(
+        n: usize,
+        target_topic: &str,
+        policy: CompressionPolicy,
+        service: SharedService,
+    ) -> Self {
+        let mut pool: Self = Self {
+            jobs: None,
+            results: never(),
+            threads: Vec::new(),
+            context: JobContext {
+                target_topic: target_topic.to_string(),
+                policy,
+                service,
+            },
+            next_seq: 0,
+            next_release: 0,
+            ready: BTreeMap::new(),
+        };
+        if n == 0 {
+            return pool;
+        }
+        let (job_send, job_recv) = crossbeam_channel::unbounded::<Job>();
+        let (result_send, result_recv) = crossbeam_channel::unbounded();
+        for _ in 0..n {
+            let jobs: Receiver<Job> = job_recv.clone();
+            let results: Sender<Encoded> = result_send.clone();
+            let context: JobContext = pool.context.clone();
+            pool.threads.push(thread::spawn(move || {
+                for job in jobs {
+                    if results.send(job.run_guarded(&context)).is_err() {
+                        break;
+                    }
+                }
+            }));
+        }
+        pool.jobs = Some(job_send);
+        pool.results = result_recv;
+        pool
+    }
+
+    /// checks if every submitted response was sent
+    #[inline]
+    pub(super) fn is_idle(&self) -> bool {
+        self.next_release == self.next_seq
+    }
+
+    /// stops the threads of the pool, waiting for the jobs they already received
+    pub(super) fn join(&mut self) {
+        self.jobs = None;
+        for thread in self.threads.drain(..) {
+            if thread.join().is_err() {
+                error!(target: &self.context.target_topic, "CRITICAL: a worker panicked");
+            }
+        }
+    }
+
+    /// service answering the requests, used by the tests
+    #[cfg(test)]
+    pub(super) fn service(&self) -> MutexGuard<'_, Box<dyn RequestService>> {
+        self.context.service()
+    }
+}
+
+impl<T: ServerType> GenericServer<T> {
+    /// hands the work needed to produce a response to the worker pool. The response is
+    /// sent once it and all the responses submitted before it are ready
+    pub(super) fn submit(&mut self, target: ResponseTarget, work: Work) {
+        let job: Job = Job {
+            seq: self.workers.next_seq,
+            target,
+            work,
+        };
+        self.workers.next_seq += 1;
+        self.dispatch(job);
+    }
+
+    /// hands a job to the pool, the jobs that need no encoding and the ones
+    /// that cannot be handed to the pool are run on the main loop
+    fn dispatch(&mut self, job: Job) {
+        let job: Job = match &self.workers.jobs {
+            Some(jobs) if !matches!(job.work, Work::Ready(_) | Work::Stream(_)) => {
+                match jobs.send(job) {
+                    Ok(()) => return,
+                    Err(e) => e.into_inner(),
+                }
+            }
+            _ => job,
+        };
+        let encoded: Encoded = job.run_guarded(&self.workers.context);
+        self.finish(encoded);
+    }
+
+    /// receives a finished job and sends the responses whose turn came. The file a
+    /// request was answered with is sent by a new job, keeping the turn of the request
+    pub(super) fn finish(&mut self, mut encoded: Encoded) {
+        if let Some(file) = encoded.file.take() {
+            let job: Job = Job {
+                seq: encoded.seq,
+                work: self.file_work(file),
+                target: encoded.target,
+            };
+            self.dispatch(job);
+            return;
+        }
+        self.workers.ready.insert(encoded.seq, encoded);
+        while let Some(encoded) = self.workers.ready.remove(&self.workers.next_release) {
+            self.workers.next_release += 1;
+            self.deliver(encoded);
+        }
+    }
+
+    /// called when every worker stopped: the responses being encoded are lost and
+    /// the following ones are encoded on the main loop
+    pub(super) fn workers_stopped(&mut self) {
+        error!(target: &self.target_topic, "CRITICAL: the workers stopped, encoding the responses on the main loop");
+        self.workers.jobs = None;
+        self.workers.results = never();
+        self.workers.next_release = self.workers.next_seq;
+        for encoded in mem::take(&mut self.workers.ready).into_values() {
+            self.deliver(encoded);
+        }
+    }
+
+    /// sends an encoded response, caching it if required
+    fn deliver(&mut self, encoded: Encoded) {
+        let Encoded {
+            target,
+            fragments,
+            sizes,
+            file_type,
+            cache,
+            stream,
+            error,
+            ..
+        } = encoded;
+        if let Some(error) = error {
+            self.record_error(error);
+        }
+        if let Some(stream) = stream {
+            self.send_streamed(target, stream);
+            return;
+        }
+        if let Some((uncompressed, compressed)) = sizes {
+            self.metrics
+                .count_bytes(file_type.as_deref(), uncompressed, compressed);
+        }
+        let Some(fragments) = fragments else {
+            return;
+        };
+        if let Some((key, version)) = cache {
+            self.content_cache.insert(key, version, fragments.clone());
+        }
+        info!(target: &self.target_topic, "Sending response {} to {}", target.rid, target.src_id);
+        self.send_fragments(&target.srch, target.src_id, target.rid, fragments);
+    }
+
+    /// waits until every submitted response is sent, used by the tests
+    /// that check the sent fragments right after handling a request
+    #[cfg(test)]
+    pub(super) fn wait_workers(&mut self) {
+        while !self.workers.is_idle() {
+            match self.workers.results.recv() {
+                Ok(encoded) => self.finish(encoded),
+                Err(_) => self.workers_stopped(),
+            }
+        }
+    }
+}
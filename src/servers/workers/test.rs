@@ -0,0 +1,170 @@
+#[cfg(test)]
+mod workers_tests {
+    use std::time::Instant;
+
+    use common::{
+        slc_commands::ServerType,
+        web_messages::{Compression, RequestMessage, ResponseMessage},
+    };
+    use crossbeam_channel::Receiver;
+    use itertools::Itertools;
+    use wg_2024::{
+        network::{NodeId, SourceRoutingHeader},
+        packet::{Ack, Packet, PacketType},
+    };
+
+    use crate::servers::{
+        test_utils::{get_dummy_builder, get_routed_server},
+        workers::{ResponseTarget, Work},
+        GenericServer, Reply, RequestService, Text,
+    };
+
+    /// service whose answers fail with a panic
+    struct PanicService;
+
+    impl RequestService for PanicService {
+        fn server_type(&self) -> ServerType {
+            ServerType::FileServer
+        }
+
+        fn handle(&mut self, _server_id: NodeId, _request: &RequestMessage) -> Reply {
+            panic!("the request failed")
+        }
+    }
+
+    /// get a [`GenericServer<Text>`] with `workers` threads and a route to the client 2,
+    /// the packets sent to the drone 1 can be read from the returned channel
+    fn get_server(workers: usize) -> (GenericServer<Text>, Receiver<Packet>) {
+        get_routed_server(get_dummy_builder(0).workers(workers), &[2])
+    }
+
+    /// target of the response `rid` to the client 2
+    fn target(rid: u16) -> ResponseTarget {
+        ResponseTarget {
+            srch: SourceRoutingHeader::new(vec![2, 1, 0], 2),
+            src_id: 2,
+            rid,
+        }
+    }
+
+    /// a small text response
+    fn small_response() -> Work {
+        Work::Encode(ResponseMessage::new_text_response(
+            0,
+            Compression::LZW,
+            b"<p>hello</p>".to_vec(),
+        ))
+    }
+
+    /// fragments sent to the drone 1, with the rid of their response
+    fn sent(dr: &Receiver<Packet>) -> Vec<(u16, u64, u64)> {
+        dr.try_iter()
+            .filter_map(|p: Packet| match p.pack_type {
+                PacketType::MsgFragment(f) => Some((
+                    (p.session_id & 0xFFFF) as u16,
+                    p.session_id,
+                    f.fragment_index,
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// tests that the responses are sent in submission order, even if a later
+    /// one is ready before an earlier one
+    #[test]
+    fn test_submission_order() {
+        let (mut server, dr) = get_server(2);
+        let large: Vec<u8> = (0..100_000u32).map(|i: u32| (i * 7 % 251) as u8).collect();
+        server.submit(
+            target(1),
+            Work::Encode(ResponseMessage::new_media_response(
+                0,
+                Compression::Huffman,
+                large,
+            )),
+        );
+        server.submit(target(2), small_response());
+        server.submit(target(3), Work::Ready(vec![[1; 128]]));
+        // the cached response waits for the ones submitted before it
+        assert!(sent(&dr).is_empty());
+        assert!(!server.workers.is_idle());
+
+        server.wait_workers();
+        let rids: Vec<u16> = sent(&dr).iter().map(|f| f.0).dedup().collect();
+        assert_eq!(rids, vec![1, 2, 3]);
+        assert!(server.metrics().bytes_uncompressed > 100_000);
+    }
+
+    /// tests that without workers the responses are sent right away
+    #[test]
+    fn test_no_workers() {
+        let (mut server, dr) = get_server(0);
+        server.submit(target(1), small_response());
+        assert!(server.workers.is_idle());
+        assert!(!sent(&dr).is_empty());
+    }
+
+    /// tests that a failed job doesn't stall the following responses, with and
+    /// without workers, and that the service is used again after panicking
+    #[test]
+    fn test_failed_job() {
+        for workers in [0, 2] {
+            let (mut server, dr): (GenericServer<Text>, _) = get_routed_server(
+                get_dummy_builder(0).service(PanicService).workers(workers),
+                &[2],
+            );
+            let request = |request: RequestMessage| Work::Request {
+                server_id: 0,
+                request,
+                range: None,
+            };
+            let list = || RequestMessage::new_text_list_request(2, Compression::None);
+            server.submit(target(1), small_response());
+            server.submit(target(2), request(list()));
+            server.submit(target(3), small_response());
+            server.submit(target(4), request(list()));
+            let server_type: RequestMessage =
+                RequestMessage::new_type_request(2, Compression::None);
+            server.submit(target(5), request(server_type));
+            server.wait_workers();
+            assert!(server.workers.is_idle());
+            let rids: Vec<u16> = sent(&dr).iter().map(|f| f.0).dedup().collect();
+            assert_eq!(rids, vec![1, 3, 5]);
+        }
+    }
+
+    /// tests that the workers are joined when the server stops
+    #[test]
+    fn test_join_workers() {
+        let (mut server, _dr) = get_server(2);
+        server.submit(target(1), small_response());
+        server.workers.join();
+        assert!(server.workers.threads.is_empty());
+        // the job submitted before stopping was done, the next ones are done on the main loop
+        server.wait_workers();
+        server.submit(target(2), small_response());
+        assert!(server.workers.is_idle());
+    }
+
+    /// tests that a graceful shutdown waits for the responses being encoded
+    #[test]
+    fn test_shutdown_waits_workers() {
+        let (mut server, dr) = get_server(1);
+        server.submit(target(1), small_response());
+        let now: Instant = Instant::now();
+        server.begin_shutdown(now);
+        assert!(!server.should_stop(now));
+
+        server.wait_workers();
+        for (_, sid, idx) in sent(&dr) {
+            server.handle_ack(
+                sid,
+                &Ack {
+                    fragment_index: idx,
+                },
+            );
+        }
+        assert!(server.should_stop(now));
+    }
+}